@@ -1,14 +1,23 @@
 use std::sync::RwLock;
 
+use crate::connectiontypes::session::SessionManager;
 use crate::data;
 pub struct Application {
     pub persistent_data: RwLock<data::PersistentData>,
+    pub sessions: SessionManager,
+}
+
+impl Default for Application {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Application {
     pub fn new() -> Self {
         Application {
             persistent_data: RwLock::new(data::PersistentData::open()),
+            sessions: SessionManager::new(),
         }
     }
 
@@ -16,13 +25,14 @@ impl Application {
         self.persistent_data.read().unwrap().connections.clone()
     }
 
+    pub fn get_settings(&self) -> data::Settings {
+        self.persistent_data.read().unwrap().settings.clone()
+    }
+
     pub fn get_connection(&self, id: &str) -> Option<data::Connection> {
         let connections = self.persistent_data.read().unwrap().connections.clone();
-        for connection in connections {
-            if connection.id == id {
-                return Some(connection);
-            }
-        }
-        None
+        connections
+            .into_iter()
+            .find(|connection| connection.id == id)
     }
 }
@@ -1,10 +1,7 @@
 use anyhow::Result;
 use db9s::app;
 use db9s::data;
-use mysql;
 use mysql::prelude::*;
-use postgres;
-use rusqlite;
 
 fn main() -> Result<(), anyhow::Error> {
     let app = app::Application::new();
@@ -58,7 +55,7 @@ fn main() -> Result<(), anyhow::Error> {
     postgres_client.execute("CREATE TABLE posts (id INT, user_id INT, title TEXT)", &[])?;
 
     for i in 0..100 {
-        let id = i.clone();
+        let id = i;
         let user_id = i % 10;
 
         postgres_client.execute(
@@ -96,7 +93,7 @@ fn main() -> Result<(), anyhow::Error> {
     // clear it all out
     redis::cmd("FLUSHALL").query::<()>(&mut redis_client)?;
     for i in 0..100 {
-        let id = i.clone();
+        let id = i;
         let user_id = i % 10;
 
         redis::cmd("HMSET")
@@ -124,7 +121,7 @@ fn main() -> Result<(), anyhow::Error> {
         [],
     )?;
     for i in 0..100 {
-        let id = i.clone();
+        let id = i;
         let user_id = i % 10;
 
         sqlite_conn.execute(
@@ -168,7 +165,7 @@ fn main() -> Result<(), anyhow::Error> {
     mysql_conn.query_drop("CREATE TABLE users (id INT, first TEXT, last TEXT)")?;
     mysql_conn.query_drop("CREATE TABLE posts (id INT, user_id INT, title TEXT)")?;
     for i in 0..100 {
-        let id = i.clone();
+        let id = i;
         let user_id = i % 10;
 
         mysql_conn.query_drop(
@@ -1,6 +1,5 @@
 use db9s::app;
 use db9s::ui;
-use fern;
 
 fn setup_logging() -> Result<(), fern::InitError> {
     fern::Dispatch::new()
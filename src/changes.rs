@@ -0,0 +1,485 @@
+use crate::connectiontypes::base::{ColumnInfo, ConnectionType, QueryParams, Value};
+use crate::import::coerce;
+use anyhow::Result;
+use log::error;
+
+// operators of a typed condition, longest first so `<=` is not taken for `<`
+const WHERE_OPERATORS: [&str; 13] = [
+    "is not null",
+    "is null",
+    "not ilike",
+    "not like",
+    "ilike",
+    "like",
+    "<>",
+    "!=",
+    ">=",
+    "<=",
+    "=",
+    ">",
+    "<",
+];
+
+/// A cell changed in the result grid, written with an UPDATE once the changes are committed.
+#[derive(Clone)]
+pub struct StagedEdit {
+    pub table: String,
+    // the primary key of the row as it was read
+    pub key: Vec<(String, Value)>,
+    pub column: String,
+    pub old: Value,
+    pub new: Value,
+}
+
+impl StagedEdit {
+    pub fn same_cell(&self, other: &StagedEdit) -> bool {
+        self.table == other.table && self.key == other.key && self.column == other.column
+    }
+}
+
+/// The primary key of a table, which rows written to are found by.
+pub fn primary_key(db: &dyn ConnectionType, table: &str) -> Result<Vec<String>> {
+    let primary_key = db.list_primary_key(table)?;
+    if primary_key.is_empty() {
+        return Err(anyhow::anyhow!(
+            "{} has no primary key to find its rows by",
+            table
+        ));
+    }
+    Ok(primary_key)
+}
+
+/// The values of the primary key columns in a row of a result.
+pub fn row_key(
+    primary_key: &[String],
+    columns: &[String],
+    row: &[Value],
+) -> Result<Vec<(String, Value)>> {
+    primary_key
+        .iter()
+        .map(|name| {
+            let idx = columns
+                .iter()
+                .position(|c| c == name)
+                .ok_or(anyhow::anyhow!(
+                    "The result has no {} column to find the row by",
+                    name
+                ))?;
+            Ok((name.clone(), row[idx].clone()))
+        })
+        .collect()
+}
+
+/// A statement writing to one row, with a copy of it with the values filled in to show.
+#[derive(Clone)]
+pub struct RowChange {
+    statement: String,
+    params: QueryParams,
+    pub display: String,
+}
+
+/// `column = value` for each pair joined by `separator`, once with placeholders bound
+/// after the parameters already in `params` and once with the values filled in.
+fn bind_pairs(
+    db: &dyn ConnectionType,
+    pairs: &[(String, Value)],
+    separator: &str,
+    params: &mut QueryParams,
+) -> (String, String) {
+    let mut bound = vec![];
+    let mut filled = vec![];
+    for (column, value) in pairs {
+        let placeholder = db.placeholder(params.len() + 1);
+        let column = db.quote_identifier(column);
+        bound.push(format!("{} = {}", column, placeholder));
+        filled.push(format!("{} = {}", column, db.sql_literal(value)));
+        params.push((placeholder, value.clone()));
+    }
+    (bound.join(separator), filled.join(separator))
+}
+
+/// One UPDATE per edited row setting every changed column of it, with the edits it writes.
+pub fn staged_updates(
+    db: &dyn ConnectionType,
+    staged: &[StagedEdit],
+) -> Vec<(RowChange, Vec<StagedEdit>)> {
+    let mut rows: Vec<Vec<StagedEdit>> = vec![];
+    for edit in staged {
+        match rows
+            .iter_mut()
+            .find(|row| row[0].table == edit.table && row[0].key == edit.key)
+        {
+            Some(row) => row.push(edit.clone()),
+            None => rows.push(vec![edit.clone()]),
+        }
+    }
+    rows.into_iter()
+        .map(|edits| {
+            let assignments = edits
+                .iter()
+                .map(|edit| (edit.column.clone(), edit.new.clone()))
+                .collect::<Vec<_>>();
+            let mut params: QueryParams = vec![];
+            let (set, set_display) = bind_pairs(db, &assignments, ", ", &mut params);
+            let (condition, condition_display) =
+                bind_pairs(db, &edits[0].key, " AND ", &mut params);
+            let table = db.quote_identifier(&edits[0].table);
+            let change = RowChange {
+                statement: format!("UPDATE {} SET {} WHERE {}", table, set, condition),
+                params,
+                display: format!(
+                    "UPDATE {} SET {} WHERE {}",
+                    table, set_display, condition_display
+                ),
+            };
+            (change, edits)
+        })
+        .collect()
+}
+
+/// One DELETE per row, each found by its primary key.
+pub fn row_deletes(
+    db: &dyn ConnectionType,
+    table: &str,
+    keys: &[Vec<(String, Value)>],
+) -> Vec<RowChange> {
+    let table = db.quote_identifier(table);
+    keys.iter()
+        .map(|key| {
+            let mut params: QueryParams = vec![];
+            let (condition, condition_display) = bind_pairs(db, key, " AND ", &mut params);
+            RowChange {
+                statement: format!("DELETE FROM {} WHERE {}", table, condition),
+                params,
+                display: format!("DELETE FROM {} WHERE {}", table, condition_display),
+            }
+        })
+        .collect()
+}
+
+/// An INSERT of the given columns, the others get their defaults.
+pub fn row_insert(db: &dyn ConnectionType, table: &str, values: &[(String, Value)]) -> RowChange {
+    let columns = values
+        .iter()
+        .map(|(column, _)| db.quote_identifier(column))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let placeholders = (1..=values.len())
+        .map(|position| db.placeholder(position))
+        .collect::<Vec<_>>();
+    let literals = values
+        .iter()
+        .map(|(_, value)| db.sql_literal(value))
+        .collect::<Vec<_>>();
+    let table = db.quote_identifier(table);
+    RowChange {
+        statement: format!(
+            "INSERT INTO {} ({}) VALUES ({})",
+            table,
+            columns,
+            placeholders.join(", ")
+        ),
+        params: placeholders
+            .into_iter()
+            .zip(values.iter().map(|(_, value)| value.clone()))
+            .collect(),
+        display: format!(
+            "INSERT INTO {} ({}) VALUES ({})",
+            table,
+            columns,
+            literals.join(", ")
+        ),
+    }
+}
+
+/// Runs the changes in a transaction of their own, or inside an open one up to a
+/// savepoint. A change not finding its row or a cancel undoes them all.
+pub fn apply_changes(
+    db: &dyn ConnectionType,
+    changes: &[RowChange],
+    in_transaction: bool,
+    cancelled: impl Fn() -> bool,
+) -> Result<u64> {
+    if in_transaction {
+        db.execute("SAVEPOINT db9s_changes", &vec![])?;
+    } else {
+        db.begin_transaction()?;
+    }
+    let result = changes.iter().try_fold(0, |changed, change| {
+        if cancelled() {
+            return Err(anyhow::anyhow!("Cancelled"));
+        }
+        let outcome = db.execute(&change.statement, &change.params)?.outcome;
+        if outcome.affected_rows == Some(0) {
+            return Err(anyhow::anyhow!(
+                "The row was changed or deleted meanwhile: {}",
+                change.display
+            ));
+        }
+        Ok(changed + outcome.affected_rows.unwrap_or(1))
+    });
+    // the last change may have finished before the cancel reached the server
+    let result = result.and_then(|changed| {
+        if cancelled() {
+            Err(anyhow::anyhow!("Cancelled"))
+        } else {
+            Ok(changed)
+        }
+    });
+    let release = || {
+        db.execute("RELEASE SAVEPOINT db9s_changes", &vec![])
+            .map(|_| ())
+    };
+    match result {
+        Ok(changed) => {
+            if in_transaction {
+                release()?;
+            } else {
+                db.commit_transaction()?;
+            }
+            Ok(changed)
+        }
+        Err(err) => {
+            let rollback = if in_transaction {
+                db.execute("ROLLBACK TO SAVEPOINT db9s_changes", &vec![])
+                    .and_then(|_| release())
+            } else {
+                db.rollback_transaction()
+            };
+            if let Err(rollback) = rollback {
+                error!("Rollback failed: {:?}", rollback);
+            }
+            Err(err.context("None of the changes were made"))
+        }
+    }
+}
+
+/// A condition typed as `column op value` on a column of the table is written with the
+/// column quoted and the value as a literal of its type, anything else is used as typed.
+pub fn where_condition(
+    input: &str,
+    columns: &[ColumnInfo],
+    db: &dyn ConnectionType,
+) -> Result<String> {
+    let input = input.trim();
+    let end = input
+        .find(|c: char| c.is_whitespace() || "=<>!".contains(c))
+        .unwrap_or(input.len());
+    let column = match columns
+        .iter()
+        .find(|column| column.name.eq_ignore_ascii_case(&input[..end]))
+    {
+        Some(column) => column,
+        None => return Ok(input.to_string()),
+    };
+    let rest = input[end..].trim_start();
+    let operator = WHERE_OPERATORS.iter().find(|operator| {
+        rest.get(..operator.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(operator))
+            // `like` is a word of its own, `likes` is not an operator
+            && (!operator.ends_with(char::is_alphabetic)
+                || rest[operator.len()..].is_empty()
+                || rest[operator.len()..].starts_with(char::is_whitespace))
+    });
+    let operator = match operator {
+        Some(operator) => *operator,
+        None => return Ok(input.to_string()),
+    };
+    let name = db.quote_identifier(&column.name);
+    let value = rest[operator.len()..].trim();
+    // more than one value, e.g. `id = 1 or id = 2`, is a fragment
+    let quoted = quoted_literal(value);
+    if quoted.is_none() && value.contains(char::is_whitespace) {
+        return Ok(input.to_string());
+    }
+    if operator.ends_with("null") {
+        if !value.is_empty() {
+            return Err(anyhow::anyhow!("`{}` takes no value", operator));
+        }
+        return Ok(format!("{} {}", name, operator.to_uppercase()));
+    }
+    if value.is_empty() {
+        return Err(anyhow::anyhow!("Type a value after `{}`", operator));
+    }
+    // nothing equals NULL
+    if value.eq_ignore_ascii_case("null") {
+        return match operator {
+            "=" => Ok(format!("{} IS NULL", name)),
+            "!=" | "<>" => Ok(format!("{} IS NOT NULL", name)),
+            _ => Err(anyhow::anyhow!(
+                "NULL can't be compared with `{}`",
+                operator
+            )),
+        };
+    }
+    let value = match quoted {
+        Some(text) => Value::Text(text),
+        None if operator.ends_with("like") => Value::Text(value.to_string()),
+        None => coerce(&Value::Text(value.to_string()), column)?,
+    };
+    let literal = db.sql_literal(&value);
+    Ok(format!("{} {} {}", name, operator.to_uppercase(), literal))
+}
+
+/// The text of the value when it is one quoted literal, `''` in it being a quote.
+fn quoted_literal(value: &str) -> Option<String> {
+    let inner = value.strip_prefix('\'')?.strip_suffix('\'')?;
+    if inner.replace("''", "").contains('\'') {
+        return None;
+    }
+    Some(inner.replace("''", "'"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connectiontypes::session::SessionManager;
+    use crate::connectiontypes::utils::get_connection_type;
+    use crate::data::{Connection, Settings};
+    use std::collections::HashMap;
+
+    // the connection types are only created, nothing connects to a server
+    fn connection_type(dsn: &str) -> Box<dyn ConnectionType> {
+        get_connection_type(
+            Connection::new("test".to_string(), dsn.to_string()),
+            HashMap::new(),
+            &SessionManager::new(),
+            &Settings::default(),
+        )
+        .unwrap()
+    }
+
+    fn columns() -> Vec<ColumnInfo> {
+        ["id:integer", "Name:text", "born:date", "active:boolean"]
+            .iter()
+            .map(|column| {
+                let (name, data_type) = column.split_once(':').unwrap();
+                ColumnInfo {
+                    data_type: data_type.to_string(),
+                    ..ColumnInfo::named(name.to_string())
+                }
+            })
+            .collect()
+    }
+
+    fn condition(input: &str) -> Result<String> {
+        where_condition(
+            input,
+            &columns(),
+            connection_type("postgres://localhost/test").as_ref(),
+        )
+    }
+
+    #[test]
+    fn columns_and_operators() {
+        assert_eq!(condition("ID=5").unwrap(), "\"id\" = 5");
+        assert_eq!(condition(" id >= 5 ").unwrap(), "\"id\" >= 5");
+        assert_eq!(condition("id <> 5").unwrap(), "\"id\" <> 5");
+        assert_eq!(condition("name like Jo%").unwrap(), "\"Name\" LIKE 'Jo%'");
+        assert_eq!(
+            condition("name not ilike 'a b%'").unwrap(),
+            "\"Name\" NOT ILIKE 'a b%'"
+        );
+        assert_eq!(
+            condition("born < 2000-01-01").unwrap(),
+            "\"born\" < '2000-01-01'"
+        );
+        assert_eq!(condition("active = yes").unwrap(), "\"active\" = TRUE");
+    }
+
+    #[test]
+    fn null_comparisons() {
+        assert_eq!(condition("id = null").unwrap(), "\"id\" IS NULL");
+        assert_eq!(condition("id != NULL").unwrap(), "\"id\" IS NOT NULL");
+        assert_eq!(condition("id is not null").unwrap(), "\"id\" IS NOT NULL");
+        assert!(condition("id is null 5").is_err());
+        assert!(condition("id > null").is_err());
+        assert!(condition("id =").is_err());
+    }
+
+    #[test]
+    fn quoted_values() {
+        assert_eq!(condition("name = 'it''s'").unwrap(), "\"Name\" = 'it''s'");
+        assert_eq!(condition("name = 'null'").unwrap(), "\"Name\" = 'null'");
+        assert_eq!(condition("name = 'a b'").unwrap(), "\"Name\" = 'a b'");
+        assert_eq!(quoted_literal("'a''b'"), Some("a'b".to_string()));
+        assert_eq!(quoted_literal("''"), Some(String::new()));
+        assert_eq!(quoted_literal("'a' or 'b'"), None);
+        assert_eq!(quoted_literal("a"), None);
+    }
+
+    #[test]
+    fn fragments_are_used_as_typed() {
+        for input in [
+            "id = 1 or id = 2",
+            "lower(name) = 'x'",
+            "nickname = 'x'",
+            "id between 1 and 2",
+            "namelike x",
+        ] {
+            assert_eq!(condition(input).unwrap(), input);
+        }
+    }
+
+    #[test]
+    fn values_of_the_wrong_type() {
+        assert!(condition("id = abc").is_err());
+        assert!(condition("born = yesterday").is_err());
+    }
+
+    #[test]
+    fn literals_of_each_database() {
+        let mysql = connection_type("mysql://localhost/test");
+        assert_eq!(
+            where_condition("name = 'a\\b'", &columns(), mysql.as_ref()).unwrap(),
+            "`Name` = 'a\\\\b'"
+        );
+        let sqlite = connection_type("sqlite://test.db");
+        assert_eq!(
+            where_condition("active = true", &columns(), sqlite.as_ref()).unwrap(),
+            "\"active\" = TRUE"
+        );
+    }
+
+    #[test]
+    fn changes_are_made_all_or_none() {
+        // sqlite DSNs name a file in the working directory
+        let path = format!("db9s-changes-{}.db", std::process::id());
+        let db = connection_type(&format!("sqlite://{}", path));
+        let change = |statement: &str| RowChange {
+            statement: statement.to_string(),
+            params: vec![],
+            display: statement.to_string(),
+        };
+        let count = |db: &dyn ConnectionType| {
+            db.execute("SELECT count(*) FROM t WHERE a > 1", &vec![])
+                .unwrap()
+                .rows[0]
+                .data[0]
+                .clone()
+        };
+        db.execute("CREATE TABLE t (a INTEGER)", &vec![]).unwrap();
+        db.execute("INSERT INTO t VALUES (1)", &vec![]).unwrap();
+        let changes = [
+            change("UPDATE t SET a = 2 WHERE a = 1"),
+            change("UPDATE t SET a = 3 WHERE a = 5"),
+        ];
+        for in_transaction in [false, true] {
+            if in_transaction {
+                db.begin_transaction().unwrap();
+            }
+            let err = apply_changes(db.as_ref(), &changes, in_transaction, || false).unwrap_err();
+            assert!(format!("{:#}", err).starts_with("None of the changes were made"));
+            assert_eq!(count(db.as_ref()), Value::Int(0));
+        }
+        // the open transaction goes on
+        apply_changes(db.as_ref(), &changes[..1], true, || false).unwrap();
+        assert_eq!(count(db.as_ref()), Value::Int(1));
+        db.rollback_transaction().unwrap();
+        assert_eq!(count(db.as_ref()), Value::Int(0));
+        assert!(apply_changes(db.as_ref(), &changes[..1], false, || true).is_err());
+        assert_eq!(count(db.as_ref()), Value::Int(0));
+        drop(db);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
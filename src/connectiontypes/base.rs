@@ -1,7 +1,37 @@
-use anyhow::Result;
+use crate::connectiontypes::splitter;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use std::cmp::Ordering;
+use std::fmt;
+use std::time::Duration;
+use uuid::Uuid;
+
 pub struct Table {
     pub id: String,
     pub name: String,
+    // e.g. table, view or materialized view
+    pub kind: String,
+    // what the server estimates, not a count
+    pub rows: Option<u64>,
+    // bytes on disk, including indexes
+    pub size: Option<u64>,
+    pub owner: Option<String>,
+    pub comment: Option<String>,
+}
+
+impl Table {
+    /// A table nothing is known about but its name.
+    pub fn named(name: String, kind: &str) -> Self {
+        Table {
+            id: name.clone(),
+            name,
+            kind: kind.to_string(),
+            rows: None,
+            size: None,
+            owner: None,
+            comment: None,
+        }
+    }
 }
 
 pub struct Schema {
@@ -14,25 +44,730 @@ pub struct DatabaseInfo {
     pub name: String,
 }
 
+/// An object of the database other than a table, e.g. a view or a function.
+pub struct SchemaObject {
+    // what `get_definition` finds the object by, names of functions are not unique
+    pub id: String,
+    pub name: String,
+    // shown as they are, what they describe depends on the kind of object
+    pub kind: String,
+    pub detail: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ObjectKind {
+    View,
+    Function,
+    Trigger,
+    Sequence,
+    Enum,
+    Table,
+    Index,
+}
+
+pub struct IndexInfo {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub unique: bool,
+    pub method: String,
+    // bytes on disk, when the server reports it
+    pub size: Option<u64>,
+    pub definition: String,
+}
+
+/// The `columns` of `table` referencing the `referenced_columns` of `referenced_table`.
+pub struct ForeignKey {
+    pub name: String,
+    pub table: String,
+    pub columns: Vec<String>,
+    pub referenced_table: String,
+    pub referenced_columns: Vec<String>,
+}
+
+impl ForeignKey {
+    /// Builds the keys from one row per column pair of name, table, column,
+    /// referenced table and referenced column, the columns of a key next to each other.
+    pub fn from_column_pairs(pairs: Vec<(String, String, String, String, String)>) -> Vec<Self> {
+        let mut keys: Vec<ForeignKey> = vec![];
+        for (name, table, column, referenced_table, referenced_column) in pairs {
+            match keys.last_mut() {
+                Some(key) if key.name == name && key.table == table => {
+                    key.columns.push(column);
+                    key.referenced_columns.push(referenced_column);
+                }
+                _ => keys.push(ForeignKey {
+                    name,
+                    table,
+                    columns: vec![column],
+                    referenced_table,
+                    referenced_columns: vec![referenced_column],
+                }),
+            }
+        }
+        keys
+    }
+}
+
+#[derive(Clone)]
+pub struct ColumnInfo {
+    pub name: String,
+    pub data_type: String,
+    pub nullable: bool,
+    pub default: Option<String>,
+    pub primary_key: bool,
+    // the columns it references, e.g. `users(id)`
+    pub foreign_key: Option<String>,
+    pub comment: Option<String>,
+}
+
+impl ColumnInfo {
+    /// A column nothing is known about but its name.
+    pub fn named(name: String) -> Self {
+        ColumnInfo {
+            name,
+            data_type: String::new(),
+            nullable: true,
+            default: None,
+            primary_key: false,
+            foreign_key: None,
+            comment: None,
+        }
+    }
+}
+
 pub struct ColumnData {
     pub column: String,
     pub data: Option<String>,
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    // arbitrary precision numbers are kept in their textual form
+    Decimal(String),
+    Text(String),
+    Bytes(Vec<u8>),
+    Json(serde_json::Value),
+    Date(NaiveDate),
+    Time(NaiveTime),
+    Timestamp(NaiveDateTime),
+    TimestampTz(DateTime<Utc>),
+    Uuid(Uuid),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    fn as_number(&self) -> Option<f64> {
+        match self {
+            Value::Int(i) => Some(*i as f64),
+            Value::Float(f) => Some(*f),
+            Value::Decimal(d) => d.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// The order values are sorted in: NULL after everything else, numbers by their
+    /// amount, text ignoring case and anything else by how it is shown.
+    pub fn compare(&self, other: &Value) -> Ordering {
+        match (self, other) {
+            (Value::Null, Value::Null) => Ordering::Equal,
+            (Value::Null, _) => Ordering::Greater,
+            (_, Value::Null) => Ordering::Less,
+            (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+            (a, b) => match (a.as_number(), b.as_number()) {
+                (Some(a), Some(b)) => a.total_cmp(&b),
+                _ => {
+                    let (a, b) = (a.to_string(), b.to_string());
+                    a.to_lowercase().cmp(&b.to_lowercase()).then(a.cmp(&b))
+                }
+            },
+        }
+    }
+
+    /// The value written as a standard SQL literal, `ConnectionType::sql_literal` writes
+    /// it the way a database reads it back.
+    pub fn to_sql_literal(&self) -> String {
+        match self {
+            Value::Null => "NULL".to_string(),
+            Value::Bool(b) => if *b { "TRUE" } else { "FALSE" }.to_string(),
+            Value::Int(_) | Value::Decimal(_) => self.to_string(),
+            Value::Float(v) if v.is_finite() => self.to_string(),
+            // not numbers in SQL, postgres reads these spellings back
+            Value::Float(v) if v.is_nan() => quote_text("NaN", false),
+            Value::Float(v) if *v > 0.0 => quote_text("Infinity", false),
+            Value::Float(_) => quote_text("-Infinity", false),
+            Value::Bytes(bytes) => format!("X'{}'", hex(bytes)),
+            value => quote_text(&value.to_string(), false),
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Null => "null",
+            Value::Bool(_) => "bool",
+            Value::Int(_) => "int",
+            Value::Float(_) => "float",
+            Value::Decimal(_) => "decimal",
+            Value::Text(_) => "text",
+            Value::Bytes(_) => "bytes",
+            Value::Json(_) => "json",
+            Value::Date(_) => "date",
+            Value::Time(_) => "time",
+            Value::Timestamp(_) => "timestamp",
+            Value::TimestampTz(_) => "timestamptz",
+            Value::Uuid(_) => "uuid",
+            Value::Array(_) => "array",
+        }
+    }
+
+    /// The value as JSON, numbers kept exact are written as strings and other types as text.
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            Value::Null => serde_json::Value::Null,
+            Value::Bool(b) => serde_json::Value::Bool(*b),
+            Value::Int(i) => serde_json::Value::from(*i),
+            // NaN and infinity have no JSON form and become null
+            Value::Float(f) => serde_json::Value::from(*f),
+            Value::Json(json) => json.clone(),
+            Value::Array(items) => items.iter().map(Value::to_json).collect(),
+            value => serde_json::Value::String(value.to_string()),
+        }
+    }
+
+    pub fn from_json(value: &serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::Null => Value::Null,
+            serde_json::Value::Bool(b) => Value::Bool(*b),
+            serde_json::Value::Number(n) => {
+                if let Some(i) = n.as_i64() {
+                    Value::Int(i)
+                } else if let Some(f) = n.as_f64() {
+                    Value::Float(f)
+                } else {
+                    Value::Decimal(n.to_string())
+                }
+            }
+            serde_json::Value::String(s) => Value::Text(s.clone()),
+            serde_json::Value::Array(items) => {
+                Value::Array(items.iter().map(Value::from_json).collect())
+            }
+            serde_json::Value::Object(_) => Value::Json(value.clone()),
+        }
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::Text(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::Text(value.to_string())
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        match value {
+            Some(v) => v.into(),
+            None => Value::Null,
+        }
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Reads bytes written as `\x` and pairs of hex digits, the way they are shown.
+pub fn parse_hex(text: &str) -> Option<Vec<u8>> {
+    let digits = text.trim().strip_prefix("\\x")?;
+    if digits.len() % 2 != 0 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|idx| u8::from_str_radix(&digits[idx..idx + 2], 16).ok())
+        .collect()
+}
+
+/// Text as a quoted SQL literal, quotes are doubled and with `backslashes` so are backslashes.
+pub fn quote_text(text: &str, backslashes: bool) -> String {
+    let text = if backslashes {
+        text.replace('\\', "\\\\")
+    } else {
+        text.to_string()
+    };
+    format!("'{}'", text.replace('\'', "''"))
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => write!(f, "NULL"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Int(i) => write!(f, "{}", i),
+            Value::Float(v) => write!(f, "{}", v),
+            Value::Decimal(d) => write!(f, "{}", d),
+            Value::Text(s) => write!(f, "{}", s),
+            Value::Bytes(bytes) => write!(f, "\\x{}", hex(bytes)),
+            Value::Json(json) => write!(f, "{}", json),
+            Value::Date(d) => write!(f, "{}", d),
+            Value::Time(t) => write!(f, "{}", t),
+            Value::Timestamp(ts) => write!(f, "{}", ts),
+            Value::TimestampTz(ts) => write!(f, "{}", ts),
+            Value::Uuid(u) => write!(f, "{}", u),
+            Value::Array(items) => {
+                write!(f, "{{")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+const PARAM_CASTS: [&str; 13] = [
+    "text",
+    "int",
+    "float",
+    "decimal",
+    "bool",
+    "date",
+    "time",
+    "timestamp",
+    "timestamptz",
+    "uuid",
+    "bytes",
+    "json",
+    "null",
+];
+
+pub fn parse_bool(text: &str) -> Result<bool> {
+    match text.trim().to_lowercase().as_str() {
+        "true" | "t" | "yes" | "y" | "on" | "1" => Ok(true),
+        "false" | "f" | "no" | "n" | "off" | "0" => Ok(false),
+        _ => Err(anyhow!("`{}` is not a boolean", text)),
+    }
+}
+
+pub fn parse_timestamp(text: &str) -> Result<NaiveDateTime> {
+    let text = text.trim();
+    for format in [
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M",
+    ] {
+        if let Ok(ts) = NaiveDateTime::parse_from_str(text, format) {
+            return Ok(ts);
+        }
+    }
+    Ok(NaiveDate::parse_from_str(text, "%Y-%m-%d")
+        .map_err(|_| anyhow!("`{}` is not a timestamp", text))?
+        .and_hms_opt(0, 0, 0)
+        .unwrap())
+}
+
+/// Parses a value typed for a query parameter. A trailing `::type` casts it,
+/// e.g. `42::int`, `2024-01-31::date` or `::null`, anything else is text.
+pub fn parse_param(input: &str) -> Result<Value> {
+    let (text, cast) = match input.rsplit_once("::") {
+        Some((text, cast)) if PARAM_CASTS.contains(&cast.trim().to_lowercase().as_str()) => {
+            (text, cast.trim().to_lowercase())
+        }
+        _ => return Ok(Value::Text(input.to_string())),
+    };
+    Ok(match cast.as_str() {
+        "int" => Value::Int(
+            text.trim()
+                .parse()
+                .map_err(|_| anyhow!("`{}` is not an integer", text))?,
+        ),
+        "float" => Value::Float(
+            text.trim()
+                .parse()
+                .map_err(|_| anyhow!("`{}` is not a number", text))?,
+        ),
+        "decimal" => {
+            text.trim()
+                .parse::<f64>()
+                .map_err(|_| anyhow!("`{}` is not a number", text))?;
+            Value::Decimal(text.trim().to_string())
+        }
+        "bool" => Value::Bool(parse_bool(text)?),
+        "date" => Value::Date(
+            NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d")
+                .map_err(|_| anyhow!("`{}` is not a date", text))?,
+        ),
+        "time" => Value::Time(
+            NaiveTime::parse_from_str(text.trim(), "%H:%M:%S%.f")
+                .or_else(|_| NaiveTime::parse_from_str(text.trim(), "%H:%M"))
+                .map_err(|_| anyhow!("`{}` is not a time", text))?,
+        ),
+        "timestamp" => Value::Timestamp(parse_timestamp(text)?),
+        "timestamptz" => Value::TimestampTz(match DateTime::parse_from_rfc3339(text.trim()) {
+            Ok(ts) => ts.with_timezone(&Utc),
+            Err(_) => parse_timestamp(text.trim().trim_end_matches("UTC"))?.and_utc(),
+        }),
+        "uuid" => Value::Uuid(
+            Uuid::parse_str(text.trim()).map_err(|_| anyhow!("`{}` is not a uuid", text))?,
+        ),
+        "bytes" => Value::Bytes(
+            parse_hex(text).ok_or(anyhow!("`{}` is not written as \\x and hex digits", text))?,
+        ),
+        "json" => Value::Json(serde_json::from_str(text)?),
+        "null" => Value::Null,
+        _ => Value::Text(text.to_string()),
+    })
+}
+
+/// Writes a value the way `parse_param` reads it back, e.g. `42::int` or `::null`.
+pub fn format_param(value: &Value) -> String {
+    match value {
+        Value::Null => "::null".to_string(),
+        // text that looks like a cast keeps being text
+        Value::Text(text) => match text.rsplit_once("::") {
+            Some((_, cast)) if PARAM_CASTS.contains(&cast.trim().to_lowercase().as_str()) => {
+                format!("{}::text", text)
+            }
+            _ => text.clone(),
+        },
+        value if PARAM_CASTS.contains(&value.type_name()) => {
+            format!("{}::{}", value, value.type_name())
+        }
+        value => value.to_string(),
+    }
+}
+
+/// Parses a value typed over `old`, the text `old` was shown as keeps it as it was.
+pub fn parse_edit(input: &str, old: &Value) -> Result<Value> {
+    if input == format_param(old) {
+        return Ok(old.clone());
+    }
+    parse_param(input)
+}
+
+/// Values bound to the placeholders of a statement, in the order they are bound.
+pub type QueryParams = Vec<(String, Value)>;
+
+/// A row of a result set, the rows are told apart by their position in it.
 pub struct QueryResultRow {
-    pub id: String,
-    pub data: Vec<Option<String>>,
+    pub data: Vec<Value>,
+}
+
+/// Where the next page of a result set continues from.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PageCursor {
+    Offset(usize),
+    Cursor(String),
+    Scan(u64),
+    SearchAfter(serde_json::Value),
+}
+
+/// A statement either produces a row set or completes with a command tag.
+#[derive(Clone, Debug, PartialEq)]
+pub enum OutcomeKind {
+    Rows,
+    Command(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct ExecutionOutcome {
+    pub kind: OutcomeKind,
+    pub affected_rows: Option<u64>,
+    pub last_insert_id: Option<u64>,
+    pub elapsed: Duration,
+    pub notices: Vec<String>,
+}
+
+impl ExecutionOutcome {
+    pub fn rows() -> Self {
+        ExecutionOutcome {
+            kind: OutcomeKind::Rows,
+            affected_rows: None,
+            last_insert_id: None,
+            elapsed: Duration::ZERO,
+            notices: vec![],
+        }
+    }
+
+    pub fn command(tag: String, affected_rows: Option<u64>) -> Self {
+        // servers report 0 affected rows for DDL, which says nothing
+        let counts_rows =
+            ["INSERT", "UPDATE", "DELETE", "MERGE", "REPLACE", "COPY"].contains(&tag.as_str());
+        ExecutionOutcome {
+            affected_rows: affected_rows.filter(|_| counts_rows),
+            kind: OutcomeKind::Command(tag),
+            ..ExecutionOutcome::rows()
+        }
+    }
+}
+
+impl fmt::Display for ExecutionOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            OutcomeKind::Rows => write!(f, "Rows returned")?,
+            OutcomeKind::Command(tag) => write!(f, "{}", tag)?,
+        }
+        if let Some(affected) = self.affected_rows {
+            write!(f, ", {} rows affected", affected)?;
+        }
+        if let Some(id) = self.last_insert_id {
+            write!(f, ", last insert id {}", id)?;
+        }
+        write!(f, " in {:.2?}", self.elapsed)
+    }
+}
+
+/// The tag a server would report for a statement, e.g. `INSERT` or `CREATE TABLE`.
+pub fn command_tag(query: &str) -> String {
+    let words: Vec<String> = query
+        .split_whitespace()
+        .take(2)
+        .map(|w| w.trim_end_matches(';').to_uppercase())
+        .collect();
+    match words.first().map(|w| w.as_str()) {
+        Some("CREATE") | Some("DROP") | Some("ALTER") => words.join(" "),
+        Some(word) => word.to_string(),
+        None => String::new(),
+    }
+}
+
+/// Whether a statement opens (`Some(true)`) or ends (`Some(false)`) a transaction.
+pub fn transaction_change(query: &str) -> Option<bool> {
+    let words: Vec<String> = query
+        .split_whitespace()
+        .take(2)
+        .map(|w| w.trim_end_matches(';').to_uppercase())
+        .collect();
+    let second = words.get(1).map(|w| w.as_str());
+    match words.first().map(|w| w.as_str()) {
+        Some("BEGIN") => Some(true),
+        Some("START") if second == Some("TRANSACTION") => Some(true),
+        Some("COMMIT") | Some("END") | Some("ABORT") => Some(false),
+        // ROLLBACK TO only goes back to a savepoint
+        Some("ROLLBACK") if second != Some("TO") => Some(false),
+        _ => None,
+    }
 }
 
 pub struct QueryResult {
     pub columns: Vec<String>,
     pub rows: Vec<QueryResultRow>,
+    pub next_page: Option<PageCursor>,
+    pub outcome: ExecutionOutcome,
+}
+
+impl QueryResult {
+    pub fn empty() -> Self {
+        QueryResult {
+            columns: vec![],
+            rows: vec![],
+            next_page: None,
+            outcome: ExecutionOutcome::rows(),
+        }
+    }
+}
+
+/// One node of a query plan as reported by EXPLAIN.
+#[derive(Clone, Debug, Default)]
+pub struct PlanNode {
+    pub node_type: String,
+    pub detail: String,
+    // the estimated total cost, including the children
+    pub cost: Option<f64>,
+    pub rows: Option<f64>,
+    pub actual_rows: Option<f64>,
+    // milliseconds over all loops, only known when the plan was analyzed
+    pub actual_time: Option<f64>,
+    pub children: Vec<PlanNode>,
+}
+
+impl PlanNode {
+    /// What the node takes by itself, without its children: the actual time when
+    /// the plan was analyzed, otherwise the estimated cost.
+    pub fn own_cost(&self) -> Option<f64> {
+        let total = self.actual_time.or(self.cost)?;
+        let children: f64 = self
+            .children
+            .iter()
+            .filter_map(|child| child.actual_time.or(child.cost))
+            .sum();
+        Some((total - children).max(0.0))
+    }
+}
+
+/// A byte count the way people read it, e.g. `16 kB` or `1.5 GB`.
+pub fn format_size(bytes: u64) -> String {
+    let units = ["bytes", "kB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, units[0])
+    } else {
+        format!("{:.1} {}", size, units[unit])
+    }
+}
+
+// words of statements that change something besides reading rows: data-modifying
+// WITH queries, `SELECT ... INTO` and the locking clauses `FOR UPDATE` and `FOR SHARE`
+const WRITING_WORDS: [&str; 6] = ["INSERT", "UPDATE", "DELETE", "MERGE", "INTO", "SHARE"];
+
+/// Returns the query without its terminator when it is a single statement only
+/// reading rows, which is what can be paged by running it again for each page.
+/// Anything else runs once as it is.
+pub fn pageable_query(query: &str, dialect: splitter::Dialect) -> Option<String> {
+    let mut statements = splitter::split_statements(query, dialect);
+    if statements.len() != 1 {
+        return None;
+    }
+    let query = statements.remove(0);
+    let words = splitter::statement_words(&query, dialect);
+    let reads = words
+        .first()
+        .is_some_and(|word| ["SELECT", "WITH", "VALUES", "TABLE"].contains(&word.as_str()));
+    let writes = words
+        .iter()
+        .any(|word| WRITING_WORDS.contains(&word.as_str()));
+    if reads && !writes {
+        Some(query)
+    } else {
+        None
+    }
+}
+
+// servers limit the number of parameters of a statement, 32766 for sqlite
+const MAX_INSERT_PARAMS: usize = 30000;
+
+/// Writes rows with multi-row INSERTs, as many rows per statement as the parameters allow.
+pub fn insert_rows<C: ConnectionType + ?Sized>(
+    db: &C,
+    table: &str,
+    columns: &[String],
+    rows: &[Vec<Value>],
+) -> Result<u64> {
+    let names = columns
+        .iter()
+        .map(|column| db.quote_identifier(column))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let per_statement = (MAX_INSERT_PARAMS / columns.len().max(1)).max(1);
+    let mut written = 0;
+    for chunk in rows.chunks(per_statement) {
+        let mut params: QueryParams = vec![];
+        let mut tuples = vec![];
+        for row in chunk {
+            let mut placeholders = vec![];
+            for value in row {
+                let placeholder = db.placeholder(params.len() + 1);
+                placeholders.push(placeholder.clone());
+                params.push((placeholder, value.clone()));
+            }
+            tuples.push(format!("({})", placeholders.join(", ")));
+        }
+        let statement = format!(
+            "INSERT INTO {} ({}) VALUES {}",
+            db.quote_identifier(table),
+            names,
+            tuples.join(", ")
+        );
+        let outcome = db.execute(&statement, &params)?.outcome;
+        written += outcome.affected_rows.unwrap_or(chunk.len() as u64);
+    }
+    Ok(written)
 }
 
 pub trait ConnectionType: Send + Sync {
     fn list_tables(&self) -> Result<Vec<Table>>;
 
-    fn query(&self) -> Result<QueryResult>;
+    /// Runs a single statement and returns its first page of rows.
+    fn execute(&self, statement: &str, params: &QueryParams) -> Result<QueryResult>;
+
+    fn fetch_page(
+        &self,
+        statement: &str,
+        params: &QueryParams,
+        cursor: &PageCursor,
+    ) -> Result<QueryResult>;
+
+    fn split_script(&self, script: &str) -> Vec<String> {
+        vec![script.trim().to_string()]
+    }
+
+    /// Quotes a table or column name in the statements db9s writes itself.
+    fn quote_identifier(&self, name: &str) -> String {
+        format!("\"{}\"", name.replace('"', "\"\""))
+    }
+
+    /// The value as a literal in the statements db9s shows or exports.
+    fn sql_literal(&self, value: &Value) -> String {
+        value.to_sql_literal()
+    }
+
+    /// The placeholder of the n-th parameter, counted from 1, in the statements db9s writes itself.
+    fn placeholder(&self, _position: usize) -> String {
+        "?".to_string()
+    }
+
+    /// Placeholders of a statement that need a value before it can run.
+    fn placeholders(&self, _statement: &str) -> Vec<String> {
+        vec![]
+    }
+
+    fn supports_transactions(&self) -> bool {
+        false
+    }
+
+    /// Whether the default query can be sorted by appending an ORDER BY clause.
+    fn supports_order_by(&self) -> bool {
+        false
+    }
+
+    /// Whether the default query can be filtered by appending a WHERE clause.
+    fn supports_where(&self) -> bool {
+        false
+    }
+
+    /// Opens a transaction on the session, statements run afterwards take part in it.
+    fn begin_transaction(&self) -> Result<()> {
+        Err(anyhow!(
+            "Transactions are not supported for this connection"
+        ))
+    }
+
+    fn commit_transaction(&self) -> Result<()> {
+        Err(anyhow!(
+            "Transactions are not supported for this connection"
+        ))
+    }
+
+    fn rollback_transaction(&self) -> Result<()> {
+        Err(anyhow!(
+            "Transactions are not supported for this connection"
+        ))
+    }
+
+    /// The plan of a statement, `analyze` runs it to measure what it actually does.
+    fn explain(&self, _statement: &str, _params: &QueryParams, _analyze: bool) -> Result<PlanNode> {
+        Err(anyhow!("Query plans are not supported for this connection"))
+    }
 
     fn default_query_string(&self) -> String;
 
@@ -40,7 +775,169 @@ pub trait ConnectionType: Send + Sync {
 
     fn list_databases(&self) -> Result<Vec<DatabaseInfo>>;
 
-    fn list_columns(&self) -> Result<Vec<String>>;
+    /// The columns of the selected table, in their order in the table.
+    fn list_columns(&self) -> Result<Vec<ColumnInfo>>;
+
+    /// The indexes of the selected table.
+    fn list_indexes(&self) -> Result<Vec<IndexInfo>>;
+
+    /// The columns of the primary key of a table, in key order.
+    fn list_primary_key(&self, _table: &str) -> Result<Vec<String>> {
+        Ok(vec![])
+    }
+
+    /// Writes imported rows holding values for `columns` into a table, returns how many were written.
+    fn import_rows(&self, table: &str, columns: &[String], rows: &[Vec<Value>]) -> Result<u64> {
+        insert_rows(self, table, columns, rows)
+    }
+
+    /// The foreign keys of a table together with the ones referencing it.
+    fn list_foreign_keys(&self, _table: &str) -> Result<Vec<ForeignKey>> {
+        Ok(vec![])
+    }
+
+    /// Views, materialized ones included.
+    fn list_views(&self) -> Result<Vec<SchemaObject>> {
+        Ok(vec![])
+    }
+
+    /// Functions and procedures.
+    fn list_functions(&self) -> Result<Vec<SchemaObject>> {
+        Ok(vec![])
+    }
+
+    fn list_triggers(&self) -> Result<Vec<SchemaObject>> {
+        Ok(vec![])
+    }
+
+    fn list_sequences(&self) -> Result<Vec<SchemaObject>> {
+        Ok(vec![])
+    }
+
+    fn list_enums(&self) -> Result<Vec<SchemaObject>> {
+        Ok(vec![])
+    }
+
+    /// The source of an object, e.g. the CREATE statement of a view.
+    /// Tables and indexes are found by name in the selected schema or table.
+    fn get_definition(&self, _kind: ObjectKind, _id: &str) -> Result<String> {
+        Err(anyhow!("Definitions are not supported for this connection"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values() -> Vec<Value> {
+        let timestamp = NaiveDate::from_ymd_opt(2024, 1, 2)
+            .unwrap()
+            .and_hms_micro_opt(3, 4, 5, 678)
+            .unwrap();
+        vec![
+            Value::Null,
+            Value::Bool(false),
+            Value::Int(-42),
+            Value::Float(1.5),
+            Value::Decimal("10.50".to_string()),
+            Value::Text("plain".to_string()),
+            Value::Text("5::int".to_string()),
+            Value::Text(String::new()),
+            Value::Bytes(vec![0, 1, 0xab, 0xff]),
+            Value::Bytes(vec![]),
+            Value::Json(serde_json::json!({"a": [1, null]})),
+            Value::Date(timestamp.date()),
+            Value::Time(timestamp.time()),
+            Value::Timestamp(timestamp),
+            Value::TimestampTz(timestamp.and_utc()),
+            Value::Uuid(Uuid::parse_str("67e55044-10b1-426f-9247-bb680e5fe0c8").unwrap()),
+            Value::Array(vec![Value::Int(1), Value::Null]),
+        ]
+    }
+
+    #[test]
+    fn params_read_back_as_written() {
+        for value in values() {
+            let written = format_param(&value);
+            match value {
+                // arrays have no cast, they are only kept by `parse_edit`
+                Value::Array(_) => assert!(parse_param(&written).is_ok()),
+                value => assert_eq!(parse_param(&written).unwrap(), value, "{}", written),
+            }
+        }
+    }
+
+    #[test]
+    fn unchanged_edits_keep_the_value() {
+        for value in values() {
+            assert_eq!(parse_edit(&format_param(&value), &value).unwrap(), value);
+        }
+        let nan = Value::Float(f64::NAN);
+        assert!(matches!(
+            parse_edit(&format_param(&nan), &nan).unwrap(),
+            Value::Float(v) if v.is_nan()
+        ));
+        assert_eq!(parse_edit("7::int", &Value::Int(6)).unwrap(), Value::Int(7));
+    }
+
+    #[test]
+    fn only_reads_are_paged() {
+        use splitter::Dialect::{MySQL, Postgres, SQLite};
+        for (query, dialect, pageable) in [
+            ("SELECT * FROM t;", Postgres, Some("SELECT * FROM t")),
+            (
+                "  with x as (select 1) select * from x",
+                Postgres,
+                Some("with x as (select 1) select * from x"),
+            ),
+            ("VALUES (1), (2)", SQLite, Some("VALUES (1), (2)")),
+            (
+                "(SELECT 1) UNION (SELECT 2)",
+                MySQL,
+                Some("(SELECT 1) UNION (SELECT 2)"),
+            ),
+            (
+                "-- latest\nSELECT 'a;b', \"update\" FROM t",
+                Postgres,
+                Some("SELECT 'a;b', \"update\" FROM t"),
+            ),
+            (
+                "SELECT 'delete' FROM `into`",
+                MySQL,
+                Some("SELECT 'delete' FROM `into`"),
+            ),
+            ("SELECT 1; SELECT 2", Postgres, None),
+            ("INSERT INTO t VALUES (1) RETURNING *", Postgres, None),
+            (
+                "WITH d AS (DELETE FROM t RETURNING *) SELECT * FROM d",
+                Postgres,
+                None,
+            ),
+            ("WITH x AS (SELECT 1) UPDATE t SET a = 1", SQLite, None),
+            ("SELECT * INTO copy FROM t", Postgres, None),
+            ("SELECT a INTO @a FROM t", MySQL, None),
+            ("SELECT * FROM t FOR UPDATE", Postgres, None),
+            ("SELECT * FROM t FOR KEY SHARE", Postgres, None),
+            ("SELECT * FROM t LOCK IN SHARE MODE", MySQL, None),
+            ("EXPLAIN SELECT 1", Postgres, None),
+            ("", SQLite, None),
+        ] {
+            assert_eq!(
+                pageable_query(query, dialect).as_deref(),
+                pageable,
+                "{}",
+                query
+            );
+        }
+    }
 
-    fn list_indexes(&self) -> Result<Vec<String>>;
+    #[test]
+    fn hex_bytes() {
+        assert_eq!(parse_hex("\\x00fF"), Some(vec![0, 255]));
+        assert_eq!(parse_hex(" \\x "), Some(vec![]));
+        for text in ["00ff", "\\x0", "\\x+f", "\\xzz", "\\xé0"] {
+            assert_eq!(parse_hex(text), None, "{}", text);
+        }
+        assert!(parse_param("\\x0::bytes").is_err());
+    }
 }
@@ -1,106 +1,102 @@
+use crate::connectiontypes::base::ConnectionType;
+use crate::connectiontypes::session::Session;
 use crate::ui::types;
 use crate::{connectiontypes::base, data::Connection};
 use anyhow::Result;
 use mdsn::Dsn;
-extern crate reqwest;
-use base64;
 use std::collections::HashMap;
-use std::str::FromStr; // Add missing import
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Instant;
+
+fn cancel_search(
+    client: &reqwest::blocking::Client,
+    tasks_url: &str,
+    cancel_url: &str,
+    opaque_id: &str,
+) -> Result<()> {
+    let body = client.get(tasks_url).send()?.text()?;
+    let tasks: serde_json::Value = serde_json::from_str(&body)?;
+    let nodes = match tasks["nodes"].as_object() {
+        Some(nodes) => nodes,
+        None => return Ok(()),
+    };
+    for node in nodes.values() {
+        if let Some(node_tasks) = node["tasks"].as_object() {
+            for (task_id, task) in node_tasks {
+                if task["headers"]["X-Opaque-Id"].as_str() == Some(opaque_id) {
+                    client
+                        .post(format!("{}/{}/_cancel", cancel_url, task_id))
+                        .send()?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
 
 pub struct ElasticSearchDatabase {
-    name: String,
     dsn: Dsn,
     selections: HashMap<types::WindowTypeID, Vec<String>>,
-    query: Option<String>,
+    page_size: usize,
+    session: Arc<Session<reqwest::blocking::Client>>,
 }
 
 impl base::ConnectionType for ElasticSearchDatabase {
     fn list_tables(&self) -> Result<Vec<base::Table>> {
         let indexes = self.list_indexes()?;
         Ok(indexes
-            .iter()
+            .into_iter()
             .map(|index| base::Table {
-                id: index.clone(),
-                name: index.clone(),
+                size: index.size,
+                ..base::Table::named(index.name, "index")
             })
             .collect())
     }
 
-    fn list_indexes(&self) -> Result<Vec<String>> {
+    fn list_indexes(&self) -> Result<Vec<base::IndexInfo>> {
         let client = self.get_client()?;
-        let url = self.get_api_url("_cat/indices");
+        let url = self.get_api_url("_cat/indices?h=index,store.size&bytes=b");
         let response = client.get(&url).send()?;
         let body = response.text()?;
-        let tables: Vec<String> = body
+        let tables: Vec<base::IndexInfo> = body
             .lines()
             .map(|line| {
                 let parts: Vec<&str> = line.split_whitespace().collect();
-                parts[2].to_string()
+                base::IndexInfo {
+                    name: parts[0].to_string(),
+                    columns: vec![],
+                    unique: false,
+                    method: "inverted".to_string(),
+                    size: parts.get(1).and_then(|size| size.parse().ok()),
+                    definition: String::new(),
+                }
             })
             .collect();
         Ok(tables)
     }
 
     fn default_query_string(&self) -> String {
-        return "{\"query\": { \"match_all\": {}}, \"size\": 50}".to_string();
+        format!(
+            "{{\"query\": {{ \"match_all\": {{}}}}, \"size\": {}}}",
+            self.page_size
+        )
     }
 
-    fn query(&self) -> Result<base::QueryResult> {
-        let mut query = self.default_query_string();
-        if let Some(custom_query) = self.query.clone() {
-            query = custom_query;
-        }
-
-        let client = self.get_client()?;
-        let url = self.get_api_url(&format!("{}/_search", self.get_selected_index()?));
-        let response = client
-            .post(&url)
-            .body(query.clone())
-            .header(
-                reqwest::header::CONTENT_TYPE,
-                reqwest::header::HeaderValue::from_static("application/json"),
-            )
-            .send()?;
-        let body = response.text()?;
-        let result: serde_json::Value = serde_json::from_str(&body)?;
+    fn execute(&self, query: &str, _params: &base::QueryParams) -> Result<base::QueryResult> {
+        self.search(query, None)
+    }
 
-        let mut columns = vec![];
-        match self.selections.get(&types::WindowTypeID::COLUMNS) {
-            Some(value) => {
-                if !value.is_empty() {
-                    columns = value.clone();
-                }
-            }
-            None => {}
-        }
-        if columns.is_empty() {
-            columns = self.list_columns()?;
+    fn fetch_page(
+        &self,
+        statement: &str,
+        _params: &base::QueryParams,
+        cursor: &base::PageCursor,
+    ) -> Result<base::QueryResult> {
+        match cursor {
+            base::PageCursor::SearchAfter(after) => self.search(statement, Some(after)),
+            _ => Err(anyhow::anyhow!("Unsupported page cursor {:?}", cursor)),
         }
-
-        let rows: Vec<base::QueryResultRow> = result["hits"]["hits"]
-            .as_array()
-            .unwrap()
-            .iter()
-            .map(|row| {
-                let mut data = vec![];
-                let raw_data = row["_source"].as_object().unwrap();
-                for column in columns.iter() {
-                    data.push(raw_data[column].to_string());
-                }
-                base::QueryResultRow {
-                    id: row["_id"].to_string(),
-                    data: data
-                        .iter()
-                        .map(|d| Some(d.clone().trim_matches('"').to_string()))
-                        .collect(),
-                }
-            })
-            .collect();
-
-        Ok(base::QueryResult {
-            columns: columns,
-            rows: rows,
-        })
     }
 
     fn list_schemas(&self) -> Result<Vec<base::Schema>> {
@@ -111,41 +107,122 @@ impl base::ConnectionType for ElasticSearchDatabase {
         Ok(vec![])
     }
 
-    fn list_columns(&self) -> Result<Vec<String>> {
+    fn list_columns(&self) -> Result<Vec<base::ColumnInfo>> {
         let client = self.get_client()?;
         let url = self.get_api_url(&format!("{}/_mapping", self.get_selected_index()?));
         let response = client.get(&url).send()?;
         let body = response.text()?;
         let result: serde_json::Value = serde_json::from_str(&body)?;
-        let columns: Vec<String> = result[self.get_selected_index()?.as_str()]["mappings"]
+        let columns: Vec<base::ColumnInfo> = result[self.get_selected_index()?.as_str()]
+            ["mappings"]
             .as_object()
             .unwrap()
             .values()
-            .map(|v| {
+            .flat_map(|v| {
                 v.as_object()
                     .unwrap()
-                    .keys()
-                    .map(|k| k.to_string())
-                    .collect::<Vec<String>>()
+                    .iter()
+                    .map(|(k, mapping)| base::ColumnInfo {
+                        data_type: mapping["type"].as_str().unwrap_or("object").to_string(),
+                        ..base::ColumnInfo::named(k.to_string())
+                    })
+                    .collect::<Vec<base::ColumnInfo>>()
             })
-            .flatten()
             .collect();
         Ok(columns)
     }
+
+    /// Indexes the rows as documents with one `_bulk` request, ids are left to ElasticSearch.
+    fn import_rows(
+        &self,
+        table: &str,
+        columns: &[String],
+        rows: &[Vec<base::Value>],
+    ) -> Result<u64> {
+        let mut body = String::new();
+        for row in rows {
+            let document: serde_json::Map<String, serde_json::Value> = columns
+                .iter()
+                .zip(row.iter())
+                .map(|(column, value)| (column.clone(), value.to_json()))
+                .collect();
+            body.push_str("{\"index\": {}}\n");
+            body.push_str(&serde_json::Value::Object(document).to_string());
+            body.push('\n');
+        }
+        let client = self.get_client()?;
+        // refreshed right away so the documents show up when browsing the index
+        let url = self.get_api_url(&format!("{}/_bulk?refresh=true", table));
+        let response = client
+            .post(&url)
+            .body(body)
+            .header(
+                reqwest::header::CONTENT_TYPE,
+                reqwest::header::HeaderValue::from_static("application/x-ndjson"),
+            )
+            .send()?
+            .text()?;
+        let result: serde_json::Value = serde_json::from_str(&response)?;
+        let items = result["items"]
+            .as_array()
+            .ok_or(anyhow::anyhow!("Unexpected bulk response: {}", response))?;
+        if result["errors"].as_bool() == Some(true) {
+            let reason = items
+                .iter()
+                .find_map(|item| item["index"]["error"]["reason"].as_str())
+                .unwrap_or("unknown");
+            return Err(anyhow::anyhow!("Indexing documents failed: {}", reason));
+        }
+        Ok(items.len() as u64)
+    }
+
+    fn get_definition(&self, kind: base::ObjectKind, id: &str) -> Result<String> {
+        if ![base::ObjectKind::Table, base::ObjectKind::Index].contains(&kind) {
+            return Err(anyhow::anyhow!("ElasticSearch has no {:?} objects", kind));
+        }
+        let client = self.get_client()?;
+        let mut body = serde_json::Map::new();
+        for (part, path) in [("settings", "_settings"), ("mappings", "_mapping")] {
+            let url = self.get_api_url(&format!("{}/{}", id, path));
+            let response: serde_json::Value =
+                serde_json::from_str(&client.get(&url).send()?.text()?)?;
+            let mut value = response[id][part].clone();
+            if value.is_null() {
+                return Err(anyhow::anyhow!(
+                    "Unexpected {} response: {}",
+                    path,
+                    response
+                ));
+            }
+            // generated by the cluster, an index can't be created with them
+            if let Some(index) = value["index"].as_object_mut() {
+                for key in ["uuid", "creation_date", "provided_name", "version"] {
+                    index.remove(key);
+                }
+            }
+            body.insert(part.to_string(), value);
+        }
+        Ok(format!(
+            "PUT {}\n{}",
+            id,
+            serde_json::to_string_pretty(&serde_json::Value::Object(body))?
+        ))
+    }
 }
 
 impl ElasticSearchDatabase {
     pub fn new(
         config: Connection,
         selections: HashMap<types::WindowTypeID, Vec<String>>,
-        query: Option<String>,
+        page_size: usize,
+        session: Arc<Session<reqwest::blocking::Client>>,
     ) -> Result<Self> {
         let dsn = Dsn::from_str(&config.dsn)?;
         Ok(ElasticSearchDatabase {
-            name: config.name,
-            dsn: dsn,
+            dsn,
             selections,
-            query,
+            page_size,
+            session,
         })
     }
 
@@ -171,7 +248,126 @@ impl ElasticSearchDatabase {
         Ok(index.unwrap())
     }
 
+    fn search(
+        &self,
+        query: &str,
+        search_after: Option<&serde_json::Value>,
+    ) -> Result<base::QueryResult> {
+        let mut search: serde_json::Value = serde_json::from_str(query)?;
+        let body = search
+            .as_object_mut()
+            .ok_or(anyhow::anyhow!("The search body must be a JSON object"))?;
+        body.entry("size").or_insert(self.page_size.into());
+        // search_after needs a sort, keep relevance order and break ties on doc order
+        body.entry("sort")
+            .or_insert(serde_json::json!(["_score", "_doc"]));
+        if let Some(after) = search_after {
+            body.insert("search_after".to_string(), after.clone());
+        }
+        let page_size = body["size"].as_u64().unwrap_or(self.page_size as u64) as usize;
+
+        let client = self.get_client()?;
+        let url = self.get_api_url(&format!("{}/_search", self.get_selected_index()?));
+        // tag the search so the task can be found and cancelled while it runs
+        let opaque_id = uuid::Uuid::new_v4().to_string();
+        let cancel_client = client.clone();
+        let tasks_url = self.get_api_url("_tasks?actions=*search&detailed=true");
+        let cancel_url = self.get_api_url("_tasks");
+        let cancel_id = opaque_id.clone();
+        self.session.set_canceller(Arc::new(move || {
+            cancel_search(&cancel_client, &tasks_url, &cancel_url, &cancel_id)
+        }));
+        let started = Instant::now();
+        let response = client
+            .post(&url)
+            .body(search.to_string())
+            .header(
+                reqwest::header::CONTENT_TYPE,
+                reqwest::header::HeaderValue::from_static("application/json"),
+            )
+            .header("X-Opaque-Id", opaque_id.as_str())
+            .send()?;
+        let body = response.text()?;
+        let elapsed = started.elapsed();
+        let result: serde_json::Value = serde_json::from_str(&body)?;
+
+        let mut columns = vec![];
+        if let Some(value) = self.selections.get(&types::WindowTypeID::COLUMNS) {
+            if !value.is_empty() {
+                columns = value.clone();
+            }
+        }
+        if columns.is_empty() {
+            columns = self
+                .list_columns()?
+                .into_iter()
+                .map(|column| column.name)
+                .collect();
+        }
+
+        let hits = result["hits"]["hits"]
+            .as_array()
+            .ok_or(anyhow::anyhow!("Unexpected search response: {}", body))?;
+        let rows: Vec<base::QueryResultRow> = hits
+            .iter()
+            .map(|row| {
+                let raw_data = row["_source"].as_object().unwrap();
+                base::QueryResultRow {
+                    data: columns
+                        .iter()
+                        .map(|column| {
+                            raw_data
+                                .get(column)
+                                .map(base::Value::from_json)
+                                .unwrap_or(base::Value::Null)
+                        })
+                        .collect(),
+                }
+            })
+            .collect();
+
+        let mut next_page = None;
+        if hits.len() >= page_size {
+            if let Some(sort) = hits.last().map(|hit| hit["sort"].clone()) {
+                next_page = Some(base::PageCursor::SearchAfter(sort));
+            }
+        }
+
+        let mut outcome = base::ExecutionOutcome::rows();
+        outcome.elapsed = elapsed;
+        if result["timed_out"].as_bool() == Some(true) {
+            outcome
+                .notices
+                .push("search timed out, results are partial".to_string());
+        }
+        if let Some(failures) = result["_shards"]["failures"].as_array() {
+            for failure in failures {
+                outcome.notices.push(format!(
+                    "shard {}: {}",
+                    failure["shard"],
+                    failure["reason"]["reason"].as_str().unwrap_or("unknown")
+                ));
+            }
+        }
+
+        Ok(base::QueryResult {
+            columns,
+            rows,
+            next_page,
+            outcome,
+        })
+    }
+
     fn get_client(&self) -> Result<reqwest::blocking::Client> {
+        // the http client is reference counted and keeps its connection pool alive between clones
+        self.session.run(
+            || self.build_client(),
+            |_| true,
+            |client| Ok(client.clone()),
+        )
+    }
+
+    fn build_client(&self) -> Result<reqwest::blocking::Client> {
         let mut builder = reqwest::blocking::Client::builder();
         if self.dsn.username.is_some() {
             let mut hm = reqwest::header::HeaderMap::new();
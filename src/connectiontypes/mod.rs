@@ -3,5 +3,7 @@ pub mod elasticsearch;
 pub mod mysql;
 pub mod pg;
 pub mod redis;
+pub mod session;
+pub mod splitter;
 pub mod sqlite;
 pub mod utils;
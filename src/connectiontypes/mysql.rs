@@ -1,70 +1,468 @@
+use crate::connectiontypes::session::Session;
+use crate::connectiontypes::splitter;
 use crate::ui::types;
 use crate::{connectiontypes::base, data::Connection};
 use anyhow::{anyhow, Result};
-use chrono::{format, DateTime, NaiveDateTime, Utc};
+use chrono::{Datelike, NaiveDate, NaiveDateTime, Timelike};
 use log::debug;
-use mysql;
+use mysql::consts::{CapabilityFlags, ColumnType};
 use mysql::prelude::*;
 use std::collections::HashMap;
-use uuid::Uuid;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Instant;
+
+// collation id of the `binary` character set, used for BLOB/BINARY columns
+const BINARY_CHARSET: u16 = 63;
+const POOL_MAX_CONNECTIONS: usize = 10;
+
+/// The pool of a session and the connection taken out of it for an open transaction.
+pub struct MySQLConnection {
+    pool: mysql::Pool,
+    // pooled connections are reset when they go back, so a transaction keeps its own
+    transaction: Option<mysql::PooledConn>,
+}
 
 pub struct MySQLDatabase {
-    name: String,
     dsn: String,
     selections: HashMap<types::WindowTypeID, Vec<String>>,
-    query: Option<String>,
+    page_size: usize,
+    session: Arc<Session<MySQLConnection>>,
 }
 
-fn get_row_value(row: &mysql::Row, column: &str) -> Option<String> {
-    let result = row
-        .get::<String, _>(column)
-        .or_else(|| row.get::<Uuid, _>(column).map(|value| value.to_string()))
-        .or_else(|| {
-            row.get_opt::<i32, _>(column)
-                .map(|value| value.unwrap().to_string())
-        })
-        .or_else(|| {
-            row.get_opt::<i64, _>(column)
-                .map(|value| value.unwrap().to_string())
-        })
-        .or_else(|| {
-            row.get_opt::<f32, _>(column)
-                .map(|value| value.unwrap().to_string())
-        })
-        .or_else(|| {
-            row.get_opt::<f64, _>(column)
-                .map(|value| value.unwrap().to_string())
-        })
-        .or_else(|| {
-            row.get_opt::<bool, _>(column)
-                .map(|value| value.unwrap().to_string())
-        });
-    result
+fn parse_text<T: FromStr>(text: &str, wrap: fn(T) -> base::Value) -> base::Value {
+    match text.parse::<T>() {
+        Ok(value) => wrap(value),
+        Err(_) => base::Value::Text(text.to_string()),
+    }
+}
+
+fn get_row_value(row: &mysql::Row, idx: usize) -> base::Value {
+    let column = &row.columns_ref()[idx];
+    let column_type = column.column_type();
+    let raw = match row.as_ref(idx) {
+        Some(value) => value,
+        None => return base::Value::Null,
+    };
+
+    match raw {
+        mysql::Value::NULL => base::Value::Null,
+        mysql::Value::Int(i) => {
+            if column_type == ColumnType::MYSQL_TYPE_TINY && column.column_length() == 1 {
+                base::Value::Bool(*i != 0)
+            } else {
+                base::Value::Int(*i)
+            }
+        }
+        mysql::Value::UInt(u) => match i64::try_from(*u) {
+            Ok(i) => base::Value::Int(i),
+            Err(_) => base::Value::Decimal(u.to_string()),
+        },
+        mysql::Value::Float(f) => base::Value::Float(*f as f64),
+        mysql::Value::Double(f) => base::Value::Float(*f),
+        mysql::Value::Date(year, month, day, hour, minute, second, micros) => {
+            let date = NaiveDate::from_ymd_opt(*year as i32, *month as u32, *day as u32);
+            match (column_type, date) {
+                (ColumnType::MYSQL_TYPE_DATE, Some(date)) => base::Value::Date(date),
+                (_, Some(date)) => match date.and_hms_micro_opt(
+                    *hour as u32,
+                    *minute as u32,
+                    *second as u32,
+                    *micros,
+                ) {
+                    Some(ts) => base::Value::Timestamp(ts),
+                    None => base::Value::Text(raw.as_sql(true).trim_matches('\'').to_string()),
+                },
+                // zero dates such as 0000-00-00 have no chrono equivalent
+                _ => base::Value::Text(raw.as_sql(true).trim_matches('\'').to_string()),
+            }
+        }
+        mysql::Value::Time(..) => {
+            base::Value::Text(raw.as_sql(true).trim_matches('\'').to_string())
+        }
+        mysql::Value::Bytes(bytes) => {
+            // the text protocol sends every value as bytes, use the column type to decode them
+            let text = match std::str::from_utf8(bytes) {
+                Ok(text) => text,
+                Err(_) => return base::Value::Bytes(bytes.clone()),
+            };
+            match column_type {
+                ColumnType::MYSQL_TYPE_TINY if column.column_length() == 1 => {
+                    base::Value::Bool(text != "0")
+                }
+                ColumnType::MYSQL_TYPE_TINY
+                | ColumnType::MYSQL_TYPE_SHORT
+                | ColumnType::MYSQL_TYPE_INT24
+                | ColumnType::MYSQL_TYPE_LONG
+                | ColumnType::MYSQL_TYPE_LONGLONG
+                | ColumnType::MYSQL_TYPE_YEAR => match text.parse::<i64>() {
+                    Ok(i) => base::Value::Int(i),
+                    Err(_) => base::Value::Decimal(text.to_string()),
+                },
+                ColumnType::MYSQL_TYPE_FLOAT | ColumnType::MYSQL_TYPE_DOUBLE => {
+                    parse_text(text, base::Value::Float)
+                }
+                ColumnType::MYSQL_TYPE_DECIMAL | ColumnType::MYSQL_TYPE_NEWDECIMAL => {
+                    base::Value::Decimal(text.to_string())
+                }
+                ColumnType::MYSQL_TYPE_DATE | ColumnType::MYSQL_TYPE_NEWDATE => {
+                    parse_text(text, base::Value::Date)
+                }
+                ColumnType::MYSQL_TYPE_DATETIME
+                | ColumnType::MYSQL_TYPE_DATETIME2
+                | ColumnType::MYSQL_TYPE_TIMESTAMP
+                | ColumnType::MYSQL_TYPE_TIMESTAMP2 => {
+                    match NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S%.f") {
+                        Ok(ts) => base::Value::Timestamp(ts),
+                        Err(_) => base::Value::Text(text.to_string()),
+                    }
+                }
+                ColumnType::MYSQL_TYPE_JSON => match serde_json::from_str(text) {
+                    Ok(json) => base::Value::Json(json),
+                    Err(_) => base::Value::Text(text.to_string()),
+                },
+                ColumnType::MYSQL_TYPE_BIT | ColumnType::MYSQL_TYPE_GEOMETRY => {
+                    base::Value::Bytes(bytes.clone())
+                }
+                ColumnType::MYSQL_TYPE_TINY_BLOB
+                | ColumnType::MYSQL_TYPE_MEDIUM_BLOB
+                | ColumnType::MYSQL_TYPE_LONG_BLOB
+                | ColumnType::MYSQL_TYPE_BLOB
+                | ColumnType::MYSQL_TYPE_VAR_STRING
+                | ColumnType::MYSQL_TYPE_STRING
+                    if column.character_set() == BINARY_CHARSET =>
+                {
+                    base::Value::Bytes(bytes.clone())
+                }
+                _ => base::Value::Text(text.to_string()),
+            }
+        }
+    }
+}
+
+fn to_query_result(raw_rows: Vec<mysql::Row>) -> base::QueryResult {
+    if raw_rows.is_empty() {
+        return base::QueryResult::empty();
+    }
+
+    let columns: Vec<String> = raw_rows
+        .first()
+        .unwrap()
+        .columns_ref()
+        .iter()
+        .map(|c| c.name_str().to_string())
+        .collect();
+    if columns.is_empty() {
+        return base::QueryResult::empty();
+    }
+
+    let mut rows = Vec::new();
+    for row in raw_rows.iter() {
+        let row_data: Vec<base::Value> = (0..columns.len())
+            .map(|idx| get_row_value(row, idx))
+            .collect();
+        rows.push(base::QueryResultRow { data: row_data });
+    }
+
+    base::QueryResult {
+        columns,
+        rows,
+        next_page: None,
+        outcome: base::ExecutionOutcome::rows(),
+    }
+}
+
+fn to_mysql_value(value: &base::Value) -> mysql::Value {
+    match value {
+        base::Value::Null => mysql::Value::NULL,
+        base::Value::Bool(b) => mysql::Value::Int(*b as i64),
+        base::Value::Int(i) => mysql::Value::Int(*i),
+        base::Value::Float(f) => mysql::Value::Double(*f),
+        base::Value::Bytes(bytes) => mysql::Value::Bytes(bytes.clone()),
+        base::Value::Date(date) => mysql::Value::Date(
+            date.year() as u16,
+            date.month() as u8,
+            date.day() as u8,
+            0,
+            0,
+            0,
+            0,
+        ),
+        base::Value::Time(time) => mysql::Value::Time(
+            false,
+            0,
+            time.hour() as u8,
+            time.minute() as u8,
+            time.second() as u8,
+            time.nanosecond() / 1000,
+        ),
+        base::Value::Timestamp(ts) => mysql::Value::Date(
+            ts.year() as u16,
+            ts.month() as u8,
+            ts.day() as u8,
+            ts.hour() as u8,
+            ts.minute() as u8,
+            ts.second() as u8,
+            ts.nanosecond() / 1000,
+        ),
+        base::Value::TimestampTz(ts) => to_mysql_value(&base::Value::Timestamp(ts.naive_utc())),
+        value => mysql::Value::Bytes(value.to_string().into_bytes()),
+    }
+}
+
+fn to_mysql_params(params: &base::QueryParams) -> mysql::Params {
+    if params.is_empty() {
+        return mysql::Params::Empty;
+    }
+    if params.iter().any(|(name, _)| name.starts_with(':')) {
+        return mysql::Params::Named(
+            params
+                .iter()
+                .map(|(name, value)| {
+                    (
+                        name.trim_start_matches(':').as_bytes().to_vec(),
+                        to_mysql_value(value),
+                    )
+                })
+                .collect(),
+        );
+    }
+    mysql::Params::Positional(params.iter().map(|(_, v)| to_mysql_value(v)).collect())
+}
+
+fn execute(
+    conn: &mut mysql::PooledConn,
+    query: &str,
+    params: &base::QueryParams,
+) -> Result<base::QueryResult> {
+    // statements without parameters keep to the text protocol, which runs anything
+    let mut query_result = if params.is_empty() {
+        read_result(conn.query_iter(query)?, query)?
+    } else {
+        read_result(conn.exec_iter(query, to_mysql_params(params))?, query)?
+    };
+    query_result.outcome.notices = take_warnings(conn)?;
+    Ok(query_result)
+}
+
+fn read_result<P: Protocol>(
+    mut result: mysql::QueryResult<'_, '_, '_, P>,
+    query: &str,
+) -> Result<base::QueryResult> {
+    let has_columns = !result.columns().as_ref().is_empty();
+    let affected = result.affected_rows();
+    let last_insert_id = result.last_insert_id();
+    let raw_rows = match result.iter() {
+        Some(set) => set.collect::<std::result::Result<Vec<mysql::Row>, _>>()?,
+        None => vec![],
+    };
+    drop(result);
+
+    let mut query_result = if has_columns {
+        to_query_result(raw_rows)
+    } else {
+        base::QueryResult {
+            outcome: base::ExecutionOutcome::command(base::command_tag(query), Some(affected)),
+            ..base::QueryResult::empty()
+        }
+    };
+    query_result.outcome.last_insert_id = last_insert_id.filter(|id| *id > 0);
+    Ok(query_result)
+}
+
+// duplicate or invalid column names and options that are not allowed in a derived table,
+// ER_DUP_FIELDNAME, ER_WRONG_COLUMN_NAME, ER_WRONG_USAGE and ER_CANT_USE_OPTION_HERE
+const DERIVED_TABLE_ERRORS: [u16; 4] = [1060, 1166, 1221, 1234];
+
+/// Whether the paging wrapper rather than the query itself was rejected.
+fn rejected_by_derived_table(err: &anyhow::Error) -> bool {
+    matches!(
+        err.downcast_ref::<mysql::Error>(),
+        Some(mysql::Error::MySqlError(err)) if DERIVED_TABLE_ERRORS.contains(&err.code)
+    )
+}
+
+fn take_warnings(conn: &mut mysql::PooledConn) -> Result<Vec<String>> {
+    if conn.warnings() == 0 {
+        return Ok(vec![]);
+    }
+    Ok(conn.query_map(
+        "SHOW WARNINGS",
+        |(level, code, message): (String, u32, String)| format!("{} {}: {}", level, code, message),
+    )?)
+}
+
+// mysql reports costs as strings, e.g. `"query_cost": "1.25"`
+fn plan_number(value: &serde_json::Value) -> Option<f64> {
+    value
+        .as_f64()
+        .or_else(|| value.as_str().and_then(|v| v.parse().ok()))
+}
+
+/// Converts an operation of `EXPLAIN FORMAT=JSON` output, e.g. a `query_block`, a
+/// `table` or an `ordering_operation`, along with the operations nested in it.
+fn to_plan_node(name: &str, plan: &serde_json::Value) -> base::PlanNode {
+    let cost_info = &plan["cost_info"];
+    let mut node_type = name.replace('_', " ");
+    if let Some(table) = plan["table_name"].as_str() {
+        node_type = format!(
+            "table {} ({})",
+            table,
+            plan["access_type"].as_str().unwrap_or("unknown")
+        );
+    }
+    let mut detail = vec![];
+    if let Some(key) = plan["key"].as_str() {
+        detail.push(format!("key: {}", key));
+    }
+    if let Some(condition) = plan["attached_condition"].as_str() {
+        detail.push(format!("condition: {}", condition));
+    }
+    let mut children = vec![];
+    if let Some(fields) = plan.as_object() {
+        for (key, value) in fields {
+            match value {
+                serde_json::Value::Object(_) if key != "cost_info" => {
+                    children.push(to_plan_node(key, value))
+                }
+                // e.g. nested_loop or query_specifications, a list of operations
+                serde_json::Value::Array(items) => {
+                    for item in items.iter().filter_map(|item| item.as_object()) {
+                        for (key, value) in item.iter().filter(|(_, value)| value.is_object()) {
+                            children.push(to_plan_node(key, value));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    base::PlanNode {
+        node_type,
+        detail: detail.join("  "),
+        cost: ["query_cost", "prefix_cost", "sort_cost"]
+            .iter()
+            .find_map(|key| plan_number(&cost_info[*key])),
+        rows: plan_number(&plan["rows_produced_per_join"])
+            .or_else(|| plan_number(&plan["rows_examined_per_scan"])),
+        actual_rows: None,
+        actual_time: None,
+        children,
+    }
+}
+
+fn quote_identifier(name: &str) -> String {
+    format!("`{}`", name.replace('`', "``"))
 }
 
 impl base::ConnectionType for MySQLDatabase {
     fn list_tables(&self) -> Result<Vec<base::Table>> {
-        let tables: Vec<base::Table> =
-            self.get_client()?
-                .query_map("SHOW TABLES;", |table: mysql::Row| -> base::Table {
-                    let tbl = table.get(0).unwrap_or("missing".to_string());
-                    base::Table {
-                        id: tbl.clone(),
-                        name: tbl,
-                    }
-                })?;
+        let query = "
+SELECT TABLE_NAME,
+       CASE TABLE_TYPE WHEN 'BASE TABLE' THEN 'table' ELSE LOWER(TABLE_TYPE) END,
+       TABLE_ROWS,
+       DATA_LENGTH + INDEX_LENGTH,
+       TABLE_COMMENT
+FROM information_schema.TABLES
+WHERE TABLE_SCHEMA = DATABASE()
+ORDER BY TABLE_NAME";
+        let tables: Vec<base::Table> = self.with_client(|conn| {
+            Ok(conn.query_map(query, |table: mysql::Row| -> base::Table {
+                let tbl = table.get(0).unwrap_or("missing".to_string());
+                base::Table {
+                    id: tbl.clone(),
+                    name: tbl,
+                    kind: table.get(1).unwrap_or_default(),
+                    rows: table.get(2).flatten(),
+                    size: table.get(3).flatten(),
+                    owner: None,
+                    // views have the comment `VIEW`
+                    comment: table
+                        .get::<Option<String>, _>(4)
+                        .flatten()
+                        .filter(|comment| !comment.is_empty() && comment != "VIEW"),
+                }
+            })?)
+        })?;
         Ok(tables)
     }
 
+    fn split_script(&self, script: &str) -> Vec<String> {
+        splitter::split_statements(script, splitter::Dialect::MySQL)
+    }
+
+    fn placeholders(&self, statement: &str) -> Vec<String> {
+        splitter::find_placeholders(statement, splitter::Dialect::MySQL)
+    }
+
+    fn supports_transactions(&self) -> bool {
+        true
+    }
+
+    fn supports_order_by(&self) -> bool {
+        true
+    }
+
+    fn supports_where(&self) -> bool {
+        true
+    }
+
+    fn begin_transaction(&self) -> Result<()> {
+        self.with_client(|conn| {
+            conn.query_drop("START TRANSACTION")?;
+            self.session.set_in_transaction(true);
+            Ok(())
+        })
+    }
+
+    fn commit_transaction(&self) -> Result<()> {
+        self.with_client(|conn| {
+            conn.query_drop("COMMIT")?;
+            self.session.set_in_transaction(false);
+            Ok(())
+        })
+    }
+
+    fn rollback_transaction(&self) -> Result<()> {
+        self.with_client(|conn| {
+            conn.query_drop("ROLLBACK")?;
+            self.session.set_in_transaction(false);
+            Ok(())
+        })
+    }
+
+    fn explain(
+        &self,
+        statement: &str,
+        params: &base::QueryParams,
+        analyze: bool,
+    ) -> Result<base::PlanNode> {
+        if analyze {
+            // EXPLAIN ANALYZE only reports a text tree
+            return Err(anyhow!("MySQL can not analyze the plan, turn ANALYZE off"));
+        }
+        let explain = format!(
+            "EXPLAIN FORMAT=JSON {}",
+            statement.trim().trim_end_matches(';')
+        );
+        let plan: Option<String> = self.with_client(|conn| {
+            Ok(if params.is_empty() {
+                conn.query_first(&explain)?
+            } else {
+                conn.exec_first(&explain, to_mysql_params(params))?
+            })
+        })?;
+        let plan: serde_json::Value =
+            serde_json::from_str(&plan.ok_or(anyhow!("The server returned no plan"))?)?;
+        Ok(to_plan_node("query_block", &plan["query_block"]))
+    }
+
     fn default_query_string(&self) -> String {
         let mut columns = vec!["*".to_string()];
-        match self.selections.get(&types::WindowTypeID::COLUMNS) {
-            Some(value) => {
-                if !value.is_empty() {
-                    columns = value.clone();
-                }
+        if let Some(value) = self.selections.get(&types::WindowTypeID::COLUMNS) {
+            if !value.is_empty() {
+                columns = value.clone();
             }
-            None => {}
         }
         let query = format!(
             "SELECT {} FROM {}",
@@ -72,91 +470,303 @@ impl base::ConnectionType for MySQLDatabase {
             self.get_selection(types::WindowTypeID::TABLES)
                 .unwrap_or("_unselected_".to_string())
         );
-        format!("{} LIMIT {}", query, 50)
+        query
     }
 
-    fn query(&self) -> Result<base::QueryResult> {
-        let mut query = self.default_query_string();
-        if let Some(custom_query) = self.query.clone() {
-            query = custom_query;
-        }
-        debug!("List tables query: {:?}", query);
-
-        let raw_rows = self.get_client()?.query_map(query, |row: mysql::Row| row)?;
-        if raw_rows.is_empty() {
-            return Ok(base::QueryResult {
-                columns: vec![],
-                rows: vec![],
-            });
-        }
-
-        let columns: Vec<String> = raw_rows
-            .first()
-            .unwrap()
-            .columns_ref()
-            .iter()
-            .map(|c| c.name_str().to_string())
-            .collect();
+    fn execute(&self, query: &str, params: &base::QueryParams) -> Result<base::QueryResult> {
+        debug!("Execute query: {:?}", query);
 
-        let mut rows = Vec::new();
-        if columns.is_empty() {
-            return Ok(base::QueryResult {
-                columns: vec![],
-                rows: vec![],
-            });
-        }
-        for row in raw_rows.iter() {
-            let mut row_data = Vec::new();
-            for column in columns.iter() {
-                row_data.push(get_row_value(row, column));
+        let started = Instant::now();
+        let mut result = None;
+        if base::pageable_query(query, splitter::Dialect::MySQL).is_some() {
+            match self.query_page(query, params, 0) {
+                Ok(page) => result = Some(page),
+                // derived tables reject some valid selects, e.g. duplicate column names
+                Err(err) if rejected_by_derived_table(&err) => {
+                    debug!("Paging query failed, running it unpaged: {:?}", err)
+                }
+                Err(err) => return Err(err),
             }
-            rows.push(base::QueryResultRow {
-                id: get_row_value(row, columns.get(0).unwrap()).unwrap_or("missing".to_string()),
-                data: row_data,
-            });
         }
+        let mut result = match result {
+            Some(result) => result,
+            None => self.with_client(|conn| {
+                let result = execute(conn, query, params)?;
+                if let Some(open) = base::transaction_change(query) {
+                    self.session.set_in_transaction(open);
+                }
+                Ok(result)
+            })?,
+        };
+        result.outcome.elapsed = started.elapsed();
+        Ok(result)
+    }
 
-        Ok(base::QueryResult { columns, rows })
+    fn fetch_page(
+        &self,
+        statement: &str,
+        params: &base::QueryParams,
+        cursor: &base::PageCursor,
+    ) -> Result<base::QueryResult> {
+        match cursor {
+            base::PageCursor::Offset(offset) => self.query_page(statement, params, *offset),
+            _ => Err(anyhow!("Unsupported page cursor {:?}", cursor)),
+        }
     }
 
     fn list_schemas(&self) -> Result<Vec<base::Schema>> {
-        let schemas: Vec<base::Schema> = self.get_client()?.query_map(
-            "SHOW schemas;",
-            |schema: mysql::Row| -> base::Schema {
-                let sch = schema.get(0).unwrap_or("missing".to_string());
-                base::Schema {
-                    id: sch.clone(),
-                    name: sch,
-                }
-            },
-        )?;
+        let schemas: Vec<base::Schema> = self.with_client(|conn| {
+            Ok(
+                conn.query_map("SHOW schemas;", |schema: mysql::Row| -> base::Schema {
+                    let sch = schema.get(0).unwrap_or("missing".to_string());
+                    base::Schema {
+                        id: sch.clone(),
+                        name: sch,
+                    }
+                })?,
+            )
+        })?;
         Ok(schemas)
     }
 
     fn list_databases(&self) -> Result<Vec<base::DatabaseInfo>> {
         let query = "SHOW databases;";
-        let raw_databases = self.get_client()?.query_map(query, |db: mysql::Row| {
-            let dbv: String = db.get(0).unwrap_or("missing".to_string());
-            base::DatabaseInfo {
-                id: dbv.clone(),
-                name: dbv,
-            }
+        let raw_databases = self.with_client(|conn| {
+            Ok(conn.query_map(query, |db: mysql::Row| {
+                let dbv: String = db.get(0).unwrap_or("missing".to_string());
+                base::DatabaseInfo {
+                    id: dbv.clone(),
+                    name: dbv,
+                }
+            })?)
         })?;
         Ok(raw_databases)
     }
 
-    fn list_columns(&self) -> Result<Vec<String>> {
-        let table_name = &self
+    fn list_columns(&self) -> Result<Vec<base::ColumnInfo>> {
+        let query = "
+SELECT c.COLUMN_NAME,
+       c.COLUMN_TYPE,
+       c.IS_NULLABLE = 'YES',
+       c.COLUMN_DEFAULT,
+       c.COLUMN_KEY = 'PRI',
+       (SELECT GROUP_CONCAT(CONCAT(k.REFERENCED_TABLE_NAME, '(', k.REFERENCED_COLUMN_NAME, ')')
+                            SEPARATOR ', ')
+        FROM information_schema.KEY_COLUMN_USAGE k
+        WHERE k.TABLE_SCHEMA = c.TABLE_SCHEMA AND k.TABLE_NAME = c.TABLE_NAME
+          AND k.COLUMN_NAME = c.COLUMN_NAME AND k.REFERENCED_TABLE_NAME IS NOT NULL),
+       c.COLUMN_COMMENT
+FROM information_schema.COLUMNS c
+WHERE c.TABLE_SCHEMA = DATABASE() AND c.TABLE_NAME = ?
+ORDER BY c.ORDINAL_POSITION";
+        let table_name = self
             .get_selection(types::WindowTypeID::TABLES)
             .unwrap_or("public".to_string());
-        let query = format!("describe {}", table_name);
-        let raw_columns = self.get_client()?.query_map(query, |cols: mysql::Row| {
-            cols.get(0).unwrap_or("missing".to_string())
+        let raw_columns = self.with_client(|conn| {
+            Ok(
+                conn.exec_map(query, (table_name.as_str(),), |cols: mysql::Row| {
+                    base::ColumnInfo {
+                        name: cols.get(0).unwrap_or("missing".to_string()),
+                        data_type: cols.get(1).unwrap_or_default(),
+                        nullable: cols.get(2).unwrap_or(true),
+                        default: cols.get(3).flatten(),
+                        primary_key: cols.get(4).unwrap_or(false),
+                        foreign_key: cols.get(5).flatten(),
+                        comment: cols
+                            .get::<Option<String>, _>(6)
+                            .flatten()
+                            .filter(|comment| !comment.is_empty()),
+                    }
+                })?,
+            )
         })?;
         Ok(raw_columns)
     }
-    fn list_indexes(&self) -> Result<Vec<String>> {
-        Ok(vec![])
+    fn list_indexes(&self) -> Result<Vec<base::IndexInfo>> {
+        let table = self
+            .get_selection(types::WindowTypeID::TABLES)
+            .ok_or(anyhow!("Select a table to list its indexes"))?;
+        let (raw_indexes, sizes) = self.with_client(|conn| {
+            let raw_indexes: Vec<mysql::Row> =
+                conn.query(format!("SHOW INDEX FROM {}", quote_identifier(&table)))?;
+            // innodb keeps index sizes in pages, reading them needs access to the mysql schema
+            let sizes: HashMap<String, u64> = conn
+                .exec::<mysql::Row, _, _>(
+                    "SELECT index_name, stat_value * @@innodb_page_size
+FROM mysql.innodb_index_stats
+WHERE database_name = DATABASE() AND table_name = ? AND stat_name = 'size'",
+                    (table.as_str(),),
+                )
+                .unwrap_or_else(|err| {
+                    debug!("Failed to read index sizes: {:?}", err);
+                    vec![]
+                })
+                .into_iter()
+                .filter_map(|row| mysql::from_row_opt::<(String, u64)>(row).ok())
+                .collect();
+            Ok((raw_indexes, sizes))
+        })?;
+
+        // SHOW INDEX lists one row per column of an index, in order
+        let mut indexes: Vec<base::IndexInfo> = vec![];
+        for row in raw_indexes {
+            let name: String = row.get("Key_name").unwrap_or_default();
+            let column = match row.get::<Option<String>, _>("Column_name").flatten() {
+                Some(column) => match row.get::<Option<u64>, _>("Sub_part").flatten() {
+                    Some(length) => format!("`{}`({})", column, length),
+                    None => format!("`{}`", column),
+                },
+                // functional key parts have an expression instead of a column
+                None => format!(
+                    "({})",
+                    row.get::<Option<String>, _>("Expression")
+                        .flatten()
+                        .unwrap_or_default()
+                ),
+            };
+            if let Some(index) = indexes.iter_mut().find(|index| index.name == name) {
+                index.columns.push(column);
+                continue;
+            }
+            indexes.push(base::IndexInfo {
+                columns: vec![column],
+                unique: row.get::<u64, _>("Non_unique") == Some(0),
+                method: row.get("Index_type").unwrap_or_default(),
+                size: sizes.get(&name).cloned(),
+                definition: String::new(),
+                name,
+            });
+        }
+        for index in indexes.iter_mut() {
+            let columns = index.columns.join(", ");
+            index.definition = if index.name == "PRIMARY" {
+                format!("PRIMARY KEY ({})", columns)
+            } else {
+                format!(
+                    "CREATE {}INDEX `{}` ON `{}` ({}) USING {}",
+                    if index.unique { "UNIQUE " } else { "" },
+                    index.name,
+                    table,
+                    columns,
+                    index.method
+                )
+            };
+        }
+        Ok(indexes)
+    }
+    fn quote_identifier(&self, name: &str) -> String {
+        quote_identifier(name)
+    }
+
+    fn sql_literal(&self, value: &base::Value) -> String {
+        match value {
+            base::Value::Null
+            | base::Value::Bool(_)
+            | base::Value::Int(_)
+            | base::Value::Float(_)
+            | base::Value::Decimal(_)
+            | base::Value::Bytes(_) => value.to_sql_literal(),
+            // a backslash starts an escape sequence in mysql strings
+            value => base::quote_text(&value.to_string(), true),
+        }
+    }
+
+    fn list_primary_key(&self, table: &str) -> Result<Vec<String>> {
+        let query = "
+SELECT COLUMN_NAME
+FROM information_schema.KEY_COLUMN_USAGE
+WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ? AND CONSTRAINT_NAME = 'PRIMARY'
+ORDER BY ORDINAL_POSITION";
+        self.with_client(|conn| Ok(conn.exec(query, (table,))?))
+    }
+
+    fn list_foreign_keys(&self, table: &str) -> Result<Vec<base::ForeignKey>> {
+        let query = "
+SELECT CONSTRAINT_NAME, TABLE_NAME, COLUMN_NAME, REFERENCED_TABLE_NAME, REFERENCED_COLUMN_NAME
+FROM information_schema.KEY_COLUMN_USAGE
+WHERE TABLE_SCHEMA = DATABASE() AND REFERENCED_TABLE_SCHEMA = DATABASE()
+  AND (TABLE_NAME = ? OR REFERENCED_TABLE_NAME = ?)
+ORDER BY TABLE_NAME, CONSTRAINT_NAME, ORDINAL_POSITION";
+        let pairs = self.with_client(|conn| Ok(conn.exec(query, (table, table))?))?;
+        Ok(base::ForeignKey::from_column_pairs(pairs))
+    }
+
+    fn list_views(&self) -> Result<Vec<base::SchemaObject>> {
+        self.list_objects(
+            "
+SELECT TABLE_NAME, TABLE_NAME, 'view', DEFINER
+FROM information_schema.VIEWS
+WHERE TABLE_SCHEMA = DATABASE()
+ORDER BY TABLE_NAME",
+        )
+    }
+
+    fn list_functions(&self) -> Result<Vec<base::SchemaObject>> {
+        // SHOW CREATE needs to know whether it is a function or a procedure
+        self.list_objects(
+            "
+SELECT CONCAT(ROUTINE_TYPE, ' ', ROUTINE_NAME),
+       ROUTINE_NAME,
+       LOWER(ROUTINE_TYPE),
+       CONCAT('returns ', DTD_IDENTIFIER)
+FROM information_schema.ROUTINES
+WHERE ROUTINE_SCHEMA = DATABASE()
+ORDER BY ROUTINE_NAME",
+        )
+    }
+
+    fn list_triggers(&self) -> Result<Vec<base::SchemaObject>> {
+        self.list_objects(
+            "
+SELECT TRIGGER_NAME, TRIGGER_NAME, CONCAT(ACTION_TIMING, ' ', EVENT_MANIPULATION), EVENT_OBJECT_TABLE
+FROM information_schema.TRIGGERS
+WHERE TRIGGER_SCHEMA = DATABASE()
+ORDER BY EVENT_OBJECT_TABLE, TRIGGER_NAME",
+        )
+    }
+
+    fn get_definition(&self, kind: base::ObjectKind, id: &str) -> Result<String> {
+        // the column holding the statement differs between the SHOW CREATE variants
+        let (query, column) = match kind {
+            base::ObjectKind::View => (format!("SHOW CREATE VIEW {}", quote_identifier(id)), 1),
+            base::ObjectKind::Function => {
+                let (routine, name) = id
+                    .split_once(' ')
+                    .ok_or(anyhow!("Unknown routine {}", id))?;
+                (
+                    format!("SHOW CREATE {} {}", routine, quote_identifier(name)),
+                    2,
+                )
+            }
+            base::ObjectKind::Trigger => {
+                (format!("SHOW CREATE TRIGGER {}", quote_identifier(id)), 2)
+            }
+            base::ObjectKind::Table => (format!("SHOW CREATE TABLE {}", quote_identifier(id)), 1),
+            // there is no SHOW CREATE INDEX, the listing already rebuilds the statement
+            base::ObjectKind::Index => {
+                let index = self
+                    .list_indexes()?
+                    .into_iter()
+                    .find(|index| index.name == id)
+                    .ok_or(anyhow!("The index {} no longer exists", id))?;
+                if index.name == "PRIMARY" {
+                    let table = self.get_selection(types::WindowTypeID::TABLES);
+                    return Ok(format!(
+                        "ALTER TABLE {} ADD {};",
+                        quote_identifier(&table.unwrap_or_default()),
+                        index.definition
+                    ));
+                }
+                return Ok(format!("{};", index.definition));
+            }
+            _ => return Err(anyhow!("MySQL has no {:?} objects", kind)),
+        };
+        let row: Option<mysql::Row> = self.with_client(|conn| Ok(conn.query_first(&query)?))?;
+        let definition: Option<String> = row.and_then(|row| row.get(column)).flatten();
+        // the statement is left out without the privileges to see it
+        definition
+            .map(|definition| format!("{};", definition))
+            .ok_or(anyhow!("The definition of {} is not available", id))
     }
 }
 
@@ -164,13 +774,66 @@ impl MySQLDatabase {
     pub fn new(
         config: Connection,
         selections: HashMap<types::WindowTypeID, Vec<String>>,
-        query: Option<String>,
+        page_size: usize,
+        session: Arc<Session<MySQLConnection>>,
     ) -> Result<Self> {
         Ok(MySQLDatabase {
-            name: config.name,
             dsn: config.dsn,
             selections,
-            query,
+            page_size,
+            session,
+        })
+    }
+
+    fn query_page(
+        &self,
+        query: &str,
+        params: &base::QueryParams,
+        offset: usize,
+    ) -> Result<base::QueryResult> {
+        let select = base::pageable_query(query, splitter::Dialect::MySQL)
+            .unwrap_or_else(|| query.to_string());
+        // one extra row tells whether there is another page,
+        // the line break ends a trailing comment of the query
+        let paged = format!(
+            "SELECT * FROM ({}\n) AS db9s_page LIMIT {} OFFSET {}",
+            select,
+            self.page_size + 1,
+            offset
+        );
+        let (mut raw_rows, notices) = self.with_client(|conn| {
+            let raw_rows = if params.is_empty() {
+                conn.query_map(&paged, |row: mysql::Row| row)?
+            } else {
+                conn.exec_map(&paged, to_mysql_params(params), |row: mysql::Row| row)?
+            };
+            Ok((raw_rows, take_warnings(conn)?))
+        })?;
+        let has_more = raw_rows.len() > self.page_size;
+        raw_rows.truncate(self.page_size);
+        let mut result = to_query_result(raw_rows);
+        result.outcome.notices = notices;
+        if has_more {
+            result.next_page = Some(base::PageCursor::Offset(offset + self.page_size));
+        }
+        Ok(result)
+    }
+
+    /// Runs a query listing objects of the current database as id, name, kind and detail.
+    fn list_objects(&self, query: &str) -> Result<Vec<base::SchemaObject>> {
+        self.with_client(|conn| {
+            Ok(conn.query_map(query, |row: mysql::Row| base::SchemaObject {
+                id: row.get(0).unwrap_or_default(),
+                name: row.get(1).unwrap_or_default(),
+                kind: row
+                    .get::<Option<String>, _>(2)
+                    .flatten()
+                    .unwrap_or_default(),
+                detail: row
+                    .get::<Option<String>, _>(3)
+                    .flatten()
+                    .unwrap_or_default(),
+            })?)
         })
     }
 
@@ -185,13 +848,57 @@ impl MySQLDatabase {
         Some(value[0].clone())
     }
 
-    fn get_client(&self) -> Result<mysql::PooledConn> {
-        let pool = mysql::Pool::new(self.dsn.as_str())?;
+    fn with_client<T>(&self, mut op: impl FnMut(&mut mysql::PooledConn) -> Result<T>) -> Result<T> {
+        // the pool checks connection health itself, only a pinned connection can be lost
+        self.session.run(
+            || self.connect(),
+            |client| {
+                client
+                    .transaction
+                    .as_mut()
+                    .is_none_or(|conn| conn.query_drop("DO 1").is_ok())
+            },
+            |client| {
+                let mut conn = match client.transaction.take() {
+                    Some(conn) => conn,
+                    None => client
+                        .pool
+                        .get_conn()
+                        .map_err(|_| anyhow!("Failed to get connection from pool"))?,
+                };
+                // KILL QUERY has to be sent from a different connection than the busy one
+                let connection_id = conn.connection_id();
+                let kill_pool = client.pool.clone();
+                self.session.set_canceller(Arc::new(move || {
+                    kill_pool
+                        .get_conn()?
+                        .query_drop(format!("KILL QUERY {}", connection_id))?;
+                    Ok(())
+                }));
+                let result = op(&mut conn);
+                if self.session.in_transaction() {
+                    client.transaction = Some(conn);
+                }
+                result
+            },
+        )
+    }
 
-        let mut conn = pool.get_conn();
-        if conn.is_err() {
-            return Err(anyhow!("Failed to get connection from pool"));
+    fn connect(&self) -> Result<MySQLConnection> {
+        let mut opts =
+            mysql::OptsBuilder::from_opts(mysql::Opts::from_url(&self.dsn)?)
+                .pool_opts(mysql::PoolOpts::default().with_constraints(
+                    mysql::PoolConstraints::new(1, POOL_MAX_CONNECTIONS).unwrap(),
+                ))
+                // an UPDATE reports the rows it matched like the other backends do, so writing
+                // the value a cell already holds is not taken for a row that went away
+                .additional_capabilities(CapabilityFlags::CLIENT_FOUND_ROWS);
+        if let Some(database) = self.get_selection(types::WindowTypeID::DATABASES) {
+            opts = opts.db_name(Some(database));
         }
-        Ok(conn.unwrap())
+        Ok(MySQLConnection {
+            pool: mysql::Pool::new(opts)?,
+            transaction: None,
+        })
     }
 }
@@ -1,57 +1,626 @@
+use crate::connectiontypes::session::Session;
+use crate::connectiontypes::splitter;
 use crate::ui::types;
 use crate::{connectiontypes::base, data::Connection};
 use anyhow::Result;
-use chrono::{DateTime, NaiveDateTime, Utc};
+use bytes::BytesMut;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
 use log::debug;
 use mdsn::Dsn;
+use postgres::types::{to_sql_checked, FromSql, IsNull, Kind, ToSql, Type};
 use postgres::{Client, NoTls};
 use std::collections::HashMap;
+use std::io::Write;
+use std::net::IpAddr;
 use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 use uuid::Uuid;
 
-fn get_row_value(row: &postgres::Row, column: &str) -> Option<String> {
-    row.try_get::<_, String>(column)
-        .or_else(|_| {
-            row.try_get::<_, Uuid>(column)
-                .map(|value| value.to_string())
-        })
-        .or_else(|_| {
-            row.try_get::<_, NaiveDateTime>(column)
-                .map(|value| value.to_string())
-        })
-        .or_else(|_| {
-            row.try_get::<_, DateTime<Utc>>(column)
-                .map(|value| value.to_string())
-        })
-        .or_else(|_| row.try_get::<_, i32>(column).map(|value| value.to_string()))
-        .or_else(|_| row.try_get::<_, i64>(column).map(|value| value.to_string()))
-        .or_else(|_| row.try_get::<_, f32>(column).map(|value| value.to_string()))
-        .or_else(|_| row.try_get::<_, f64>(column).map(|value| value.to_string()))
-        .or_else(|_| {
-            row.try_get::<_, bool>(column)
-                .map(|value| value.to_string())
+struct PgValue(base::Value);
+
+type DecodeResult<T> = std::result::Result<T, Box<dyn std::error::Error + Sync + Send>>;
+
+impl<'a> FromSql<'a> for PgValue {
+    fn from_sql(ty: &Type, raw: &'a [u8]) -> DecodeResult<Self> {
+        Ok(PgValue(decode_value(ty, raw)?))
+    }
+
+    fn from_sql_null(_: &Type) -> DecodeResult<Self> {
+        Ok(PgValue(base::Value::Null))
+    }
+
+    fn accepts(_: &Type) -> bool {
+        true
+    }
+}
+
+/// A parameter typed at the prompt, converted to whatever type postgres expects for it.
+#[derive(Debug)]
+struct PgParam(base::Value);
+
+impl ToSql for PgParam {
+    fn to_sql(&self, ty: &Type, out: &mut BytesMut) -> DecodeResult<IsNull> {
+        let text = match &self.0 {
+            base::Value::Null => return Ok(IsNull::Yes),
+            base::Value::Bytes(bytes) if *ty == Type::BYTEA => return bytes.to_sql(ty, out),
+            base::Value::Text(text) => text.clone(),
+            value => value.to_string(),
+        };
+        let text = text.as_str();
+        match *ty {
+            Type::BOOL => base::parse_bool(text)?.to_sql(ty, out),
+            Type::INT2 => text.trim().parse::<i16>()?.to_sql(ty, out),
+            Type::INT4 => text.trim().parse::<i32>()?.to_sql(ty, out),
+            Type::INT8 => text.trim().parse::<i64>()?.to_sql(ty, out),
+            Type::OID => text.trim().parse::<u32>()?.to_sql(ty, out),
+            Type::NUMERIC => {
+                encode_numeric(text, out)?;
+                Ok(IsNull::No)
+            }
+            Type::FLOAT4 => text.trim().parse::<f32>()?.to_sql(ty, out),
+            Type::FLOAT8 => text.trim().parse::<f64>()?.to_sql(ty, out),
+            Type::DATE => NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d")?.to_sql(ty, out),
+            Type::TIME => NaiveTime::parse_from_str(text.trim(), "%H:%M:%S%.f")?.to_sql(ty, out),
+            Type::TIMESTAMP => base::parse_timestamp(text)?.to_sql(ty, out),
+            Type::TIMESTAMPTZ => match DateTime::parse_from_rfc3339(text.trim()) {
+                Ok(ts) => ts.with_timezone(&Utc).to_sql(ty, out),
+                Err(_) => base::parse_timestamp(text.trim_end_matches("UTC"))?
+                    .and_utc()
+                    .to_sql(ty, out),
+            },
+            Type::UUID => Uuid::parse_str(text.trim())?.to_sql(ty, out),
+            Type::JSON | Type::JSONB => {
+                serde_json::from_str::<serde_json::Value>(text)?.to_sql(ty, out)
+            }
+            // bytes typed the way they are shown, anything else as it is
+            Type::BYTEA => match base::parse_hex(text) {
+                Some(bytes) => bytes.to_sql(ty, out),
+                None => text.as_bytes().to_sql(ty, out),
+            },
+            _ if <&str as ToSql>::accepts(ty) => text.to_sql(ty, out),
+            _ => Err(format!(
+                "can't bind a value to a parameter of type {}, cast it in the query, e.g. $1::text::{}",
+                ty, ty
+            )
+            .into()),
+        }
+    }
+
+    fn accepts(_: &Type) -> bool {
+        true
+    }
+
+    to_sql_checked!();
+}
+
+fn bind_params(params: &base::QueryParams) -> Vec<PgParam> {
+    params
+        .iter()
+        .map(|(_, value)| PgParam(value.clone()))
+        .collect()
+}
+
+fn param_refs(params: &[PgParam]) -> Vec<&(dyn ToSql + Sync)> {
+    params
+        .iter()
+        .map(|param| param as &(dyn ToSql + Sync))
+        .collect()
+}
+
+fn read_i16(raw: &[u8], offset: usize) -> DecodeResult<i16> {
+    Ok(i16::from_be_bytes(
+        raw.get(offset..offset + 2)
+            .ok_or("truncated value")?
+            .try_into()?,
+    ))
+}
+
+fn read_i32(raw: &[u8], offset: usize) -> DecodeResult<i32> {
+    Ok(i32::from_be_bytes(
+        raw.get(offset..offset + 4)
+            .ok_or("truncated value")?
+            .try_into()?,
+    ))
+}
+
+fn read_i64(raw: &[u8], offset: usize) -> DecodeResult<i64> {
+    Ok(i64::from_be_bytes(
+        raw.get(offset..offset + 8)
+            .ok_or("truncated value")?
+            .try_into()?,
+    ))
+}
+
+fn decode_numeric(raw: &[u8]) -> DecodeResult<String> {
+    let ndigits = read_i16(raw, 0)? as i32;
+    let weight = read_i16(raw, 2)? as i32;
+    let sign = read_i16(raw, 4)? as u16;
+    let dscale = read_i16(raw, 6)? as usize;
+    match sign {
+        0xC000 => return Ok("NaN".to_string()),
+        0xD000 => return Ok("Infinity".to_string()),
+        0xF000 => return Ok("-Infinity".to_string()),
+        _ => {}
+    }
+    let mut digits = Vec::with_capacity(ndigits as usize);
+    for i in 0..ndigits {
+        digits.push(read_i16(raw, 8 + i as usize * 2)?);
+    }
+    let digit = |idx: i32| -> i16 {
+        if idx >= 0 && idx < ndigits {
+            digits[idx as usize]
+        } else {
+            0
+        }
+    };
+
+    let mut result = String::new();
+    if sign == 0x4000 {
+        result.push('-');
+    }
+    if weight < 0 {
+        result.push('0');
+    } else {
+        for idx in 0..=weight {
+            if idx == 0 {
+                result.push_str(&digit(idx).to_string());
+            } else {
+                result.push_str(&format!("{:04}", digit(idx)));
+            }
+        }
+    }
+    if dscale > 0 {
+        let mut fraction = String::new();
+        let mut idx = weight + 1;
+        while fraction.len() < dscale {
+            fraction.push_str(&format!("{:04}", digit(idx)));
+            idx += 1;
+        }
+        fraction.truncate(dscale);
+        result.push('.');
+        result.push_str(&fraction);
+    }
+    Ok(result)
+}
+
+fn encode_numeric(text: &str, out: &mut BytesMut) -> DecodeResult<()> {
+    let text = text.trim();
+    let (negative, number) = match text.strip_prefix('-') {
+        Some(number) => (true, number),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let (int_part, frac_part) = number.split_once('.').unwrap_or((number, ""));
+    let is_digits = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+    if (int_part.is_empty() && frac_part.is_empty())
+        || !is_digits(int_part)
+        || !is_digits(frac_part)
+    {
+        if number.eq_ignore_ascii_case("nan") {
+            out.extend_from_slice(&[0, 0, 0, 0, 0xC0, 0, 0, 0]);
+            return Ok(());
+        }
+        return Err(format!("`{}` is not a number", text).into());
+    }
+
+    // base 10000 digits, the integer part is padded on the left and the fraction on the right
+    let int_part = int_part.trim_start_matches('0');
+    let int_pad = (4 - int_part.len() % 4) % 4;
+    let frac_pad = (4 - frac_part.len() % 4) % 4;
+    let padded = format!(
+        "{}{}{}{}",
+        "0".repeat(int_pad),
+        int_part,
+        frac_part,
+        "0".repeat(frac_pad)
+    );
+    let groups: Vec<i16> = padded
+        .as_bytes()
+        .chunks(4)
+        .map(|chunk| chunk.iter().fold(0, |acc, d| acc * 10 + (d - b'0') as i16))
+        .collect();
+    let mut weight = ((int_part.len() + int_pad) / 4) as i16 - 1;
+    let digits = match groups.iter().position(|g| *g != 0) {
+        Some(first) => {
+            let last = groups.iter().rposition(|g| *g != 0).unwrap();
+            weight -= first as i16;
+            &groups[first..=last]
+        }
+        None => {
+            weight = 0;
+            &groups[0..0]
+        }
+    };
+    let sign: u16 = if negative && !digits.is_empty() {
+        0x4000
+    } else {
+        0
+    };
+    out.extend_from_slice(&(digits.len() as i16).to_be_bytes());
+    out.extend_from_slice(&weight.to_be_bytes());
+    out.extend_from_slice(&sign.to_be_bytes());
+    out.extend_from_slice(&(frac_part.len() as u16).to_be_bytes());
+    for digit in digits {
+        out.extend_from_slice(&digit.to_be_bytes());
+    }
+    Ok(())
+}
+
+fn decode_interval(raw: &[u8]) -> DecodeResult<String> {
+    let micros = read_i64(raw, 0)?;
+    let days = read_i32(raw, 8)?;
+    let months = read_i32(raw, 12)?;
+
+    let mut parts = vec![];
+    if months / 12 != 0 {
+        parts.push(format!("{} years", months / 12));
+    }
+    if months % 12 != 0 {
+        parts.push(format!("{} mons", months % 12));
+    }
+    if days != 0 {
+        parts.push(format!("{} days", days));
+    }
+    if micros != 0 || parts.is_empty() {
+        let sign = if micros < 0 { "-" } else { "" };
+        let micros = micros.unsigned_abs();
+        let seconds = micros / 1_000_000;
+        let mut time = format!(
+            "{}{:02}:{:02}:{:02}",
+            sign,
+            seconds / 3600,
+            (seconds / 60) % 60,
+            seconds % 60
+        );
+        if micros % 1_000_000 != 0 {
+            time.push_str(&format!(".{:06}", micros % 1_000_000));
+        }
+        parts.push(time);
+    }
+    Ok(parts.join(" "))
+}
+
+fn decode_array(member: &Type, raw: &[u8]) -> DecodeResult<base::Value> {
+    let ndim = read_i32(raw, 0)?;
+    if ndim <= 0 {
+        return Ok(base::Value::Array(vec![]));
+    }
+    let mut dimensions = vec![];
+    for i in 0..ndim as usize {
+        dimensions.push(read_i32(raw, 12 + i * 8)? as usize);
+    }
+
+    let mut offset = 12 + ndim as usize * 8;
+    let mut elements = vec![];
+    let total: usize = dimensions.iter().product();
+    for _ in 0..total {
+        let len = read_i32(raw, offset)?;
+        offset += 4;
+        if len < 0 {
+            elements.push(base::Value::Null);
+            continue;
+        }
+        let len = len as usize;
+        let data = raw.get(offset..offset + len).ok_or("truncated array")?;
+        elements.push(decode_value(member, data)?);
+        offset += len;
+    }
+
+    // fold the flat element list back into nested arrays, innermost dimension first
+    for dim in dimensions.iter().skip(1).rev() {
+        elements = elements
+            .chunks(*dim)
+            .map(|chunk| base::Value::Array(chunk.to_vec()))
+            .collect();
+    }
+    Ok(base::Value::Array(elements))
+}
+
+fn decode_value(ty: &Type, raw: &[u8]) -> DecodeResult<base::Value> {
+    if let Kind::Array(member) = ty.kind() {
+        return decode_array(member, raw);
+    }
+    if let Kind::Domain(inner) = ty.kind() {
+        return decode_value(inner, raw);
+    }
+    let value = match *ty {
+        Type::BOOL => base::Value::Bool(bool::from_sql(ty, raw)?),
+        Type::CHAR => base::Value::Int(i8::from_sql(ty, raw)? as i64),
+        Type::INT2 => base::Value::Int(i16::from_sql(ty, raw)? as i64),
+        Type::INT4 => base::Value::Int(i32::from_sql(ty, raw)? as i64),
+        Type::INT8 => base::Value::Int(i64::from_sql(ty, raw)?),
+        Type::OID => base::Value::Int(u32::from_sql(ty, raw)? as i64),
+        Type::FLOAT4 => base::Value::Float(f32::from_sql(ty, raw)? as f64),
+        Type::FLOAT8 => base::Value::Float(f64::from_sql(ty, raw)?),
+        Type::NUMERIC => base::Value::Decimal(decode_numeric(raw)?),
+        Type::BYTEA => base::Value::Bytes(raw.to_vec()),
+        Type::JSON => base::Value::Json(serde_json::from_slice(raw)?),
+        // jsonb is prefixed with a format version byte
+        Type::JSONB => base::Value::Json(serde_json::from_slice(raw.get(1..).unwrap_or(&[]))?),
+        Type::UUID => base::Value::Uuid(Uuid::from_sql(ty, raw)?),
+        Type::DATE => match NaiveDate::from_sql(ty, raw) {
+            Ok(date) => base::Value::Date(date),
+            Err(_) => base::Value::Text(infinity_label(read_i32(raw, 0)? as i64)),
+        },
+        Type::TIME => base::Value::Time(NaiveTime::from_sql(ty, raw)?),
+        Type::TIMESTAMP => match NaiveDateTime::from_sql(ty, raw) {
+            Ok(ts) => base::Value::Timestamp(ts),
+            Err(_) => base::Value::Text(infinity_label(read_i64(raw, 0)?)),
+        },
+        Type::TIMESTAMPTZ => match DateTime::<Utc>::from_sql(ty, raw) {
+            Ok(ts) => base::Value::TimestampTz(ts),
+            Err(_) => base::Value::Text(infinity_label(read_i64(raw, 0)?)),
+        },
+        Type::INTERVAL => base::Value::Text(decode_interval(raw)?),
+        Type::INET | Type::CIDR => base::Value::Text(IpAddr::from_sql(ty, raw)?.to_string()),
+        Type::TEXT | Type::VARCHAR | Type::BPCHAR | Type::NAME | Type::UNKNOWN | Type::XML => {
+            base::Value::Text(std::str::from_utf8(raw)?.to_string())
+        }
+        // enum labels and citext are sent as their text
+        _ if matches!(ty.kind(), Kind::Enum(_)) || ty.name() == "citext" => {
+            base::Value::Text(std::str::from_utf8(raw)?.to_string())
+        }
+        // the binary format of any other type, e.g. money, point or tsvector, is its own
+        _ => base::Value::Bytes(raw.to_vec()),
+    };
+    Ok(value)
+}
+
+fn infinity_label(raw: i64) -> String {
+    if raw < 0 {
+        "-infinity".to_string()
+    } else {
+        "infinity".to_string()
+    }
+}
+
+fn get_row_value(row: &postgres::Row, idx: usize) -> base::Value {
+    match row.try_get::<_, PgValue>(idx) {
+        Ok(value) => value.0,
+        Err(err) => {
+            debug!("Failed to decode column {}: {:?}", idx, err);
+            base::Value::Null
+        }
+    }
+}
+
+fn to_query_result(
+    columns: &[postgres::Column],
+    raw_rows: Vec<postgres::Row>,
+) -> base::QueryResult {
+    let columns: Vec<String> = columns.iter().map(|c| c.name().to_string()).collect();
+    if columns.is_empty() {
+        return base::QueryResult::empty();
+    }
+
+    let mut rows = Vec::new();
+    for row in raw_rows.iter() {
+        let row_data: Vec<base::Value> = (0..columns.len())
+            .map(|idx| get_row_value(row, idx))
+            .collect();
+        rows.push(base::QueryResultRow { data: row_data });
+    }
+
+    base::QueryResult {
+        columns,
+        rows,
+        next_page: None,
+        outcome: base::ExecutionOutcome::rows(),
+    }
+}
+
+// the conditions and keys postgres reports for a plan node, in the order psql shows them
+const PLAN_DETAILS: [&str; 9] = [
+    "Index Cond",
+    "Recheck Cond",
+    "Hash Cond",
+    "Merge Cond",
+    "Join Filter",
+    "Filter",
+    "Sort Key",
+    "Group Key",
+    "Cache Key",
+];
+
+/// Converts a node of `EXPLAIN (FORMAT JSON)` output, along with its children.
+fn to_plan_node(plan: &serde_json::Value) -> base::PlanNode {
+    let mut node_type = plan["Node Type"].as_str().unwrap_or("Unknown").to_string();
+    if let Some(join) = plan["Join Type"].as_str().filter(|join| *join != "Inner") {
+        node_type = format!("{} ({})", node_type, join);
+    }
+    if let Some(index) = plan["Index Name"].as_str() {
+        node_type = format!("{} using {}", node_type, index);
+    }
+    if let Some(relation) = plan["Relation Name"].as_str() {
+        node_type = format!("{} on {}", node_type, relation);
+        if let Some(alias) = plan["Alias"].as_str().filter(|alias| *alias != relation) {
+            node_type = format!("{} {}", node_type, alias);
+        }
+    }
+    let detail = PLAN_DETAILS
+        .iter()
+        .filter_map(|key| {
+            let value = match &plan[*key] {
+                serde_json::Value::String(value) => value.clone(),
+                serde_json::Value::Array(values) => values
+                    .iter()
+                    .filter_map(|value| value.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                _ => return None,
+            };
+            Some(format!("{}: {}", key, value))
         })
-        .ok()
+        .collect::<Vec<_>>()
+        .join("  ");
+    // actual figures are per loop
+    let loops = plan["Actual Loops"].as_f64().unwrap_or(1.0);
+    base::PlanNode {
+        node_type,
+        detail,
+        cost: plan["Total Cost"].as_f64(),
+        rows: plan["Plan Rows"].as_f64(),
+        actual_rows: plan["Actual Rows"].as_f64().map(|rows| rows * loops),
+        actual_time: plan["Actual Total Time"].as_f64().map(|time| time * loops),
+        children: plan["Plans"]
+            .as_array()
+            .map(|plans| plans.iter().map(to_plan_node).collect())
+            .unwrap_or_default(),
+    }
+}
+
+/// A client together with the paging cursor currently open on it.
+pub struct PgConnection {
+    client: Client,
+    // only open inside the user's transaction, until it is drained or replaced
+    cursor: Option<String>,
+    in_transaction: bool,
+    notices: Arc<Mutex<Vec<String>>>,
+}
+
+impl PgConnection {
+    fn take_notices(&mut self) -> Vec<String> {
+        std::mem::take(&mut *self.notices.lock().unwrap())
+    }
+
+    fn execute(&mut self, query: &str, params: &base::QueryParams) -> Result<base::QueryResult> {
+        self.close_cursor()?;
+        let statement = self.client.prepare(query)?;
+        if statement.columns().is_empty() {
+            let params = bind_params(params);
+            let affected = self.client.execute(&statement, &param_refs(&params))?;
+            return Ok(base::QueryResult {
+                outcome: base::ExecutionOutcome::command(base::command_tag(query), Some(affected)),
+                ..base::QueryResult::empty()
+            });
+        }
+        self.query_statement(&statement, params)
+    }
+
+    /// Runs a statement returning rows, the columns are known even without any row.
+    fn query(&mut self, query: &str, params: &base::QueryParams) -> Result<base::QueryResult> {
+        let statement = self.client.prepare(query)?;
+        self.query_statement(&statement, params)
+    }
+
+    fn query_statement(
+        &mut self,
+        statement: &postgres::Statement,
+        params: &base::QueryParams,
+    ) -> Result<base::QueryResult> {
+        let params = bind_params(params);
+        let raw_rows = self.client.query(statement, &param_refs(&params))?;
+        Ok(to_query_result(statement.columns(), raw_rows))
+    }
+
+    fn track_transaction(&mut self, query: &str) {
+        if let Some(open) = base::transaction_change(query) {
+            self.in_transaction = open;
+        }
+    }
+
+    fn end_transaction(&mut self, command: &str) -> Result<()> {
+        // the cursor belongs to the transaction and goes away with it
+        self.cursor = None;
+        // postgres ends the transaction even when COMMIT fails
+        self.in_transaction = false;
+        self.client.batch_execute(command)?;
+        Ok(())
+    }
+
+    fn close_cursor(&mut self) -> Result<()> {
+        let name = match self.cursor.take() {
+            Some(name) => name,
+            None => return Ok(()),
+        };
+        if let Err(err) = self.client.batch_execute(&format!("CLOSE {}", name)) {
+            // an aborted transaction refuses everything but ROLLBACK, which drops the cursor anyway
+            debug!("Failed to close cursor {}: {:?}", name, err);
+        }
+        Ok(())
+    }
+
+    /// Only declared inside the user's transaction, outside of one the pages are
+    /// queried with `query_page` so no transaction is left open between them.
+    fn open_cursor(&mut self, query: &str, params: &base::QueryParams) -> Result<String> {
+        self.close_cursor()?;
+        let name = format!("db9s_page_{}", Uuid::new_v4().simple());
+        let declare = format!("DECLARE {} NO SCROLL CURSOR FOR {}", name, query);
+        let params = bind_params(params);
+        self.client.execute(&declare, &param_refs(&params))?;
+        self.cursor = Some(name.clone());
+        Ok(name)
+    }
+
+    fn fetch(&mut self, name: &str, page_size: usize) -> Result<base::QueryResult> {
+        let mut result = self.query(&format!("FETCH {} FROM {}", page_size, name), &vec![])?;
+        if result.rows.len() < page_size {
+            self.close_cursor()?;
+        } else {
+            result.next_page = Some(base::PageCursor::Cursor(name.to_string()));
+        }
+        Ok(result)
+    }
+
+    fn query_page(
+        &mut self,
+        select: &str,
+        params: &base::QueryParams,
+        offset: usize,
+        page_size: usize,
+    ) -> Result<base::QueryResult> {
+        self.close_cursor()?;
+        // one extra row tells whether there is another page,
+        // the line break ends a trailing comment of the query
+        let paged = format!(
+            "SELECT * FROM ({}\n) AS db9s_page LIMIT {} OFFSET {}",
+            select,
+            page_size + 1,
+            offset
+        );
+        let mut result = self.query(&paged, params)?;
+        if result.rows.len() > page_size {
+            result.rows.truncate(page_size);
+            result.next_page = Some(base::PageCursor::Offset(offset + page_size));
+        }
+        Ok(result)
+    }
 }
 
 pub struct PostgreSQLDatabase {
-    name: String,
     dsn: Dsn,
     selections: HashMap<types::WindowTypeID, Vec<String>>,
-    query: Option<String>,
+    page_size: usize,
+    session: Arc<Session<PgConnection>>,
 }
 
 impl base::ConnectionType for PostgreSQLDatabase {
     fn list_tables(&self) -> Result<Vec<base::Table>> {
-        let raw_tables = self.get_client()?.query(
-            "
-SELECT table_name
-FROM information_schema.tables
-WHERE table_schema = $1;",
-            &[&self
-                .get_selection(types::WindowTypeID::SCHEMAS)
-                .unwrap_or("public".to_string())],
-        )?;
+        let schema = self
+            .get_selection(types::WindowTypeID::SCHEMAS)
+            .unwrap_or("public".to_string());
+        let raw_tables = self.with_client(|client| {
+            Ok(client.query(
+                "
+SELECT c.relname,
+       CASE c.relkind
+           WHEN 'r' THEN 'table'
+           WHEN 'p' THEN 'partitioned table'
+           WHEN 'v' THEN 'view'
+           WHEN 'm' THEN 'materialized view'
+           WHEN 'f' THEN 'foreign table'
+       END,
+       c.reltuples::bigint,
+       pg_total_relation_size(c.oid),
+       pg_get_userbyid(c.relowner),
+       obj_description(c.oid, 'pg_class')
+FROM pg_class c
+JOIN pg_namespace n ON n.oid = c.relnamespace
+WHERE n.nspname = $1 AND c.relkind IN ('r', 'p', 'v', 'm', 'f')
+ORDER BY c.relname;",
+                &[&schema],
+            )?)
+        })?;
         debug!("List tables query: {:?}", raw_tables);
 
         let tables: Vec<base::Table> = raw_tables
@@ -59,21 +628,108 @@ WHERE table_schema = $1;",
             .map(|t| base::Table {
                 id: t.get(0),
                 name: t.get(0),
+                kind: t.get(1),
+                // never analyzed tables have no estimate (-1, 0 before postgres 14)
+                rows: t
+                    .get::<_, Option<i64>>(2)
+                    .filter(|rows| *rows >= 0)
+                    .map(|rows| rows as u64),
+                size: t.get::<_, Option<i64>>(3).map(|size| size as u64),
+                owner: t.get(4),
+                comment: t.get(5),
             })
             .collect();
 
         Ok(tables)
     }
 
+    fn split_script(&self, script: &str) -> Vec<String> {
+        splitter::split_statements(script, splitter::Dialect::Postgres)
+    }
+
+    fn placeholders(&self, statement: &str) -> Vec<String> {
+        splitter::find_placeholders(statement, splitter::Dialect::Postgres)
+    }
+
+    fn supports_transactions(&self) -> bool {
+        true
+    }
+
+    fn supports_order_by(&self) -> bool {
+        true
+    }
+
+    fn supports_where(&self) -> bool {
+        true
+    }
+
+    fn begin_transaction(&self) -> Result<()> {
+        self.with_connection(|conn| {
+            conn.close_cursor()?;
+            conn.client.batch_execute("BEGIN")?;
+            conn.in_transaction = true;
+            Ok(())
+        })
+    }
+
+    fn commit_transaction(&self) -> Result<()> {
+        self.with_connection(|conn| conn.end_transaction("COMMIT"))
+    }
+
+    fn rollback_transaction(&self) -> Result<()> {
+        self.with_connection(|conn| conn.end_transaction("ROLLBACK"))
+    }
+
+    fn explain(
+        &self,
+        statement: &str,
+        params: &base::QueryParams,
+        analyze: bool,
+    ) -> Result<base::PlanNode> {
+        let options = if analyze {
+            "FORMAT JSON, ANALYZE"
+        } else {
+            "FORMAT JSON"
+        };
+        let explain = format!(
+            "EXPLAIN ({}) {}",
+            options,
+            statement.trim().trim_end_matches(';')
+        );
+        let plan: serde_json::Value = self.with_connection(|conn| {
+            conn.close_cursor()?;
+            let params = bind_params(params);
+            let params = param_refs(&params);
+            // ANALYZE runs the statement, whatever it changes is rolled back.
+            // A savepoint also keeps a failing EXPLAIN from aborting the user's transaction.
+            let (start, end) = if conn.in_transaction {
+                (
+                    "SAVEPOINT db9s_explain",
+                    "ROLLBACK TO SAVEPOINT db9s_explain",
+                )
+            } else if analyze {
+                ("BEGIN", "ROLLBACK")
+            } else {
+                return Ok(conn.client.query_one(&explain, &params)?.try_get(0)?);
+            };
+            conn.client.batch_execute(start)?;
+            let result = conn.client.query_one(&explain, &params);
+            conn.client.batch_execute(end)?;
+            Ok(result?.try_get(0)?)
+        })?;
+        let root = &plan[0]["Plan"];
+        if root.is_null() {
+            return Err(anyhow::anyhow!("Unexpected plan: {}", plan));
+        }
+        Ok(to_plan_node(root))
+    }
+
     fn default_query_string(&self) -> String {
         let mut columns = vec!["*".to_string()];
-        match self.selections.get(&types::WindowTypeID::COLUMNS) {
-            Some(value) => {
-                if !value.is_empty() {
-                    columns = value.clone();
-                }
+        if let Some(value) = self.selections.get(&types::WindowTypeID::COLUMNS) {
+            if !value.is_empty() {
+                columns = value.clone();
             }
-            None => {}
         }
         let query = format!(
             "SELECT {} FROM {}",
@@ -81,58 +737,64 @@ WHERE table_schema = $1;",
             self.get_selection(types::WindowTypeID::TABLES)
                 .unwrap_or("_unselected_".to_string())
         );
-        format!("{} LIMIT {}", query, 50)
+        query
     }
 
-    fn query(&self) -> Result<base::QueryResult> {
-        let mut query = self.default_query_string();
-        if let Some(custom_query) = self.query.clone() {
-            query = custom_query;
-        }
-        debug!("List tables query: {:?}", query);
+    fn execute(&self, query: &str, params: &base::QueryParams) -> Result<base::QueryResult> {
+        debug!("Execute query: {:?}", query);
 
-        let raw_rows = self.get_client()?.query(query.as_str(), &[])?;
-        if raw_rows.is_empty() {
-            return Ok(base::QueryResult {
-                columns: vec![],
-                rows: vec![],
-            });
-        }
-
-        let columns: Vec<String> = raw_rows
-            .first()
-            .unwrap()
-            .columns()
-            .iter()
-            .map(|c| c.name().to_string())
-            .collect();
+        let started = Instant::now();
+        let mut result = self.with_connection(|conn| {
+            let mut result = match base::pageable_query(query, splitter::Dialect::Postgres) {
+                Some(select) if conn.in_transaction => {
+                    let name = conn.open_cursor(&select, params)?;
+                    conn.fetch(&name, self.page_size)?
+                }
+                Some(select) => conn.query_page(&select, params, 0, self.page_size)?,
+                None => {
+                    let result = conn.execute(query, params)?;
+                    conn.track_transaction(query);
+                    result
+                }
+            };
+            result.outcome.notices = conn.take_notices();
+            Ok(result)
+        })?;
+        result.outcome.elapsed = started.elapsed();
+        Ok(result)
+    }
 
-        let mut rows = Vec::new();
-        if columns.is_empty() {
-            return Ok(base::QueryResult {
-                columns: vec![],
-                rows: vec![],
-            });
-        }
-        for row in raw_rows.iter() {
-            let mut row_data = Vec::new();
-            for column in columns.iter() {
-                row_data.push(get_row_value(row, column));
+    fn fetch_page(
+        &self,
+        statement: &str,
+        params: &base::QueryParams,
+        cursor: &base::PageCursor,
+    ) -> Result<base::QueryResult> {
+        match cursor {
+            base::PageCursor::Cursor(name) => self.with_connection(|conn| {
+                if conn.cursor.as_ref() != Some(name) {
+                    return Err(anyhow::anyhow!(
+                        "The result set was closed, refresh to run the query again"
+                    ));
+                }
+                conn.fetch(name, self.page_size)
+            }),
+            base::PageCursor::Offset(offset) => {
+                let select = base::pageable_query(statement, splitter::Dialect::Postgres)
+                    .ok_or(anyhow::anyhow!("The query can not be paged"))?;
+                self.with_connection(|conn| {
+                    conn.query_page(&select, params, *offset, self.page_size)
+                })
             }
-            rows.push(base::QueryResultRow {
-                id: get_row_value(row, columns.get(0).unwrap()).unwrap_or("missing".to_string()),
-                data: row_data,
-            });
+            _ => Err(anyhow::anyhow!("Unsupported page cursor {:?}", cursor)),
         }
-
-        Ok(base::QueryResult { columns, rows })
     }
 
     fn list_schemas(&self) -> Result<Vec<base::Schema>> {
         let query = "SELECT schema_name
 FROM information_schema.schemata;
 ";
-        let raw_schemas = self.get_client()?.query(query, &[])?;
+        let raw_schemas = self.with_client(|client| Ok(client.query(query, &[])?))?;
         let rows: Vec<base::Schema> = raw_schemas
             .iter()
             .map(|r| base::Schema {
@@ -145,7 +807,7 @@ FROM information_schema.schemata;
 
     fn list_databases(&self) -> Result<Vec<base::DatabaseInfo>> {
         let query = "SELECT datname FROM pg_database;";
-        let raw_databases = self.get_client()?.query(query, &[])?;
+        let raw_databases = self.with_client(|client| Ok(client.query(query, &[])?))?;
         let rows: Vec<base::DatabaseInfo> = raw_databases
             .iter()
             .map(|r| base::DatabaseInfo {
@@ -156,28 +818,310 @@ FROM information_schema.schemata;
         Ok(rows)
     }
 
-    fn list_columns(&self) -> Result<Vec<String>> {
-        let query = format!(
-            "SELECT column_name
-FROM information_schema.columns
-WHERE table_schema = $1 AND table_name = $2;"
-        );
-        let raw_columns = self.get_client()?.query(
-            query.as_str(),
-            &[
-                &self
-                    .get_selection(types::WindowTypeID::SCHEMAS)
-                    .unwrap_or("public".to_string()),
-                &self
-                    .get_selection(types::WindowTypeID::TABLES)
-                    .unwrap_or("public".to_string()),
-            ],
-        )?;
-        let rows: Vec<String> = raw_columns.iter().map(|r| r.get(0)).collect();
+    fn list_columns(&self) -> Result<Vec<base::ColumnInfo>> {
+        let query = "
+SELECT c.column_name,
+       format_type(a.atttypid, a.atttypmod),
+       c.is_nullable = 'YES',
+       c.column_default,
+       EXISTS (SELECT 1
+               FROM pg_constraint con
+               WHERE con.contype = 'p' AND con.conrelid = a.attrelid
+                 AND a.attnum = ANY(con.conkey)),
+       (SELECT string_agg(format('%s(%I)', con.confrelid::regclass, fa.attname), ', ')
+        FROM pg_constraint con
+        JOIN pg_attribute fa ON fa.attrelid = con.confrelid
+         AND fa.attnum = con.confkey[array_position(con.conkey, a.attnum)]
+        WHERE con.contype = 'f' AND con.conrelid = a.attrelid
+          AND a.attnum = ANY(con.conkey)),
+       col_description(a.attrelid, a.attnum)
+FROM information_schema.columns c
+JOIN pg_attribute a ON a.attrelid = to_regclass(format('%I.%I', c.table_schema, c.table_name))
+ AND a.attname = c.column_name
+WHERE c.table_schema = $1 AND c.table_name = $2
+ORDER BY c.ordinal_position;";
+        let schema = self
+            .get_selection(types::WindowTypeID::SCHEMAS)
+            .unwrap_or("public".to_string());
+        let table = self
+            .get_selection(types::WindowTypeID::TABLES)
+            .unwrap_or("public".to_string());
+        let raw_columns =
+            self.with_client(|client| Ok(client.query(query, &[&schema, &table])?))?;
+        let rows: Vec<base::ColumnInfo> = raw_columns
+            .iter()
+            .map(|r| base::ColumnInfo {
+                name: r.get(0),
+                data_type: r.get(1),
+                nullable: r.get(2),
+                default: r.get(3),
+                primary_key: r.get(4),
+                foreign_key: r.get(5),
+                comment: r.get(6),
+            })
+            .collect();
         Ok(rows)
     }
-    fn list_indexes(&self) -> Result<Vec<String>> {
-        Ok(vec![])
+    fn list_indexes(&self) -> Result<Vec<base::IndexInfo>> {
+        let query = "
+SELECT i.relname,
+       ARRAY(SELECT pg_get_indexdef(ix.indexrelid, k, true)
+             FROM generate_series(1, ix.indnatts) AS k
+             ORDER BY k),
+       ix.indisunique,
+       am.amname,
+       pg_relation_size(ix.indexrelid),
+       pg_get_indexdef(ix.indexrelid)
+FROM pg_index ix
+JOIN pg_class i ON i.oid = ix.indexrelid
+JOIN pg_class t ON t.oid = ix.indrelid
+JOIN pg_namespace n ON n.oid = t.relnamespace
+JOIN pg_am am ON am.oid = i.relam
+WHERE n.nspname = $1 AND t.relname = $2
+ORDER BY i.relname;";
+        let schema = self
+            .get_selection(types::WindowTypeID::SCHEMAS)
+            .unwrap_or("public".to_string());
+        let table = self
+            .get_selection(types::WindowTypeID::TABLES)
+            .ok_or(anyhow::anyhow!("Select a table to list its indexes"))?;
+        let raw_indexes =
+            self.with_client(|client| Ok(client.query(query, &[&schema, &table])?))?;
+        Ok(raw_indexes
+            .iter()
+            .map(|r| base::IndexInfo {
+                name: r.get(0),
+                columns: r.get(1),
+                unique: r.get(2),
+                method: r.get(3),
+                size: Some(r.get::<_, i64>(4) as u64),
+                definition: r.get(5),
+            })
+            .collect())
+    }
+    fn placeholder(&self, position: usize) -> String {
+        format!("${}", position)
+    }
+
+    fn sql_literal(&self, value: &base::Value) -> String {
+        match value {
+            // X'..' is a bit string in postgres, bytea reads the hex format
+            base::Value::Bytes(_) => base::quote_text(&value.to_string(), false),
+            value => value.to_sql_literal(),
+        }
+    }
+
+    fn list_primary_key(&self, table: &str) -> Result<Vec<String>> {
+        let query = "
+SELECT a.attname::text
+FROM pg_index ix
+JOIN pg_attribute a ON a.attrelid = ix.indrelid AND a.attnum = ANY(ix.indkey)
+WHERE ix.indrelid = to_regclass(format('%I.%I', $1::text, $2::text)) AND ix.indisprimary
+ORDER BY array_position(ix.indkey::int2[], a.attnum);";
+        let schema = self
+            .get_selection(types::WindowTypeID::SCHEMAS)
+            .unwrap_or("public".to_string());
+        let table = table.to_string();
+        let rows = self.with_client(|client| Ok(client.query(query, &[&schema, &table])?))?;
+        Ok(rows.iter().map(|r| r.get(0)).collect())
+    }
+
+    /// Rows are streamed with COPY, arrays have no CSV form COPY reads and are inserted instead.
+    fn import_rows(
+        &self,
+        table: &str,
+        columns: &[String],
+        rows: &[Vec<base::Value>],
+    ) -> Result<u64> {
+        if rows.iter().any(|row| {
+            row.iter()
+                .any(|value| matches!(value, base::Value::Array(_)))
+        }) {
+            return base::insert_rows(self, table, columns, rows);
+        }
+        let schema = self
+            .get_selection(types::WindowTypeID::SCHEMAS)
+            .unwrap_or("public".to_string());
+        let copy = format!(
+            "COPY {}.{} ({}) FROM STDIN (FORMAT csv)",
+            base::ConnectionType::quote_identifier(self, &schema),
+            base::ConnectionType::quote_identifier(self, table),
+            columns
+                .iter()
+                .map(|column| base::ConnectionType::quote_identifier(self, column))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        // an unquoted empty field is NULL, everything else is quoted
+        let mut data = String::new();
+        for row in rows {
+            let fields = row
+                .iter()
+                .map(|value| match value {
+                    base::Value::Null => String::new(),
+                    value => format!("\"{}\"", value.to_string().replace('"', "\"\"")),
+                })
+                .collect::<Vec<_>>();
+            data.push_str(&fields.join(","));
+            data.push('\n');
+        }
+        self.with_connection(|conn| {
+            let mut writer = conn.client.copy_in(&copy)?;
+            writer.write_all(data.as_bytes())?;
+            Ok(writer.finish()?)
+        })
+    }
+
+    /// Only keys between tables of the selected schema are listed.
+    fn list_foreign_keys(&self, table: &str) -> Result<Vec<base::ForeignKey>> {
+        let query = "
+SELECT kcu.constraint_name::text, kcu.table_name::text, kcu.column_name::text,
+       ukcu.table_name::text, ukcu.column_name::text
+FROM information_schema.referential_constraints rc
+JOIN information_schema.key_column_usage kcu
+  ON kcu.constraint_schema = rc.constraint_schema AND kcu.constraint_name = rc.constraint_name
+JOIN information_schema.key_column_usage ukcu
+  ON ukcu.constraint_schema = rc.unique_constraint_schema
+ AND ukcu.constraint_name = rc.unique_constraint_name
+ AND ukcu.ordinal_position = kcu.position_in_unique_constraint
+WHERE rc.constraint_schema = $1 AND ukcu.table_schema = $1
+  AND (kcu.table_name = $2 OR ukcu.table_name = $2)
+ORDER BY kcu.table_name, kcu.constraint_name, kcu.ordinal_position;";
+        let schema = self
+            .get_selection(types::WindowTypeID::SCHEMAS)
+            .unwrap_or("public".to_string());
+        let table = table.to_string();
+        let rows = self.with_client(|client| Ok(client.query(query, &[&schema, &table])?))?;
+        Ok(base::ForeignKey::from_column_pairs(
+            rows.iter()
+                .map(|r| (r.get(0), r.get(1), r.get(2), r.get(3), r.get(4)))
+                .collect(),
+        ))
+    }
+
+    fn list_views(&self) -> Result<Vec<base::SchemaObject>> {
+        self.list_objects(
+            "
+SELECT c.oid::text,
+       c.relname,
+       CASE c.relkind WHEN 'm' THEN 'materialized view' ELSE 'view' END,
+       pg_get_userbyid(c.relowner)
+FROM pg_class c
+JOIN pg_namespace n ON n.oid = c.relnamespace
+WHERE n.nspname = $1 AND c.relkind IN ('v', 'm')
+ORDER BY c.relname;",
+        )
+    }
+
+    fn list_functions(&self) -> Result<Vec<base::SchemaObject>> {
+        self.list_objects(
+            "
+SELECT p.oid::text,
+       p.proname,
+       CASE p.prokind
+           WHEN 'p' THEN 'procedure'
+           WHEN 'a' THEN 'aggregate'
+           WHEN 'w' THEN 'window'
+           ELSE 'function'
+       END,
+       format('(%s)%s %s',
+              pg_get_function_identity_arguments(p.oid),
+              ' returns ' || pg_get_function_result(p.oid),
+              l.lanname)
+FROM pg_proc p
+JOIN pg_namespace n ON n.oid = p.pronamespace
+JOIN pg_language l ON l.oid = p.prolang
+WHERE n.nspname = $1
+ORDER BY p.proname;",
+        )
+    }
+
+    fn list_triggers(&self) -> Result<Vec<base::SchemaObject>> {
+        self.list_objects(
+            "
+SELECT t.oid::text,
+       t.tgname,
+       substring(pg_get_triggerdef(t.oid) FROM '((?:BEFORE|AFTER|INSTEAD OF) .*?) ON '),
+       c.relname
+FROM pg_trigger t
+JOIN pg_class c ON c.oid = t.tgrelid
+JOIN pg_namespace n ON n.oid = c.relnamespace
+WHERE n.nspname = $1 AND NOT t.tgisinternal
+ORDER BY c.relname, t.tgname;",
+        )
+    }
+
+    fn list_sequences(&self) -> Result<Vec<base::SchemaObject>> {
+        self.list_objects(
+            "
+SELECT format('%I.%I', schemaname, sequencename),
+       sequencename,
+       data_type::text,
+       format('start %s, increment %s, last %s',
+              start_value, increment_by, coalesce(last_value::text, 'none'))
+FROM pg_sequences
+WHERE schemaname = $1
+ORDER BY sequencename;",
+        )
+    }
+
+    fn list_enums(&self) -> Result<Vec<base::SchemaObject>> {
+        self.list_objects(
+            "
+SELECT t.oid::text,
+       t.typname,
+       'enum',
+       string_agg(quote_literal(e.enumlabel), ', ' ORDER BY e.enumsortorder)
+FROM pg_type t
+JOIN pg_namespace n ON n.oid = t.typnamespace
+JOIN pg_enum e ON e.enumtypid = t.oid
+WHERE n.nspname = $1
+GROUP BY t.oid, t.typname
+ORDER BY t.typname;",
+        )
+    }
+
+    fn get_definition(&self, kind: base::ObjectKind, id: &str) -> Result<String> {
+        let query = match kind {
+            base::ObjectKind::Table => return self.table_definition(id),
+            base::ObjectKind::Index => return self.index_definition(id),
+            base::ObjectKind::View => {
+                "
+SELECT format(E'CREATE %s %s AS\\n%s',
+              CASE c.relkind WHEN 'm' THEN 'MATERIALIZED VIEW' ELSE 'VIEW' END,
+              c.oid::regclass,
+              pg_get_viewdef(c.oid, true))
+FROM pg_class c
+WHERE c.oid = $1::text::oid;"
+            }
+            base::ObjectKind::Function => {
+                "SELECT rtrim(pg_get_functiondef($1::text::oid), E'\\n') || ';';"
+            }
+            base::ObjectKind::Trigger => "SELECT pg_get_triggerdef($1::text::oid, true) || ';';",
+            base::ObjectKind::Sequence => {
+                "
+SELECT format(E'CREATE SEQUENCE %I.%I\\n    AS %s\\n    START WITH %s\\n    INCREMENT BY %s\\n    MINVALUE %s\\n    MAXVALUE %s\\n    CACHE %s%s;',
+              schemaname, sequencename, data_type, start_value, increment_by,
+              min_value, max_value, cache_size,
+              CASE WHEN cycle THEN E'\\n    CYCLE' ELSE '' END)
+FROM pg_sequences
+WHERE format('%I.%I', schemaname, sequencename) = $1;"
+            }
+            base::ObjectKind::Enum => {
+                "
+SELECT format(E'CREATE TYPE %s AS ENUM (\\n    %s\\n);',
+              t.oid::regtype,
+              string_agg(quote_literal(e.enumlabel), E',\\n    ' ORDER BY e.enumsortorder))
+FROM pg_type t
+JOIN pg_enum e ON e.enumtypid = t.oid
+WHERE t.oid = $1::text::oid
+GROUP BY t.oid;"
+            }
+        };
+        let id = id.to_string();
+        let row = self.with_client(|client| Ok(client.query_opt(query, &[&id])?))?;
+        row.and_then(|row| row.get(0))
+            .ok_or(anyhow::anyhow!("The {:?} no longer exists", kind))
     }
 }
 
@@ -185,14 +1129,15 @@ impl PostgreSQLDatabase {
     pub fn new(
         config: Connection,
         selections: HashMap<types::WindowTypeID, Vec<String>>,
-        query: Option<String>,
+        page_size: usize,
+        session: Arc<Session<PgConnection>>,
     ) -> Result<Self> {
         let dsn = Dsn::from_str(&config.dsn)?;
         Ok(PostgreSQLDatabase {
-            name: config.name,
-            dsn: dsn,
+            dsn,
             selections,
-            query,
+            page_size,
+            session,
         })
     }
 
@@ -207,7 +1152,215 @@ impl PostgreSQLDatabase {
         Some(value[0].clone())
     }
 
-    fn get_client(&self) -> Result<Client> {
+    /// Postgres has no SHOW CREATE TABLE, the statement is put together from the catalogs.
+    fn table_definition(&self, name: &str) -> Result<String> {
+        let schema = self
+            .get_selection(types::WindowTypeID::SCHEMAS)
+            .unwrap_or("public".to_string());
+        let relation = self
+            .with_client(|client| {
+                Ok(client.query_opt(
+                    "
+SELECT c.oid::text,
+       c.relkind::text,
+       format('%I.%I', n.nspname, c.relname),
+       CASE WHEN c.relkind = 'f' THEN 'FOREIGN TABLE'
+            WHEN c.relpersistence = 'u' THEN 'UNLOGGED TABLE'
+            ELSE 'TABLE' END,
+       (SELECT format(' PARTITION OF %s %s', i.inhparent::regclass,
+                      pg_get_expr(c.relpartbound, c.oid, true))
+        FROM pg_inherits i
+        WHERE c.relispartition AND i.inhrelid = c.oid),
+       CASE WHEN c.relkind = 'p' THEN ' PARTITION BY ' || pg_get_partkeydef(c.oid) END,
+       (SELECT format(' SERVER %I', s.srvname)
+        FROM pg_foreign_table ft
+        JOIN pg_foreign_server s ON s.oid = ft.ftserver
+        WHERE ft.ftrelid = c.oid),
+       obj_description(c.oid, 'pg_class')
+FROM pg_class c
+JOIN pg_namespace n ON n.oid = c.relnamespace
+WHERE n.nspname = $1 AND c.relname = $2;",
+                    &[&schema, &name],
+                )?)
+            })?
+            .ok_or(anyhow::anyhow!("The table {} no longer exists", name))?;
+        let oid: String = relation.get(0);
+        let relkind: String = relation.get(1);
+        if relkind == "v" || relkind == "m" {
+            return base::ConnectionType::get_definition(self, base::ObjectKind::View, &oid);
+        }
+        let qualified: String = relation.get(2);
+        let partition_of: Option<String> = relation.get(4);
+
+        let (columns, constraints, indexes) = self.with_client(|client| {
+            let columns = client.query(
+                "
+SELECT format('%I %s', a.attname, format_type(a.atttypid, a.atttypmod))
+       || CASE WHEN a.attidentity = 'a' THEN ' GENERATED ALWAYS AS IDENTITY'
+               WHEN a.attidentity = 'd' THEN ' GENERATED BY DEFAULT AS IDENTITY'
+               WHEN a.attgenerated = 's'
+                   THEN format(' GENERATED ALWAYS AS (%s) STORED', pg_get_expr(d.adbin, d.adrelid))
+               WHEN d.adbin IS NOT NULL THEN ' DEFAULT ' || pg_get_expr(d.adbin, d.adrelid)
+               ELSE '' END
+       || CASE WHEN a.attnotnull THEN ' NOT NULL' ELSE '' END,
+       a.attislocal,
+       format('COMMENT ON COLUMN %s.%I IS ', $2::text, a.attname)
+       || quote_literal(col_description(a.attrelid, a.attnum)) || ';'
+FROM pg_attribute a
+LEFT JOIN pg_attrdef d ON d.adrelid = a.attrelid AND d.adnum = a.attnum
+WHERE a.attrelid = $1::text::oid AND a.attnum > 0 AND NOT a.attisdropped
+ORDER BY a.attnum;",
+                &[&oid, &qualified],
+            )?;
+            let constraints = client.query(
+                "
+SELECT format('CONSTRAINT %I %s', conname, pg_get_constraintdef(oid, true)), conislocal
+FROM pg_constraint
+WHERE conrelid = $1::text::oid AND contype IN ('p', 'u', 'f', 'c', 'x')
+ORDER BY array_position(ARRAY['p', 'u', 'x', 'f', 'c'], contype::text), conname;",
+                &[&oid],
+            )?;
+            // indexes backing a constraint are created by it
+            let indexes = client.query(
+                "
+SELECT pg_get_indexdef(ix.indexrelid) || ';',
+       format('COMMENT ON INDEX %s IS ', ix.indexrelid::regclass)
+       || quote_literal(obj_description(ix.indexrelid, 'pg_class')) || ';'
+FROM pg_index ix
+JOIN pg_class i ON i.oid = ix.indexrelid
+WHERE ix.indrelid = $1::text::oid
+  AND NOT EXISTS (SELECT 1 FROM pg_constraint con WHERE con.conindid = ix.indexrelid
+                                                    AND con.contype IN ('p', 'u', 'x'))
+ORDER BY i.relname;",
+                &[&oid],
+            )?;
+            Ok((columns, constraints, indexes))
+        })?;
+
+        // partitions take their columns and inherited constraints from the parent
+        let partition = partition_of.is_some();
+        let mut body: Vec<String> = columns
+            .iter()
+            .filter(|r| !partition || r.get::<_, bool>(1))
+            .map(|r| r.get(0))
+            .collect();
+        body.extend(
+            constraints
+                .iter()
+                .filter(|r| !partition || r.get::<_, bool>(1))
+                .map(|r| r.get::<_, String>(0)),
+        );
+        let mut definition = format!(
+            "CREATE {} {}{}",
+            relation.get::<_, String>(3),
+            qualified,
+            partition_of.unwrap_or_default()
+        );
+        if !body.is_empty() || !partition {
+            definition.push_str(&format!(" (\n    {}\n)", body.join(",\n    ")));
+        }
+        for clause in [5, 6] {
+            definition.push_str(
+                &relation
+                    .get::<_, Option<String>>(clause)
+                    .unwrap_or_default(),
+            );
+        }
+        definition.push(';');
+
+        if !indexes.is_empty() {
+            definition.push('\n');
+        }
+        for index in indexes.iter() {
+            definition.push_str(&format!("\n{}", index.get::<_, String>(0)));
+        }
+
+        // quote_literal() leaves out the comments that are not set
+        let mut comments: Vec<String> = vec![];
+        if let Some(comment) = relation.get::<_, Option<String>>(7) {
+            comments.push(format!(
+                "COMMENT ON TABLE {} IS '{}';",
+                qualified,
+                comment.replace('\'', "''")
+            ));
+        }
+        comments.extend(columns.iter().filter_map(|r| r.get::<_, Option<String>>(2)));
+        comments.extend(indexes.iter().filter_map(|r| r.get::<_, Option<String>>(1)));
+        if !comments.is_empty() {
+            definition.push_str(&format!("\n\n{}", comments.join("\n")));
+        }
+        Ok(definition)
+    }
+
+    fn index_definition(&self, name: &str) -> Result<String> {
+        let schema = self
+            .get_selection(types::WindowTypeID::SCHEMAS)
+            .unwrap_or("public".to_string());
+        let name = name.to_string();
+        let row = self.with_client(|client| {
+            Ok(client.query_opt(
+                "
+SELECT pg_get_indexdef(i.oid) || ';'
+       || coalesce(format(E'\\n\\nCOMMENT ON INDEX %s IS ', i.oid::regclass)
+                   || quote_literal(obj_description(i.oid, 'pg_class')) || ';', '')
+FROM pg_class i
+JOIN pg_namespace n ON n.oid = i.relnamespace
+WHERE n.nspname = $1 AND i.relname = $2 AND i.relkind IN ('i', 'I');",
+                &[&schema, &name],
+            )?)
+        })?;
+        row.map(|row| row.get(0))
+            .ok_or(anyhow::anyhow!("The index {} no longer exists", name))
+    }
+
+    /// Runs a query listing objects of the selected schema as id, name, kind and detail.
+    fn list_objects(&self, query: &str) -> Result<Vec<base::SchemaObject>> {
+        let schema = self
+            .get_selection(types::WindowTypeID::SCHEMAS)
+            .unwrap_or("public".to_string());
+        let raw_objects = self.with_client(|client| Ok(client.query(query, &[&schema])?))?;
+        Ok(raw_objects
+            .iter()
+            .map(|r| base::SchemaObject {
+                id: r.get(0),
+                name: r.get(1),
+                kind: r.get::<_, Option<String>>(2).unwrap_or_default(),
+                detail: r.get::<_, Option<String>>(3).unwrap_or_default(),
+            })
+            .collect())
+    }
+
+    /// For reads only, `op` runs again on a new connection when the first one was lost.
+    fn with_client<T>(&self, mut op: impl FnMut(&mut Client) -> Result<T>) -> Result<T> {
+        self.session.run_read_only(
+            || self.connect(),
+            |conn| !conn.client.is_closed(),
+            self.tracked(|conn| op(&mut conn.client)),
+        )
+    }
+
+    fn with_connection<T>(&self, op: impl FnMut(&mut PgConnection) -> Result<T>) -> Result<T> {
+        self.session.run(
+            || self.connect(),
+            |conn| !conn.client.is_closed(),
+            self.tracked(op),
+        )
+    }
+
+    fn tracked<'a, T>(
+        &'a self,
+        mut op: impl FnMut(&mut PgConnection) -> Result<T> + 'a,
+    ) -> impl FnMut(&mut PgConnection) -> Result<T> + 'a {
+        move |conn| {
+            // only report notices raised by this operation
+            conn.take_notices();
+            let result = op(conn);
+            self.session.set_in_transaction(conn.in_transaction);
+            result
+        }
+    }
+
+    fn connect(&self) -> Result<PgConnection> {
         let addr = self.dsn.addresses.first().unwrap();
         let conn_string = &format!(
             "host={} port={} dbname={} user={} password={} ",
@@ -218,6 +1371,23 @@ impl PostgreSQLDatabase {
             self.dsn.username.clone().unwrap_or("postgres".to_string()),
             self.dsn.password.clone().unwrap_or("".to_string())
         );
-        return Ok(Client::connect(conn_string.as_str(), NoTls)?);
+        let notices = Arc::new(Mutex::new(vec![]));
+        let sink = Arc::clone(&notices);
+        let mut config: postgres::Config = conn_string.parse()?;
+        config.notice_callback(move |notice| {
+            sink.lock()
+                .unwrap()
+                .push(format!("{}: {}", notice.severity(), notice.message()));
+        });
+        let client = config.connect(NoTls)?;
+        let token = client.cancel_token();
+        self.session
+            .set_canceller(Arc::new(move || Ok(token.cancel_query(NoTls)?)));
+        Ok(PgConnection {
+            client,
+            cursor: None,
+            in_transaction: false,
+            notices,
+        })
     }
 }
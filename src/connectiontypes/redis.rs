@@ -1,14 +1,84 @@
+use crate::connectiontypes::session::Session;
 use crate::ui::types;
 use crate::{connectiontypes::base, data::Connection};
 use anyhow::Result;
-use redis;
+use redis::ConnectionLike;
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+fn to_query_result(
+    keys: Vec<String>,
+    values: Vec<Option<String>>,
+    next_page: Option<base::PageCursor>,
+) -> base::QueryResult {
+    // Combine keys and values
+    let rows: Vec<base::QueryResultRow> = keys
+        .into_iter()
+        .zip(values)
+        .map(|(key, value)| base::QueryResultRow {
+            data: vec![key.into(), value.into()],
+        })
+        .collect();
+
+    base::QueryResult {
+        columns: vec!["key".to_string(), "value".to_string()],
+        rows,
+        next_page,
+        outcome: base::ExecutionOutcome::rows(),
+    }
+}
+
+fn get_query_parts(query: &str) -> Vec<String> {
+    query.split_whitespace().map(|s| s.to_string()).collect()
+}
+
+fn reply_to_string(reply: &redis::Value) -> String {
+    match reply {
+        redis::Value::Nil => "(nil)".to_string(),
+        redis::Value::Int(i) => i.to_string(),
+        redis::Value::Data(bytes) => String::from_utf8_lossy(bytes).to_string(),
+        redis::Value::Bulk(items) => items
+            .iter()
+            .map(reply_to_string)
+            .collect::<Vec<_>>()
+            .join(" "),
+        redis::Value::Status(status) => status.clone(),
+        redis::Value::Okay => "OK".to_string(),
+    }
+}
+
+fn reply_to_query_result(command: &str, reply: redis::Value) -> base::QueryResult {
+    let items = match reply {
+        redis::Value::Bulk(items) => items,
+        redis::Value::Data(_) => vec![reply],
+        // status and integer replies are what a command reports back, not data
+        _ => {
+            let tag = format!("{} {}", command.to_uppercase(), reply_to_string(&reply));
+            return base::QueryResult {
+                outcome: base::ExecutionOutcome::command(tag, None),
+                ..base::QueryResult::empty()
+            };
+        }
+    };
+    let rows = items
+        .iter()
+        .map(|item| base::QueryResultRow {
+            data: vec![reply_to_string(item).into()],
+        })
+        .collect();
+    base::QueryResult {
+        columns: vec!["value".to_string()],
+        rows,
+        next_page: None,
+        outcome: base::ExecutionOutcome::rows(),
+    }
+}
 
 pub struct RedisConnectionType {
-    name: String,
     dsn: String,
-    selections: HashMap<types::WindowTypeID, Vec<String>>,
-    query: Option<String>,
+    page_size: usize,
+    session: Arc<Session<redis::Connection>>,
 }
 impl base::ConnectionType for RedisConnectionType {
     fn list_tables(&self) -> Result<Vec<base::Table>> {
@@ -16,53 +86,35 @@ impl base::ConnectionType for RedisConnectionType {
     }
 
     fn default_query_string(&self) -> String {
-        return "SCAN 0 COUNT 50".to_string();
+        format!("SCAN 0 COUNT {}", self.page_size)
     }
 
-    fn query(&self) -> Result<base::QueryResult> {
-        let mut con = self.get_client()?.get_connection()?;
-        let mut qs = self.default_query_string();
-        if let Some(custom_query) = self.query.clone() {
-            qs = custom_query;
-        }
-
-        qs = qs.split("\n").collect::<Vec<_>>().join(" ");
-        let qs = qs
-            .split(" ")
-            .filter(|s| !s.trim().is_empty())
-            .collect::<Vec<&str>>();
-        let mut command = redis::cmd(qs[0]);
-        for arg in qs.iter().skip(1) {
-            command.arg(*arg);
-        }
-
-        let mut keys = vec![];
-        let mut values = vec![];
-        if qs[0] == "SCAN" {
-            let (_, mut chunk): (usize, Vec<String>) = command.query(&mut con)?;
-            keys.append(&mut chunk);
+    fn execute(&self, query: &str, _params: &base::QueryParams) -> Result<base::QueryResult> {
+        let qs = get_query_parts(query);
+        let qs = qs.iter().map(|s| s.as_str()).collect::<Vec<&str>>();
+        let started = Instant::now();
+        let mut result = if qs[0].to_uppercase() == "SCAN" {
+            let cursor = qs.get(1).and_then(|c| c.parse().ok()).unwrap_or(0);
+            self.scan(&qs, cursor)?
         } else {
-            keys = command.query(&mut con)?;
-        }
+            self.run_command(&qs)?
+        };
+        result.outcome.elapsed = started.elapsed();
+        Ok(result)
+    }
 
-        if ["SCAN", "KEYS"].contains(&qs[0].to_uppercase().as_str()) && !keys.is_empty() {
-            values = redis::cmd("MGET").arg(keys.as_slice()).query(&mut con)?;
+    fn fetch_page(
+        &self,
+        statement: &str,
+        _params: &base::QueryParams,
+        cursor: &base::PageCursor,
+    ) -> Result<base::QueryResult> {
+        let qs = get_query_parts(statement);
+        let qs = qs.iter().map(|s| s.as_str()).collect::<Vec<&str>>();
+        match cursor {
+            base::PageCursor::Scan(cursor) => self.scan(&qs, *cursor),
+            _ => Err(anyhow::anyhow!("Unsupported page cursor {:?}", cursor)),
         }
-
-        // Combine keys and values
-        let rows: Vec<base::QueryResultRow> = keys
-            .into_iter()
-            .zip(values.into_iter())
-            .map(|(key, value)| base::QueryResultRow {
-                id: key.clone(),
-                data: vec![Some(key), value],
-            })
-            .collect();
-
-        Ok(base::QueryResult {
-            columns: vec!["key".to_string(), "value".to_string()],
-            rows: rows,
-        })
     }
 
     fn list_schemas(&self) -> Result<Vec<base::Schema>> {
@@ -73,10 +125,10 @@ impl base::ConnectionType for RedisConnectionType {
         Ok(vec![])
     }
 
-    fn list_columns(&self) -> Result<Vec<String>> {
+    fn list_columns(&self) -> Result<Vec<base::ColumnInfo>> {
         Ok(vec![])
     }
-    fn list_indexes(&self) -> Result<Vec<String>> {
+    fn list_indexes(&self) -> Result<Vec<base::IndexInfo>> {
         Ok(vec![])
     }
 }
@@ -84,30 +136,93 @@ impl base::ConnectionType for RedisConnectionType {
 impl RedisConnectionType {
     pub fn new(
         config: Connection,
-        selections: HashMap<types::WindowTypeID, Vec<String>>,
-        query: Option<String>,
+        _selections: HashMap<types::WindowTypeID, Vec<String>>,
+        page_size: usize,
+        session: Arc<Session<redis::Connection>>,
     ) -> Result<Self> {
         Ok(RedisConnectionType {
-            name: config.name,
             dsn: config.dsn,
-            selections,
-            query,
+            page_size,
+            session,
         })
     }
 
-    fn get_selection(&self, selection_type: types::WindowTypeID) -> Option<String> {
-        if !self.selections.contains_key(&selection_type) {
-            return None;
+    fn run_command(&self, qs: &[&str]) -> Result<base::QueryResult> {
+        let mut command = redis::cmd(qs[0]);
+        for arg in qs.iter().skip(1) {
+            command.arg(*arg);
         }
-        let value = self.selections.get(&selection_type).unwrap();
-        if value.is_empty() {
-            return None;
+
+        if qs[0].to_uppercase() != "KEYS" {
+            let reply: redis::Value = self.with_connection(|con| Ok(command.query(con)?))?;
+            return Ok(reply_to_query_result(qs[0], reply));
         }
-        Some(value[0].clone())
+
+        let (keys, values) = self.with_connection(|con| {
+            let keys: Vec<String> = command.query(con)?;
+            let mut values: Vec<Option<String>> = vec![];
+            if !keys.is_empty() {
+                values = redis::cmd("MGET").arg(keys.as_slice()).query(con)?;
+            }
+            Ok((keys, values))
+        })?;
+
+        Ok(to_query_result(keys, values, None))
+    }
+
+    /// Scan from `cursor` until a page worth of keys is found or the keyspace is exhausted.
+    fn scan(&self, qs: &[&str], cursor: u64) -> Result<base::QueryResult> {
+        // everything after the cursor, e.g. MATCH and COUNT
+        let options = qs.iter().skip(2).cloned().collect::<Vec<&str>>();
+        let has_count = options.iter().any(|o| o.to_uppercase() == "COUNT");
+        let (keys, values, cursor) = self.with_read_connection(|con| {
+            let mut keys: Vec<String> = vec![];
+            let mut cursor = cursor;
+            loop {
+                let mut command = redis::cmd("SCAN");
+                command.arg(cursor);
+                for option in options.iter() {
+                    command.arg(*option);
+                }
+                if !has_count {
+                    command.arg("COUNT").arg(self.page_size);
+                }
+                let (next, mut chunk): (u64, Vec<String>) = command.query(con)?;
+                keys.append(&mut chunk);
+                cursor = next;
+                if cursor == 0 || keys.len() >= self.page_size {
+                    break;
+                }
+            }
+            let mut values: Vec<Option<String>> = vec![];
+            if !keys.is_empty() {
+                values = redis::cmd("MGET").arg(keys.as_slice()).query(con)?;
+            }
+            Ok((keys, values, cursor))
+        })?;
+
+        let next_page = if cursor == 0 {
+            None
+        } else {
+            Some(base::PageCursor::Scan(cursor))
+        };
+        Ok(to_query_result(keys, values, next_page))
+    }
+
+    fn with_connection<T>(&self, op: impl FnMut(&mut redis::Connection) -> Result<T>) -> Result<T> {
+        self.session.run(|| self.connect(), |con| con.is_open(), op)
+    }
+
+    fn with_read_connection<T>(
+        &self,
+        op: impl FnMut(&mut redis::Connection) -> Result<T>,
+    ) -> Result<T> {
+        self.session
+            .run_read_only(|| self.connect(), |con| con.is_open(), op)
     }
 
-    fn get_client(&self) -> Result<redis::Client> {
+    fn connect(&self) -> Result<redis::Connection> {
         let client = redis::Client::open(self.dsn.as_str())?;
-        Ok(client)
+        Ok(client.get_connection()?)
     }
 }
@@ -0,0 +1,289 @@
+use crate::data::Connection;
+use crate::ui::types;
+use anyhow::{anyhow, Result};
+use log::{info, warn};
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+pub const IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SessionKey {
+    pub connection_id: String,
+    pub database: Option<String>,
+}
+
+impl SessionKey {
+    pub fn new(conn: &Connection, selections: &HashMap<types::WindowTypeID, Vec<String>>) -> Self {
+        SessionKey {
+            connection_id: conn.id.clone(),
+            database: selections
+                .get(&types::WindowTypeID::DATABASES)
+                .and_then(|dbs| dbs.first().cloned()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SessionStatus {
+    Disconnected,
+    Connecting,
+    Connected,
+    Idle,
+    Failed(String),
+}
+
+impl fmt::Display for SessionStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionStatus::Disconnected => write!(f, "disconnected"),
+            SessionStatus::Connecting => write!(f, "connecting"),
+            SessionStatus::Connected => write!(f, "connected"),
+            SessionStatus::Idle => write!(f, "idle (closed)"),
+            SessionStatus::Failed(err) => write!(f, "failed: {}", err),
+        }
+    }
+}
+
+pub type Canceller = Arc<dyn Fn() -> Result<()> + Send + Sync>;
+
+/// A live client for one backend, reused across `ConnectionType` instances.
+pub struct Session<C> {
+    client: Mutex<Option<C>>,
+    status: RwLock<SessionStatus>,
+    last_used: Mutex<Instant>,
+    // kept outside of `client` so a running query can be stopped while it holds the client lock
+    canceller: Mutex<Option<Canceller>>,
+    // an open transaction pins the client, it is never closed or silently reconnected
+    in_transaction: AtomicBool,
+}
+
+impl<C: Send> Session<C> {
+    fn new() -> Self {
+        Session {
+            client: Mutex::new(None),
+            status: RwLock::new(SessionStatus::Disconnected),
+            last_used: Mutex::new(Instant::now()),
+            canceller: Mutex::new(None),
+            in_transaction: AtomicBool::new(false),
+        }
+    }
+
+    pub fn set_canceller(&self, canceller: Canceller) {
+        *self.canceller.lock().unwrap() = Some(canceller);
+    }
+
+    pub fn in_transaction(&self) -> bool {
+        self.in_transaction.load(Ordering::SeqCst)
+    }
+
+    pub fn set_in_transaction(&self, in_transaction: bool) {
+        self.in_transaction.store(in_transaction, Ordering::SeqCst);
+    }
+
+    fn lose_transaction(&self) -> anyhow::Error {
+        self.set_in_transaction(false);
+        self.set_status(SessionStatus::Disconnected);
+        anyhow!("The connection was lost, the open transaction was rolled back")
+    }
+
+    fn set_status(&self, status: SessionStatus) {
+        *self.status.write().unwrap() = status;
+    }
+
+    /// Run `op` against the cached client, connecting first if needed.
+    /// A client that died since the last call is replaced before `op` runs, but `op`
+    /// itself is never run twice: it may have reached the server before the connection
+    /// dropped, and writes must not be applied again.
+    pub fn run<T>(
+        &self,
+        connect: impl Fn() -> Result<C>,
+        is_alive: impl Fn(&mut C) -> bool,
+        op: impl FnMut(&mut C) -> Result<T>,
+    ) -> Result<T> {
+        self.run_with_retry(connect, is_alive, op, false)
+    }
+
+    /// Like `run` for an `op` that changes nothing: when it fails and `is_alive` reports
+    /// the client as dead, the client is reconnected and `op` retried once, unless a
+    /// transaction was open on it.
+    pub fn run_read_only<T>(
+        &self,
+        connect: impl Fn() -> Result<C>,
+        is_alive: impl Fn(&mut C) -> bool,
+        op: impl FnMut(&mut C) -> Result<T>,
+    ) -> Result<T> {
+        self.run_with_retry(connect, is_alive, op, true)
+    }
+
+    fn run_with_retry<T>(
+        &self,
+        connect: impl Fn() -> Result<C>,
+        is_alive: impl Fn(&mut C) -> bool,
+        mut op: impl FnMut(&mut C) -> Result<T>,
+        retry: bool,
+    ) -> Result<T> {
+        let mut client = self.client.lock().unwrap();
+        *self.last_used.lock().unwrap() = Instant::now();
+        let mut retried = !retry;
+        loop {
+            let needs_connect = match client.as_mut() {
+                Some(c) => !is_alive(c),
+                None => true,
+            };
+            if needs_connect && self.in_transaction() {
+                // a new client would quietly run everything outside of the transaction
+                *client = None;
+                return Err(self.lose_transaction());
+            }
+            if needs_connect {
+                self.set_status(SessionStatus::Connecting);
+                match connect() {
+                    Ok(c) => {
+                        *client = Some(c);
+                        self.set_status(SessionStatus::Connected);
+                    }
+                    Err(err) => {
+                        *client = None;
+                        self.set_status(SessionStatus::Failed(err.to_string()));
+                        return Err(err);
+                    }
+                }
+            }
+
+            let result = op(client.as_mut().unwrap());
+            if result.is_err() && !is_alive(client.as_mut().unwrap()) {
+                *client = None;
+                if self.in_transaction() {
+                    return Err(self.lose_transaction());
+                }
+                if !retried {
+                    warn!("Session lost its connection, reconnecting");
+                    retried = true;
+                    continue;
+                }
+                // the next call connects again
+                self.set_status(SessionStatus::Disconnected);
+            }
+            *self.last_used.lock().unwrap() = Instant::now();
+            return result;
+        }
+    }
+}
+
+trait SessionHandle: Send + Sync {
+    fn status(&self) -> SessionStatus;
+    fn idle_for(&self) -> Duration;
+    fn close(&self, status: SessionStatus);
+    fn cancel(&self) -> Result<()>;
+    fn in_transaction(&self) -> bool;
+    fn as_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync>;
+}
+
+impl<C: Send + 'static> SessionHandle for Session<C> {
+    fn status(&self) -> SessionStatus {
+        self.status.read().unwrap().clone()
+    }
+
+    fn idle_for(&self) -> Duration {
+        self.last_used.lock().unwrap().elapsed()
+    }
+
+    fn close(&self, status: SessionStatus) {
+        // a session that is busy running a query is not idle, leave it alone
+        if let Ok(mut client) = self.client.try_lock() {
+            if client.take().is_some() {
+                self.set_in_transaction(false);
+                self.set_status(status);
+            }
+        }
+    }
+
+    fn cancel(&self) -> Result<()> {
+        let canceller = self.canceller.lock().unwrap().clone();
+        match canceller {
+            Some(cancel) => cancel(),
+            None => Ok(()),
+        }
+    }
+
+    fn in_transaction(&self) -> bool {
+        Session::in_transaction(self)
+    }
+
+    fn as_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync> {
+        self
+    }
+}
+
+#[derive(Default)]
+pub struct SessionManager {
+    sessions: RwLock<HashMap<SessionKey, Arc<dyn SessionHandle>>>,
+}
+
+impl SessionManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get<C: Send + 'static>(&self, key: SessionKey) -> Arc<Session<C>> {
+        let mut sessions = self.sessions.write().unwrap();
+        if let Some(handle) = sessions.get(&key) {
+            if let Ok(session) = Arc::clone(handle).as_any().downcast::<Session<C>>() {
+                return session;
+            }
+        }
+        let session = Arc::new(Session::<C>::new());
+        sessions.insert(key, Arc::clone(&session) as Arc<dyn SessionHandle>);
+        session
+    }
+
+    pub fn status(&self, key: &SessionKey) -> SessionStatus {
+        match self.sessions.read().unwrap().get(key) {
+            Some(handle) => handle.status(),
+            None => SessionStatus::Disconnected,
+        }
+    }
+
+    pub fn cancel(&self, key: &SessionKey) -> Result<()> {
+        let handle = self.sessions.read().unwrap().get(key).cloned();
+        match handle {
+            Some(handle) => handle.cancel(),
+            None => Ok(()),
+        }
+    }
+
+    pub fn in_transaction(&self, key: &SessionKey) -> bool {
+        match self.sessions.read().unwrap().get(key) {
+            Some(handle) => handle.in_transaction(),
+            None => false,
+        }
+    }
+
+    pub fn close_idle(&self, timeout: Duration) {
+        for (key, handle) in self.sessions.read().unwrap().iter() {
+            if handle.status() == SessionStatus::Connected
+                && !handle.in_transaction()
+                && handle.idle_for() > timeout
+            {
+                info!("Closing idle session for {:?}", key);
+                handle.close(SessionStatus::Idle);
+            }
+        }
+    }
+
+    pub fn close_connection(&self, connection_id: &str) {
+        let mut sessions = self.sessions.write().unwrap();
+        for (_, handle) in sessions
+            .iter()
+            .filter(|(k, _)| k.connection_id == connection_id)
+        {
+            handle.close(SessionStatus::Disconnected);
+        }
+        sessions.retain(|k, _| k.connection_id != connection_id);
+    }
+}
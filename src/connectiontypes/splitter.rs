@@ -0,0 +1,542 @@
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Dialect {
+    Postgres,
+    MySQL,
+    SQLite,
+}
+
+struct Splitter<'a> {
+    chars: &'a [char],
+    dialect: Dialect,
+    pos: usize,
+    statements: Vec<String>,
+    current: String,
+    has_code: bool,
+    // keywords seen in the current statement, used to find BEGIN ... END blocks
+    words: Vec<String>,
+    depth: usize,
+    delimiter: String,
+    placeholders: Vec<String>,
+    // keywords and names of the whole script, outside of quotes and comments
+    seen: Vec<String>,
+}
+
+impl<'a> Splitter<'a> {
+    fn new(chars: &'a [char], dialect: Dialect) -> Self {
+        Splitter {
+            chars,
+            dialect,
+            pos: 0,
+            statements: vec![],
+            current: String::new(),
+            has_code: false,
+            words: vec![],
+            depth: 0,
+            delimiter: ";".to_string(),
+            placeholders: vec![],
+            seen: vec![],
+        }
+    }
+
+    fn peek(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn starts_with(&self, text: &str) -> bool {
+        text.chars()
+            .enumerate()
+            .all(|(i, c)| self.peek(i) == Some(c))
+    }
+
+    fn take(&mut self, count: usize) {
+        let end = (self.pos + count).min(self.chars.len());
+        self.current.extend(&self.chars[self.pos..end]);
+        self.pos = end;
+    }
+
+    fn take_until(&mut self, terminator: &str) {
+        let start = self.pos;
+        while self.pos < self.chars.len() && !self.starts_with(terminator) {
+            self.pos += 1;
+        }
+        self.pos = (self.pos + terminator.chars().count()).min(self.chars.len());
+        self.current.extend(&self.chars[start..self.pos]);
+    }
+
+    fn finish_statement(&mut self) {
+        let statement = self.current.trim().to_string();
+        if self.has_code {
+            self.statements.push(statement);
+        }
+        self.current.clear();
+        self.has_code = false;
+        self.words.clear();
+        self.depth = 0;
+    }
+
+    fn at_line_start(&self) -> bool {
+        self.chars[..self.pos]
+            .iter()
+            .rev()
+            .take_while(|c| **c != '\n')
+            .all(|c| c.is_whitespace())
+    }
+
+    fn line_comment(&self) -> bool {
+        match self.dialect {
+            // mysql only treats `--` as a comment when followed by whitespace
+            Dialect::MySQL => {
+                self.peek(0) == Some('#')
+                    || (self.starts_with("--") && self.peek(2).is_none_or(|c| c.is_whitespace()))
+            }
+            _ => self.starts_with("--"),
+        }
+    }
+
+    fn block_comment(&mut self) {
+        let mut depth = 0;
+        while self.pos < self.chars.len() {
+            if self.starts_with("/*") {
+                depth += 1;
+                self.take(2);
+            } else if self.starts_with("*/") {
+                depth -= 1;
+                self.take(2);
+                // only postgres nests block comments
+                if depth == 0 || self.dialect != Dialect::Postgres {
+                    return;
+                }
+            } else {
+                self.take(1);
+            }
+        }
+    }
+
+    fn quoted(&mut self, quote: char, backslash_escapes: bool) {
+        self.take(1);
+        while let Some(c) = self.peek(0) {
+            if backslash_escapes && c == '\\' {
+                self.take(2);
+            } else if c == quote {
+                self.take(1);
+                // a doubled quote is an escaped quote
+                if self.peek(0) != Some(quote) {
+                    return;
+                }
+                self.take(1);
+            } else {
+                self.take(1);
+            }
+        }
+    }
+
+    fn dollar_tag(&self) -> Option<String> {
+        let previous = self.pos.checked_sub(1).map(|p| self.chars[p]);
+        if previous.is_some_and(|c| c.is_alphanumeric() || c == '_') {
+            return None;
+        }
+        let mut tag = String::from("$");
+        let mut offset = 1;
+        while let Some(c) = self.peek(offset) {
+            if c == '$' {
+                tag.push(c);
+                return Some(tag);
+            }
+            let valid = c == '_' || c.is_alphabetic() || (offset > 1 && c.is_numeric());
+            if !valid {
+                return None;
+            }
+            tag.push(c);
+            offset += 1;
+        }
+        None
+    }
+
+    fn word(&mut self) {
+        let start = self.pos;
+        while self
+            .peek(0)
+            .is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '$')
+        {
+            self.pos += 1;
+        }
+        let word: String = self.chars[start..self.pos].iter().collect();
+        self.current.push_str(&word);
+        self.has_code = true;
+
+        let word = word.to_uppercase();
+        let after_end = self.words.last().map(|w| w.as_str()) == Some("END");
+        match word.as_str() {
+            // `END CASE` closes the block once, the CASE is part of the closer
+            "CASE" if after_end => {}
+            "CASE" => self.depth += 1,
+            // procedural blocks closed by `END IF` and the like are not counted when opened
+            "END" if ["IF", "LOOP", "WHILE", "REPEAT"].contains(&self.next_word().as_str()) => {}
+            "END" => self.depth = self.depth.saturating_sub(1),
+            // trigger bodies in sqlite and `BEGIN ATOMIC` bodies in postgres contain `;`
+            "BEGIN" if self.dialect == Dialect::SQLite && self.in_create_trigger() => {
+                self.depth += 1
+            }
+            "ATOMIC"
+                if self.dialect == Dialect::Postgres
+                    && self.words.last().map(|w| w.as_str()) == Some("BEGIN") =>
+            {
+                self.depth += 1
+            }
+            _ => {}
+        }
+        self.seen.push(word.clone());
+        self.words.push(word);
+    }
+
+    fn next_word(&self) -> String {
+        self.chars[self.pos..]
+            .iter()
+            .skip_while(|c| c.is_whitespace())
+            .take_while(|c| c.is_alphanumeric() || **c == '_')
+            .collect::<String>()
+            .to_uppercase()
+    }
+
+    fn placeholder(&self) -> Option<String> {
+        let c = self.peek(0)?;
+        let previous = self.pos.checked_sub(1).map(|p| self.chars[p]);
+        if previous.is_some_and(|p| p.is_alphanumeric() || p == '_' || (p == ':' && c == ':')) {
+            return None;
+        }
+        let follows = |offset: usize, digits_only: bool| -> String {
+            self.chars[self.pos + offset..]
+                .iter()
+                .take_while(|c| {
+                    if digits_only {
+                        c.is_ascii_digit()
+                    } else {
+                        c.is_alphanumeric() || **c == '_'
+                    }
+                })
+                .collect()
+        };
+        let named = |prefix: char| -> Option<String> {
+            let name = follows(1, false);
+            if name.is_empty() || name.starts_with(|n: char| n.is_ascii_digit()) {
+                return None;
+            }
+            Some(format!("{}{}", prefix, name))
+        };
+        match (self.dialect, c) {
+            (Dialect::Postgres, '$') => {
+                let number = follows(1, true);
+                (!number.is_empty()).then(|| format!("${}", number))
+            }
+            (Dialect::MySQL, '?') => Some("?".to_string()),
+            (Dialect::SQLite, '?') => Some(format!("?{}", follows(1, true))),
+            // `::` is a cast and `:=` an assignment, neither are placeholders
+            (Dialect::MySQL, ':') | (Dialect::SQLite, ':') if self.peek(1) != Some(':') => {
+                named(':')
+            }
+            (Dialect::SQLite, '@') | (Dialect::SQLite, '$') => named(c),
+            _ => None,
+        }
+    }
+
+    fn in_create_trigger(&self) -> bool {
+        self.words.first().map(|w| w.as_str()) == Some("CREATE")
+            && self.words.iter().take(4).any(|w| w == "TRIGGER")
+    }
+
+    fn delimiter_command(&mut self) -> bool {
+        // the mysql client's DELIMITER command changes the statement terminator
+        if self.dialect != Dialect::MySQL || self.has_code || !self.at_line_start() {
+            return false;
+        }
+        let line: String = self.chars[self.pos..]
+            .iter()
+            .take_while(|c| **c != '\n')
+            .collect();
+        let mut parts = line.split_whitespace();
+        if !parts
+            .next()
+            .is_some_and(|p| p.eq_ignore_ascii_case("DELIMITER"))
+        {
+            return false;
+        }
+        if let Some(delimiter) = parts.next() {
+            self.delimiter = delimiter.to_string();
+        }
+        self.pos += line.chars().count();
+        true
+    }
+
+    fn split(&mut self) -> Vec<String> {
+        while let Some(c) = self.peek(0) {
+            let length = self.current.len();
+            if self.line_comment() || self.starts_with("/*") {
+                if self.line_comment() {
+                    self.take_until("\n");
+                } else {
+                    self.block_comment();
+                }
+                // comments in front of a statement are not part of it
+                if !self.has_code {
+                    self.current.truncate(length);
+                }
+            } else if self.delimiter_command() {
+                continue;
+            } else if self.starts_with(&self.delimiter) && self.depth == 0 {
+                self.pos += self.delimiter.chars().count();
+                self.finish_statement();
+            } else if c == '\'' {
+                let escape_string = self.dialect == Dialect::Postgres
+                    && self.current.ends_with(['E', 'e'])
+                    && !self
+                        .current
+                        .trim_end_matches(['E', 'e'])
+                        .ends_with(|p: char| p.is_alphanumeric() || p == '_');
+                self.has_code = true;
+                self.quoted(c, self.dialect == Dialect::MySQL || escape_string);
+            } else if c == '"' {
+                self.has_code = true;
+                self.quoted(c, self.dialect == Dialect::MySQL);
+            } else if c == '`' && self.dialect != Dialect::Postgres {
+                self.has_code = true;
+                self.quoted(c, false);
+            } else if c == '[' && self.dialect == Dialect::SQLite {
+                self.has_code = true;
+                self.take_until("]");
+            } else if c == '$' && self.dialect == Dialect::Postgres && self.dollar_tag().is_some() {
+                let tag = self.dollar_tag().unwrap();
+                self.has_code = true;
+                self.take(tag.chars().count());
+                self.take_until(&tag);
+            } else if let Some(placeholder) = self.placeholder() {
+                self.has_code = true;
+                self.take(placeholder.chars().count());
+                self.placeholders.push(placeholder);
+            } else if c.is_alphanumeric() || c == '_' {
+                self.word();
+            } else {
+                if !c.is_whitespace() {
+                    self.has_code = true;
+                }
+                self.take(1);
+            }
+        }
+        self.finish_statement();
+        std::mem::take(&mut self.statements)
+    }
+}
+
+/// Splits a script into its statements, leaving out empty ones and the terminators.
+/// Quotes, comments, postgres dollar quoting and `BEGIN ... END` bodies are respected.
+pub fn split_statements(script: &str, dialect: Dialect) -> Vec<String> {
+    let chars: Vec<char> = script.chars().collect();
+    Splitter::new(&chars, dialect).split()
+}
+
+/// The keywords and unquoted names of a statement in upper case, leaving out
+/// quoted text, quoted names and comments.
+pub fn statement_words(statement: &str, dialect: Dialect) -> Vec<String> {
+    let chars: Vec<char> = statement.chars().collect();
+    let mut splitter = Splitter::new(&chars, dialect);
+    splitter.split();
+    splitter.seen
+}
+
+/// Lists the bind parameters of a statement in the order the driver binds them:
+/// `$1..$n` for postgres, every `?` and each `:name` once for mysql,
+/// every `?` and each `?NNN`, `:name`, `@name` or `$name` once for sqlite.
+pub fn find_placeholders(statement: &str, dialect: Dialect) -> Vec<String> {
+    let chars: Vec<char> = statement.chars().collect();
+    let mut splitter = Splitter::new(&chars, dialect);
+    splitter.split();
+    let found = splitter.placeholders;
+    if dialect == Dialect::Postgres {
+        let last = found
+            .iter()
+            .filter_map(|p| p[1..].parse::<usize>().ok())
+            .max()
+            .unwrap_or(0);
+        return (1..=last).map(|n| format!("${}", n)).collect();
+    }
+    let mut placeholders: Vec<String> = vec![];
+    for placeholder in found {
+        if placeholder == "?" || !placeholders.contains(&placeholder) {
+            placeholders.push(placeholder);
+        }
+    }
+    placeholders
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(script: &str, dialect: Dialect) -> Vec<String> {
+        split_statements(script, dialect)
+    }
+
+    #[test]
+    fn terminators_inside_quotes() {
+        assert_eq!(
+            split("SELECT 'a;b'; SELECT \"c;d\";", Dialect::Postgres),
+            vec!["SELECT 'a;b'", "SELECT \"c;d\""]
+        );
+        assert_eq!(
+            split("SELECT 'it''s;'; SELECT 2", Dialect::SQLite),
+            vec!["SELECT 'it''s;'", "SELECT 2"]
+        );
+        assert_eq!(
+            split("SELECT `a;b`, [c;d] FROM t; SELECT 2", Dialect::SQLite),
+            vec!["SELECT `a;b`, [c;d] FROM t", "SELECT 2"]
+        );
+    }
+
+    #[test]
+    fn backslash_escapes() {
+        // only mysql strings and postgres E'' strings escape with a backslash
+        assert_eq!(
+            split(r"SELECT 'a\';b'; SELECT 2", Dialect::MySQL),
+            vec![r"SELECT 'a\';b'", "SELECT 2"]
+        );
+        assert_eq!(
+            split(r"SELECT E'a\';b'; SELECT 2", Dialect::Postgres),
+            vec![r"SELECT E'a\';b'", "SELECT 2"]
+        );
+        assert_eq!(
+            split(r"SELECT 'a\'; SELECT 2", Dialect::Postgres),
+            vec![r"SELECT 'a\'", "SELECT 2"]
+        );
+    }
+
+    #[test]
+    fn dollar_quoting() {
+        let script = "CREATE FUNCTION f() RETURNS int AS $$ SELECT 1; $$ LANGUAGE sql;
+CREATE FUNCTION g() RETURNS int AS $body$ SELECT $$;$$; $body$ LANGUAGE sql;
+SELECT $1";
+        assert_eq!(
+            split(script, Dialect::Postgres),
+            vec![
+                "CREATE FUNCTION f() RETURNS int AS $$ SELECT 1; $$ LANGUAGE sql",
+                "CREATE FUNCTION g() RETURNS int AS $body$ SELECT $$;$$; $body$ LANGUAGE sql",
+                "SELECT $1",
+            ]
+        );
+    }
+
+    #[test]
+    fn comments() {
+        assert_eq!(
+            split(
+                "-- a;\nSELECT 1; /* b; */ SELECT 2 -- c;\n;",
+                Dialect::Postgres
+            ),
+            vec!["SELECT 1", "SELECT 2 -- c;"]
+        );
+        // postgres nests block comments, the others end at the first `*/`
+        assert_eq!(
+            split("SELECT /* a /* b */ ; */ 1", Dialect::Postgres),
+            vec!["SELECT /* a /* b */ ; */ 1"]
+        );
+        assert_eq!(
+            split("# a;\nSELECT 1 --x;\n", Dialect::MySQL),
+            vec!["SELECT 1 --x"]
+        );
+        assert!(split("-- only a comment;\n/* ; */", Dialect::SQLite).is_empty());
+    }
+
+    #[test]
+    fn delimiter_command() {
+        let script = "DELIMITER //
+CREATE PROCEDURE p() BEGIN SELECT 1; SELECT 2; END//
+DELIMITER ;
+SELECT 3;";
+        assert_eq!(
+            split(script, Dialect::MySQL),
+            vec![
+                "CREATE PROCEDURE p() BEGIN SELECT 1; SELECT 2; END",
+                "SELECT 3",
+            ]
+        );
+    }
+
+    #[test]
+    fn case_nesting() {
+        assert_eq!(
+            split(
+                "SELECT CASE WHEN a THEN CASE b WHEN 1 THEN 2 END END; SELECT 2",
+                Dialect::Postgres
+            ),
+            vec![
+                "SELECT CASE WHEN a THEN CASE b WHEN 1 THEN 2 END END",
+                "SELECT 2"
+            ]
+        );
+    }
+
+    #[test]
+    fn end_keyword_closes_once() {
+        let script = "DELIMITER //
+CREATE PROCEDURE p(x INT) BEGIN
+  IF x > 1 THEN SELECT 3; END IF;
+  WHILE x > 0 DO SET x = x - 1; END WHILE;
+  CASE x WHEN 1 THEN SELECT 1; ELSE SELECT 2; END CASE;
+  CASE WHEN x > 2 THEN SELECT 4; END CASE;
+END//
+DELIMITER ;
+SELECT 5;";
+        let statements = split(script, Dialect::MySQL);
+        assert_eq!(statements.len(), 2);
+        assert!(statements[0].ends_with("END CASE;\nEND"));
+        assert_eq!(statements[1], "SELECT 5");
+    }
+
+    #[test]
+    fn trigger_and_atomic_bodies() {
+        let script = "CREATE TRIGGER t AFTER INSERT ON a BEGIN
+  UPDATE b SET c = CASE WHEN new.x THEN 1 END;
+  DELETE FROM d;
+END;
+SELECT 1";
+        assert_eq!(
+            split(script, Dialect::SQLite),
+            vec![
+                "CREATE TRIGGER t AFTER INSERT ON a BEGIN
+  UPDATE b SET c = CASE WHEN new.x THEN 1 END;
+  DELETE FROM d;
+END",
+                "SELECT 1",
+            ]
+        );
+        assert_eq!(
+            split(
+                "CREATE FUNCTION f() RETURNS int BEGIN ATOMIC SELECT 1; END; SELECT 2",
+                Dialect::Postgres
+            ),
+            vec![
+                "CREATE FUNCTION f() RETURNS int BEGIN ATOMIC SELECT 1; END",
+                "SELECT 2"
+            ]
+        );
+        // a plain BEGIN starts a transaction, it is not a block
+        assert_eq!(
+            split("BEGIN; SELECT 1; END;", Dialect::SQLite),
+            vec!["BEGIN", "SELECT 1", "END"]
+        );
+    }
+
+    #[test]
+    fn placeholders() {
+        assert_eq!(
+            find_placeholders("SELECT $2, $1::text, '$3'", Dialect::Postgres),
+            vec!["$1", "$2"]
+        );
+        assert_eq!(
+            find_placeholders("SELECT ?, :a, :a, ?", Dialect::MySQL),
+            vec!["?", ":a", "?"]
+        );
+        assert_eq!(
+            find_placeholders("SELECT ?1, @b, $c, :d, x::int", Dialect::SQLite),
+            vec!["?1", "@b", "$c", ":d"]
+        );
+    }
+}
@@ -1,59 +1,289 @@
+use crate::connectiontypes::session::Session;
+use crate::connectiontypes::splitter;
 use crate::ui::types;
 use crate::{connectiontypes::base, data::Connection};
 use anyhow::Result;
 use log::{debug, info};
 use mdsn::Dsn;
+use rusqlite::types::{Value as SqliteValue, ValueRef};
 use std::collections::HashMap;
 use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Instant;
 
-use rusqlite;
+fn get_row_value(row: &rusqlite::Row, idx: usize, declared_type: Option<&str>) -> base::Value {
+    let raw = match row.get_ref(idx) {
+        Ok(raw) => raw,
+        Err(_) => return base::Value::Null,
+    };
+    // sqlite only has storage classes, the declared column type tells us how a value was meant
+    let declared_type = declared_type.unwrap_or("").to_uppercase();
+    match raw {
+        ValueRef::Null => base::Value::Null,
+        ValueRef::Integer(i) => {
+            if declared_type.starts_with("BOOL") {
+                base::Value::Bool(i != 0)
+            } else {
+                base::Value::Int(i)
+            }
+        }
+        ValueRef::Real(f) => base::Value::Float(f),
+        ValueRef::Text(bytes) => {
+            let text = String::from_utf8_lossy(bytes).to_string();
+            if declared_type.contains("JSON") {
+                if let Ok(json) = serde_json::from_str(&text) {
+                    return base::Value::Json(json);
+                }
+            }
+            base::Value::Text(text)
+        }
+        ValueRef::Blob(bytes) => base::Value::Bytes(bytes.to_vec()),
+    }
+}
+
+fn to_sqlite_value(value: &base::Value) -> SqliteValue {
+    match value {
+        base::Value::Null => SqliteValue::Null,
+        base::Value::Bool(b) => SqliteValue::Integer(*b as i64),
+        base::Value::Int(i) => SqliteValue::Integer(*i),
+        base::Value::Float(f) => SqliteValue::Real(*f),
+        base::Value::Bytes(bytes) => SqliteValue::Blob(bytes.clone()),
+        value => SqliteValue::Text(value.to_string()),
+    }
+}
+
+fn bind_params(stmt: &mut rusqlite::Statement, params: &base::QueryParams) -> Result<()> {
+    for (idx, (name, value)) in params.iter().enumerate() {
+        let position = match name.strip_prefix('?') {
+            Some("") => idx + 1,
+            Some(number) => number.parse()?,
+            None => stmt
+                .parameter_index(name)?
+                .ok_or(anyhow::anyhow!("Unknown parameter {}", name))?,
+        };
+        stmt.raw_bind_parameter(position, to_sqlite_value(value))?;
+    }
+    Ok(())
+}
+
+/// Reads at most `limit` rows after skipping the first `offset` ones.
+fn query_rows(
+    conn: &rusqlite::Connection,
+    query: &str,
+    params: &base::QueryParams,
+    offset: usize,
+    limit: Option<usize>,
+) -> Result<base::QueryResult> {
+    let mut stmt = conn.prepare(query)?;
+    bind_params(&mut stmt, params)?;
+    if stmt.column_count() == 0 {
+        let affected = stmt.raw_execute()?;
+        let tag = base::command_tag(query);
+        let mut outcome = base::ExecutionOutcome::command(tag.clone(), Some(affected as u64));
+        if tag == "INSERT" || tag == "REPLACE" {
+            outcome.last_insert_id = Some(conn.last_insert_rowid() as u64);
+        }
+        return Ok(base::QueryResult {
+            outcome,
+            ..base::QueryResult::empty()
+        });
+    }
+    let columns: Vec<String> = stmt.column_names().iter().map(|s| s.to_string()).collect();
+    let declared_types: Vec<Option<String>> = stmt
+        .columns()
+        .iter()
+        .map(|c| c.decl_type().map(|t| t.to_string()))
+        .collect();
+    let mut raw_rows = stmt.raw_query();
+
+    let mut results = vec![];
+    let mut skipped = 0;
+    while let Some(row) = raw_rows.next()? {
+        if skipped < offset {
+            skipped += 1;
+            continue;
+        }
+        if limit.is_some_and(|limit| results.len() >= limit) {
+            break;
+        }
+        let data: Vec<base::Value> = declared_types
+            .iter()
+            .enumerate()
+            .map(|(idx, declared_type)| get_row_value(row, idx, declared_type.as_deref()))
+            .collect();
+        results.push(base::QueryResultRow { data });
+    }
+
+    Ok(base::QueryResult {
+        columns,
+        rows: results,
+        next_page: None,
+        outcome: base::ExecutionOutcome::rows(),
+    })
+}
 
-fn get_row_value(row: &rusqlite::Row, column: &str) -> Option<String> {
-    let sval: Result<String, _> = row.get(column);
-    if sval.is_ok() {
-        return sval.ok();
+/// Nests the rows of `EXPLAIN QUERY PLAN` (id, parent, detail) below the node with their parent id.
+fn to_plan_node(detail: String, id: i64, rows: &[(i64, i64, String)]) -> base::PlanNode {
+    base::PlanNode {
+        node_type: detail,
+        children: rows
+            .iter()
+            .filter(|(child, parent, _)| *parent == id && *child != id)
+            .map(|(child, _, detail)| to_plan_node(detail.clone(), *child, rows))
+            .collect(),
+        ..base::PlanNode::default()
     }
-    let ival: Result<i32, _> = row.get(column);
-    if ival.is_ok() {
-        return ival.ok().map(|v| v.to_string());
+}
+
+/// When a trigger fires, e.g. `AFTER UPDATE OF name`, read from its CREATE statement.
+fn trigger_event(sql: &str) -> String {
+    let words: Vec<&str> = sql.split_whitespace().collect();
+    let start = words.iter().position(|word| {
+        ["BEFORE", "AFTER", "INSTEAD", "DELETE", "INSERT", "UPDATE"]
+            .contains(&word.to_uppercase().as_str())
+    });
+    let event: Vec<&str> = match start {
+        Some(start) => words[start..]
+            .iter()
+            .take_while(|word| !word.eq_ignore_ascii_case("ON"))
+            .cloned()
+            .collect(),
+        None => vec![],
+    };
+    let event = event.join(" ").to_uppercase();
+    // without a timing sqlite fires before the change
+    if ["DELETE", "INSERT", "UPDATE"]
+        .iter()
+        .any(|word| event.starts_with(word))
+    {
+        return format!("BEFORE {}", event);
     }
-    None
+    event
 }
 
 pub struct SQLiteConnectionType {
-    name: String,
     path: String,
     selections: HashMap<types::WindowTypeID, Vec<String>>,
-    query: Option<String>,
+    page_size: usize,
+    session: Arc<Session<rusqlite::Connection>>,
 }
 
 impl base::ConnectionType for SQLiteConnectionType {
     fn list_tables(&self) -> Result<Vec<base::Table>> {
-        let tables_query = "SELECT name FROM sqlite_master WHERE type='table' ORDER BY name;";
-        let conn = self.get_connection()?;
-        let mut stmt = conn.prepare(tables_query)?;
-        let mut rows = stmt.query([])?;
-
-        let mut results = vec![];
-        while let Some(row) = rows.next()? {
-            results.push(base::Table {
-                id: row.get(0)?,
-                name: row.get(0)?,
-            });
+        let tables_query =
+            "SELECT name, type FROM sqlite_master WHERE type IN ('table', 'view') ORDER BY name;";
+        self.with_connection(|conn| {
+            // the first number of a sqlite_stat1 entry is the row count, as of the last ANALYZE
+            let estimates: HashMap<String, u64> = conn
+                .prepare("SELECT tbl, MAX(CAST(stat AS INTEGER)) FROM sqlite_stat1 GROUP BY tbl")
+                .and_then(|mut stmt| {
+                    stmt.query_map([], |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as u64)))?
+                        .collect()
+                })
+                .unwrap_or_default();
+            // dbstat is only there when sqlite was built with it
+            let sizes: HashMap<String, u64> = conn
+                .prepare(
+                    "SELECT m.tbl_name, SUM(d.pgsize) FROM dbstat d
+JOIN sqlite_master m ON m.name = d.name GROUP BY m.tbl_name",
+                )
+                .and_then(|mut stmt| {
+                    stmt.query_map([], |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as u64)))?
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            let mut stmt = conn.prepare(tables_query)?;
+            let mut rows = stmt.query([])?;
+
+            let mut results = vec![];
+            while let Some(row) = rows.next()? {
+                let name: String = row.get(0)?;
+                results.push(base::Table {
+                    rows: estimates.get(&name).cloned(),
+                    size: sizes.get(&name).cloned(),
+                    ..base::Table::named(name, &row.get::<_, String>(1)?)
+                });
+            }
+
+            Ok(results)
+        })
+    }
+
+    fn split_script(&self, script: &str) -> Vec<String> {
+        splitter::split_statements(script, splitter::Dialect::SQLite)
+    }
+
+    fn placeholders(&self, statement: &str) -> Vec<String> {
+        splitter::find_placeholders(statement, splitter::Dialect::SQLite)
+    }
+
+    fn sql_literal(&self, value: &base::Value) -> String {
+        match value {
+            // sqlite reads numbers too large for a double as infinity and stores NaN as NULL
+            base::Value::Float(v) if v.is_nan() => "NULL".to_string(),
+            base::Value::Float(v) if v.is_infinite() => {
+                if *v > 0.0 { "9e999" } else { "-9e999" }.to_string()
+            }
+            value => value.to_sql_literal(),
         }
+    }
+
+    fn supports_transactions(&self) -> bool {
+        true
+    }
+
+    fn supports_order_by(&self) -> bool {
+        true
+    }
+
+    fn supports_where(&self) -> bool {
+        true
+    }
+
+    fn begin_transaction(&self) -> Result<()> {
+        self.with_connection(|conn| Ok(conn.execute_batch("BEGIN")?))
+    }
 
-        Ok(results)
+    fn commit_transaction(&self) -> Result<()> {
+        self.with_connection(|conn| Ok(conn.execute_batch("COMMIT")?))
+    }
+
+    fn rollback_transaction(&self) -> Result<()> {
+        self.with_connection(|conn| Ok(conn.execute_batch("ROLLBACK")?))
+    }
+
+    fn explain(
+        &self,
+        statement: &str,
+        params: &base::QueryParams,
+        analyze: bool,
+    ) -> Result<base::PlanNode> {
+        if analyze {
+            return Err(anyhow::anyhow!(
+                "SQLite can not analyze the plan, turn ANALYZE off"
+            ));
+        }
+        let explain = format!("EXPLAIN QUERY PLAN {}", statement);
+        let rows = self.with_connection(|conn| {
+            let mut stmt = conn.prepare(&explain)?;
+            bind_params(&mut stmt, params)?;
+            let mut raw_rows = stmt.raw_query();
+            let mut rows = vec![];
+            while let Some(row) = raw_rows.next()? {
+                rows.push((row.get(0)?, row.get(1)?, row.get(3)?));
+            }
+            Ok(rows)
+        })?;
+        Ok(to_plan_node("QUERY PLAN".to_string(), 0, &rows))
     }
 
     fn default_query_string(&self) -> String {
         let mut columns = vec!["*".to_string()];
-        match self.selections.get(&types::WindowTypeID::COLUMNS) {
-            Some(value) => {
-                if !value.is_empty() {
-                    columns = value.clone();
-                }
+        if let Some(value) = self.selections.get(&types::WindowTypeID::COLUMNS) {
+            if !value.is_empty() {
+                columns = value.clone();
             }
-            None => {}
         }
         let query = format!(
             "SELECT {} FROM {}",
@@ -61,48 +291,32 @@ impl base::ConnectionType for SQLiteConnectionType {
             self.get_selection(types::WindowTypeID::TABLES)
                 .unwrap_or("_unselected_".to_string())
         );
-        format!("{} LIMIT {}", query, 50)
+        query
     }
 
-    fn query(&self) -> Result<base::QueryResult> {
-        let mut query = self.default_query_string();
-        if let Some(custom_query) = self.query.clone() {
-            query = custom_query;
-        }
-        debug!("List tables query: {:?}", query);
-
-        let conn = self.get_connection()?;
-        let mut stmt = conn.prepare(&query)?;
-        let columns: Vec<String> = stmt.column_names().iter().map(|s| s.to_string()).collect();
-        let mut raw_rows = stmt.query([])?;
-
-        if columns.is_empty() {
-            return Ok(base::QueryResult {
-                columns: vec![],
-                rows: vec![],
-            });
-        }
+    fn execute(&self, query: &str, params: &base::QueryParams) -> Result<base::QueryResult> {
+        debug!("Execute query: {:?}", query);
 
-        let mut results = vec![];
-        let id_colm = columns.first().unwrap();
-        while let Some(row) = raw_rows.next()? {
-            results.push(base::QueryResultRow {
-                id: get_row_value(row, id_colm.as_str()).unwrap_or("missing".to_string()),
-                data: columns.iter().map(|c| get_row_value(row, c)).collect(),
-            });
-        }
+        let started = Instant::now();
+        let mut result = if base::pageable_query(query, splitter::Dialect::SQLite).is_some() {
+            self.query_page(query, params, 0)?
+        } else {
+            self.with_connection(|conn| query_rows(conn, query, params, 0, None))?
+        };
+        result.outcome.elapsed = started.elapsed();
+        Ok(result)
+    }
 
-        if results.is_empty() {
-            return Ok(base::QueryResult {
-                columns: vec![],
-                rows: vec![],
-            });
+    fn fetch_page(
+        &self,
+        statement: &str,
+        params: &base::QueryParams,
+        cursor: &base::PageCursor,
+    ) -> Result<base::QueryResult> {
+        match cursor {
+            base::PageCursor::Offset(offset) => self.query_page(statement, params, *offset),
+            _ => Err(anyhow::anyhow!("Unsupported page cursor {:?}", cursor)),
         }
-
-        Ok(base::QueryResult {
-            columns,
-            rows: results,
-        })
     }
 
     fn list_schemas(&self) -> Result<Vec<base::Schema>> {
@@ -113,54 +327,273 @@ impl base::ConnectionType for SQLiteConnectionType {
         Ok(vec![])
     }
 
-    fn list_columns(&self) -> Result<Vec<String>> {
-        let columns_query = format!(
-            "PRAGMA table_info({})",
-            self.get_selection(types::WindowTypeID::TABLES)
-                .unwrap_or("_unselected_".to_string())
-        );
-        let conn = self.get_connection()?;
-        let mut stmt = conn.prepare(&columns_query)?;
-        let columns: Vec<String> = stmt.column_names().iter().map(|s| s.to_string()).collect();
-        let mut rows = stmt.query([])?;
+    fn list_columns(&self) -> Result<Vec<base::ColumnInfo>> {
+        let table = self
+            .get_selection(types::WindowTypeID::TABLES)
+            .unwrap_or("_unselected_".to_string());
+        self.with_connection(|conn| {
+            let mut references: HashMap<String, Vec<String>> = HashMap::new();
+            let mut stmt =
+                conn.prepare("SELECT \"from\", \"table\", \"to\" FROM pragma_foreign_key_list(?)")?;
+            let mut rows = stmt.query([&table])?;
+            while let Some(row) = rows.next()? {
+                let parent: String = row.get(1)?;
+                // without a target column the key references the primary key of the parent
+                let target = match row.get::<_, Option<String>>(2)? {
+                    Some(column) => format!("{}({})", parent, column),
+                    None => parent,
+                };
+                references.entry(row.get(0)?).or_default().push(target);
+            }
 
-        info!("Columns: {:?}", columns);
+            // table_xinfo also lists generated columns, hidden ones are left out
+            let mut stmt = conn.prepare(
+                "SELECT name, type, \"notnull\", dflt_value, pk FROM pragma_table_xinfo(?) WHERE hidden != 1",
+            )?;
+            let mut rows = stmt.query([&table])?;
 
-        let mut results = vec![];
-        while let Some(row) = rows.next()? {
-            let column_name: String = row.get("name")?;
-            results.push(column_name);
-        }
+            let mut results = vec![];
+            while let Some(row) = rows.next()? {
+                let column_name: String = row.get("name")?;
+                results.push(base::ColumnInfo {
+                    data_type: row.get("type")?,
+                    nullable: !row.get::<_, bool>("notnull")?,
+                    default: row.get("dflt_value")?,
+                    primary_key: row.get::<_, i64>("pk")? > 0,
+                    foreign_key: references.get(&column_name).map(|r| r.join(", ")),
+                    comment: None,
+                    name: column_name,
+                });
+            }
 
-        Ok(results)
+            info!("Columns: {:?}", results.len());
+            Ok(results)
+        })
     }
-    fn list_indexes(&self) -> Result<Vec<String>> {
-        Ok(vec![])
+    fn list_indexes(&self) -> Result<Vec<base::IndexInfo>> {
+        let table = self
+            .get_selection(types::WindowTypeID::TABLES)
+            .ok_or(anyhow::anyhow!("Select a table to list its indexes"))?;
+        self.with_connection(|conn| {
+            let mut stmt =
+                conn.prepare("SELECT name, \"unique\", origin FROM pragma_index_list(?)")?;
+            let raw_indexes = stmt
+                .query_map([&table], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, bool>(1)?,
+                        row.get::<_, String>(2)?,
+                    ))
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+
+            let mut results = vec![];
+            for (name, unique, origin) in raw_indexes {
+                let columns = conn
+                    .prepare("SELECT name FROM pragma_index_info(?) ORDER BY seqno")?
+                    .query_map([&name], |row| row.get::<_, Option<String>>(0))?
+                    .map(|column| Ok(column?.unwrap_or("<expression>".to_string())))
+                    .collect::<Result<Vec<_>>>()?;
+                // indexes made for a constraint have no sql of their own
+                let definition = conn
+                    .query_row(
+                        "SELECT sql FROM sqlite_master WHERE type = 'index' AND name = ?",
+                        [&name],
+                        |row| row.get::<_, Option<String>>(0),
+                    )
+                    .unwrap_or_default()
+                    .unwrap_or_else(|| match origin.as_str() {
+                        "pk" => format!("PRIMARY KEY ({})", columns.join(", ")),
+                        _ => format!("UNIQUE ({})", columns.join(", ")),
+                    });
+                // dbstat is only there when sqlite was built with it
+                let size = conn
+                    .query_row(
+                        "SELECT SUM(pgsize) FROM dbstat WHERE name = ?",
+                        [&name],
+                        |row| row.get::<_, Option<i64>>(0),
+                    )
+                    .ok()
+                    .flatten()
+                    .map(|size| size as u64);
+                results.push(base::IndexInfo {
+                    name,
+                    columns,
+                    unique,
+                    method: "btree".to_string(),
+                    size,
+                    definition,
+                });
+            }
+            Ok(results)
+        })
+    }
+    fn list_primary_key(&self, table: &str) -> Result<Vec<String>> {
+        self.with_connection(|conn| {
+            let mut stmt =
+                conn.prepare("SELECT name FROM pragma_table_info(?) WHERE pk > 0 ORDER BY pk")?;
+            let columns = stmt
+                .query_map([table], |row| row.get(0))?
+                .collect::<rusqlite::Result<Vec<String>>>()?;
+            Ok(columns)
+        })
+    }
+
+    fn list_foreign_keys(&self, table: &str) -> Result<Vec<base::ForeignKey>> {
+        // the key references the primary key when it names no columns, and sqlite
+        // keeps the referenced table the way it is spelled in the key
+        let query = "
+SELECT m.name || '_fk' || f.id,
+       m.name,
+       f.\"from\",
+       coalesce((SELECT name FROM sqlite_master WHERE name = f.\"table\" COLLATE NOCASE),
+                f.\"table\"),
+       coalesce(f.\"to\", (SELECT p.name FROM pragma_table_info(f.\"table\") p
+                           WHERE p.pk = f.seq + 1))
+FROM sqlite_master m, pragma_foreign_key_list(m.name) f
+WHERE m.type = 'table' AND (m.name = ?1 OR f.\"table\" = ?1 COLLATE NOCASE)
+ORDER BY m.name, f.id, f.seq";
+        let pairs = self.with_connection(|conn| {
+            let mut stmt = conn.prepare(query)?;
+            let pairs = stmt
+                .query_map([table], |row| {
+                    Ok((
+                        row.get(0)?,
+                        row.get(1)?,
+                        row.get(2)?,
+                        row.get(3)?,
+                        row.get(4)?,
+                    ))
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(pairs)
+        })?;
+        Ok(base::ForeignKey::from_column_pairs(pairs))
+    }
+
+    fn list_views(&self) -> Result<Vec<base::SchemaObject>> {
+        self.list_objects("view")
+    }
+
+    fn list_triggers(&self) -> Result<Vec<base::SchemaObject>> {
+        self.list_objects("trigger")
+    }
+
+    fn get_definition(&self, kind: base::ObjectKind, id: &str) -> Result<String> {
+        let object_type = match kind {
+            base::ObjectKind::View => "view",
+            base::ObjectKind::Trigger => "trigger",
+            base::ObjectKind::Table => return self.table_definition(id),
+            base::ObjectKind::Index => "index",
+            _ => return Err(anyhow::anyhow!("SQLite has no {:?} objects", kind)),
+        };
+        let sql: Option<String> = self.with_connection(|conn| {
+            Ok(conn.query_row(
+                "SELECT sql FROM sqlite_master WHERE type = ? AND name = ?",
+                [object_type, id],
+                |row| row.get(0),
+            )?)
+        })?;
+        match sql {
+            Some(sql) => Ok(format!("{};", sql)),
+            // indexes made for a constraint are part of the table
+            None => match self.get_selection(types::WindowTypeID::TABLES) {
+                Some(table) => self.table_definition(&table),
+                None => Err(anyhow::anyhow!("The definition of {} is not available", id)),
+            },
+        }
     }
 }
 
 impl SQLiteConnectionType {
+    /// The CREATE statement of a table or view followed by those of its indexes.
+    fn table_definition(&self, name: &str) -> Result<String> {
+        let statements = self.with_connection(|conn| {
+            let mut stmt = conn.prepare(
+                "
+SELECT sql
+FROM sqlite_master
+WHERE tbl_name = ? AND type IN ('table', 'view', 'index') AND sql IS NOT NULL
+ORDER BY type = 'index', name",
+            )?;
+            let statements = stmt
+                .query_map([name], |row| row.get::<_, String>(0))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(statements)
+        })?;
+        if statements.is_empty() {
+            return Err(anyhow::anyhow!("The table {} no longer exists", name));
+        }
+        let mut statements = statements.into_iter().map(|sql| format!("{};", sql));
+        let mut definition = statements.next().unwrap_or_default();
+        let indexes: Vec<String> = statements.collect();
+        if !indexes.is_empty() {
+            definition.push_str(&format!("\n\n{}", indexes.join("\n")));
+        }
+        Ok(definition)
+    }
+
     pub fn new(
         config: Connection,
         selections: HashMap<types::WindowTypeID, Vec<String>>,
-        query: Option<String>,
+        page_size: usize,
+        session: Arc<Session<rusqlite::Connection>>,
     ) -> Result<Self> {
         let dsn = Dsn::from_str(&config.dsn)?;
         let addr = dsn.addresses.first().unwrap();
-        let conn_string = &format!(
-            "host={} port={} dbname={} user={} password={} ",
-            addr.clone().host.unwrap_or("localhost".to_string()),
-            addr.port.unwrap_or(5432),
-            dsn.subject.unwrap_or("postgres".to_string()),
-            dsn.username.unwrap_or("postgres".to_string()),
-            dsn.password.unwrap_or("".to_string())
-        );
 
         Ok(SQLiteConnectionType {
-            name: config.name,
             path: addr.clone().host.unwrap(),
             selections,
-            query,
+            page_size,
+            session,
+        })
+    }
+
+    fn query_page(
+        &self,
+        query: &str,
+        params: &base::QueryParams,
+        offset: usize,
+    ) -> Result<base::QueryResult> {
+        // sqlite steps through skipped rows for OFFSET as well, so stepping the statement
+        // ourselves costs the same and keeps the column names of the query intact.
+        // One extra row tells whether there is another page.
+        let mut result = self.with_connection(|conn| {
+            query_rows(conn, query, params, offset, Some(self.page_size + 1))
+        })?;
+        if result.rows.len() > self.page_size {
+            result.rows.truncate(self.page_size);
+            result.next_page = Some(base::PageCursor::Offset(offset + self.page_size));
+        }
+        Ok(result)
+    }
+
+    /// Lists the objects of a type in sqlite_master, triggers with their event and table.
+    fn list_objects(&self, object_type: &str) -> Result<Vec<base::SchemaObject>> {
+        self.with_connection(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT name, tbl_name, sql FROM sqlite_master WHERE type = ? ORDER BY tbl_name, name",
+            )?;
+            let mut rows = stmt.query([object_type])?;
+
+            let mut results = vec![];
+            while let Some(row) = rows.next()? {
+                let name: String = row.get(0)?;
+                let sql: String = row.get::<_, Option<String>>(2)?.unwrap_or_default();
+                let (kind, detail) = if object_type == "trigger" {
+                    (trigger_event(&sql), row.get(1)?)
+                } else {
+                    (object_type.to_string(), String::new())
+                };
+                results.push(base::SchemaObject {
+                    id: name.clone(),
+                    name,
+                    kind,
+                    detail,
+                });
+            }
+            Ok(results)
         })
     }
 
@@ -175,8 +608,30 @@ impl SQLiteConnectionType {
         Some(value[0].clone())
     }
 
-    fn get_connection(&self) -> Result<rusqlite::Connection> {
+    fn with_connection<T>(
+        &self,
+        mut op: impl FnMut(&mut rusqlite::Connection) -> Result<T>,
+    ) -> Result<T> {
+        self.session.run(
+            || self.open_connection(),
+            |_| true,
+            |conn| {
+                let result = op(conn);
+                // sqlite knows whether a transaction is open, however it was started
+                self.session.set_in_transaction(!conn.is_autocommit());
+                result
+            },
+        )
+    }
+
+    fn open_connection(&self) -> Result<rusqlite::Connection> {
         info!("Opening connection to {}", self.path.clone());
-        return Ok(rusqlite::Connection::open(self.path.clone())?);
+        let conn = rusqlite::Connection::open(self.path.clone())?;
+        let interrupt = conn.get_interrupt_handle();
+        self.session.set_canceller(Arc::new(move || {
+            interrupt.interrupt();
+            Ok(())
+        }));
+        Ok(conn)
     }
 }
@@ -3,7 +3,7 @@ use super::mysql;
 use super::pg;
 use super::redis;
 use super::sqlite;
-use crate::data::Connection;
+use crate::data::{Connection, Settings};
 use crate::ui::types;
 use crate::ui::types::WindowTypeID;
 use anyhow::anyhow;
@@ -12,6 +12,7 @@ use mdsn::Dsn;
 use std::{collections::HashMap, str::FromStr};
 
 use super::base::ConnectionType;
+use super::session::{SessionKey, SessionManager};
 
 const SUPPORTED_DRIVERS: [&str; 6] = [
     "postgres",
@@ -43,29 +44,45 @@ pub fn validate_dsn(raw_dsn: String) -> (bool, String) {
 pub fn get_connection_type(
     conn: Connection,
     selections: HashMap<types::WindowTypeID, Vec<String>>,
-    custom_queries: HashMap<String, String>,
+    sessions: &SessionManager,
+    settings: &Settings,
 ) -> Result<Box<dyn ConnectionType>> {
     let dsn = Dsn::from_str(&conn.dsn)?;
-    let query = custom_queries.get(&conn.id).map(|s| s.to_string());
+    let key = SessionKey::new(&conn, &selections);
     if dsn.driver == "postgres" || dsn.driver == "postgresql" {
         return Ok(Box::new(pg::PostgreSQLDatabase::new(
-            conn, selections, query,
+            conn,
+            selections,
+            settings.page_size,
+            sessions.get(key),
         )?));
     } else if dsn.driver == "redis" {
         return Ok(Box::new(redis::RedisConnectionType::new(
-            conn, selections, query,
+            conn,
+            selections,
+            settings.page_size,
+            sessions.get(key),
         )?));
     } else if dsn.driver == "sqlite" {
         return Ok(Box::new(sqlite::SQLiteConnectionType::new(
-            conn, selections, query,
+            conn,
+            selections,
+            settings.page_size,
+            sessions.get(key),
         )?));
     } else if dsn.driver == "mysql" {
         return Ok(Box::new(mysql::MySQLDatabase::new(
-            conn, selections, query,
+            conn,
+            selections,
+            settings.page_size,
+            sessions.get(key),
         )?));
     } else if dsn.driver == "elasticsearch" {
         return Ok(Box::new(elasticsearch::ElasticSearchDatabase::new(
-            conn, selections, query,
+            conn,
+            selections,
+            settings.page_size,
+            sessions.get(key),
         )?));
     }
     Err(anyhow!("Unsupported DSN type"))
@@ -73,8 +90,11 @@ pub fn get_connection_type(
 
 pub fn feature_supported(conn: Connection, window_type: WindowTypeID) -> Result<bool> {
     let dsn = Dsn::from_str(&conn.dsn)?;
-    if dsn.driver == "postgres" || dsn.driver == "postgresql" || dsn.driver == "mysql" {
+    if dsn.driver == "postgres" || dsn.driver == "postgresql" {
         return Ok(true); // all features supported
+    } else if dsn.driver == "mysql" {
+        // mysql has neither sequences nor enum types of their own
+        return Ok(![WindowTypeID::SEQUENCES, WindowTypeID::ENUMS].contains(&window_type));
     } else if dsn.driver == "redis" {
         return Ok([
             WindowTypeID::DATABASES,
@@ -87,7 +107,12 @@ pub fn feature_supported(conn: Connection, window_type: WindowTypeID) -> Result<
             WindowTypeID::CONNECTIONS,
             WindowTypeID::TABLES,
             WindowTypeID::COLUMNS,
+            WindowTypeID::INDEXES,
             WindowTypeID::QUERY,
+            WindowTypeID::PLAN,
+            WindowTypeID::VIEWS,
+            WindowTypeID::TRIGGERS,
+            WindowTypeID::DEFINITION,
         ]
         .contains(&window_type));
     } else if dsn.driver == "elasticsearch" {
@@ -95,6 +120,7 @@ pub fn feature_supported(conn: Connection, window_type: WindowTypeID) -> Result<
             WindowTypeID::CONNECTIONS,
             WindowTypeID::TABLES,
             WindowTypeID::QUERY,
+            WindowTypeID::DEFINITION,
         ]
         .contains(&window_type));
     }
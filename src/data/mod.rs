@@ -1,12 +1,11 @@
 use mdsn::Dsn;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::str::FromStr;
 
 use std::io::prelude::*;
 use std::path::PathBuf;
 
-use dirs;
-
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Connection {
     pub id: String,
@@ -14,11 +13,75 @@ pub struct Connection {
     pub dsn: String,
     #[serde(default)]
     pub query_history: Vec<String>,
+    // the parameter values last used with a query of the history, by placeholder
+    #[serde(default)]
+    pub query_params: HashMap<String, HashMap<String, String>>,
+}
+
+fn default_page_size() -> usize {
+    50
+}
+
+fn default_stop_on_error() -> bool {
+    true
+}
+
+fn default_max_column_width() -> usize {
+    40
+}
+
+fn default_sort_on_server() -> bool {
+    true
+}
+
+/// How whole rows are put on the clipboard.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CopyFormat {
+    #[default]
+    Tsv,
+    Json,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Settings {
+    #[serde(default = "default_page_size")]
+    pub page_size: usize,
+    // stop running a script at the first statement that fails
+    #[serde(default = "default_stop_on_error")]
+    pub stop_on_error: bool,
+    // the text copied is also piped to this command, e.g. `xclip -selection clipboard`,
+    // for terminals that ignore OSC 52
+    #[serde(default)]
+    pub clipboard_command: Option<String>,
+    #[serde(default)]
+    pub copy_format: CopyFormat,
+    // longer values are cut off in the grid, resizing a column goes past it
+    #[serde(default = "default_max_column_width")]
+    pub max_column_width: usize,
+    // sorting a browsed table runs it again with ORDER BY, otherwise the loaded rows are sorted
+    #[serde(default = "default_sort_on_server")]
+    pub sort_on_server: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            page_size: default_page_size(),
+            stop_on_error: default_stop_on_error(),
+            clipboard_command: None,
+            copy_format: CopyFormat::default(),
+            max_column_width: default_max_column_width(),
+            sort_on_server: default_sort_on_server(),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct PersistentData {
     pub connections: Vec<Connection>,
+    #[serde(default)]
+    pub settings: Settings,
 }
 
 fn get_settings_directory() -> PathBuf {
@@ -37,6 +100,7 @@ impl Connection {
             name,
             dsn,
             query_history: vec![],
+            query_params: HashMap::new(),
         }
     }
     pub fn new_with_id(name: String, dsn: String, id: String) -> Self {
@@ -45,6 +109,7 @@ impl Connection {
             name,
             dsn,
             query_history: vec![],
+            query_params: HashMap::new(),
         }
     }
 
@@ -59,11 +124,11 @@ impl Connection {
             return String::from("unknown");
         }
         let addr = addr.unwrap();
-        return format!(
+        format!(
             "{}:{}",
             addr.clone().host.unwrap_or("unset".to_string()),
             addr.port.unwrap_or(0)
-        );
+        )
     }
 
     pub fn get_type(&self) -> String {
@@ -72,7 +137,7 @@ impl Connection {
             return String::from("unknown");
         }
         let dsn = dsn.unwrap();
-        return dsn.driver;
+        dsn.driver
     }
 }
 
@@ -84,11 +149,12 @@ impl PersistentData {
             let mut file = std::fs::File::open(settings_path).unwrap();
             let mut contents = String::new();
             file.read_to_string(&mut contents).unwrap();
-            return serde_json::from_str(&contents).unwrap();
+            serde_json::from_str(&contents).unwrap()
         } else {
-            return PersistentData {
+            PersistentData {
                 connections: vec![],
-            };
+                settings: Settings::default(),
+            }
         }
     }
 
@@ -120,9 +186,24 @@ impl PersistentData {
 
         // max 100 queries
         if connection.query_history.len() > 100 {
-            connection.query_history.remove(0);
+            let removed = connection.query_history.remove(0);
+            connection.query_params.remove(&removed);
         }
 
         self.save();
     }
+
+    pub fn set_query_params(
+        &mut self,
+        connection_id: String,
+        query: String,
+        params: HashMap<String, String>,
+    ) {
+        let connection = self.connections.iter_mut().find(|c| c.id == connection_id);
+        if connection.is_none() {
+            return;
+        }
+        connection.unwrap().query_params.insert(query, params);
+        self.save();
+    }
 }
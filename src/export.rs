@@ -0,0 +1,530 @@
+use crate::connectiontypes::base::{ConnectionType, QueryParams, Value};
+use anyhow::Result;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+/// When a CSV field is put in quotes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CsvQuoting {
+    // only fields that need it: holding the delimiter, a quote or a line break
+    Minimal,
+    All,
+    NonNumeric,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExportFormat {
+    Csv {
+        delimiter: char,
+        quoting: CsvQuoting,
+        header: bool,
+    },
+    Json,
+    Ndjson,
+    Markdown,
+    Sql,
+}
+
+/// What `:export <format> <path> [option=value ...]` asked for.
+#[derive(Clone, Debug)]
+pub struct Export {
+    pub format: ExportFormat,
+    pub path: PathBuf,
+    // write the rows on screen only instead of running the query again for all of them
+    pub loaded_only: bool,
+    // the table the INSERTs of the sql format write to
+    pub table: Option<String>,
+}
+
+pub fn parse_delimiter(value: &str) -> Result<char> {
+    Ok(match value {
+        "tab" | "\\t" => '\t',
+        "comma" => ',',
+        "semicolon" => ';',
+        "pipe" => '|',
+        "space" => ' ',
+        _ => {
+            let mut chars = value.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if c != '"' && c != '\n' && c != '\r' => c,
+                _ => return Err(anyhow::anyhow!("`{}` can't delimit CSV fields", value)),
+            }
+        }
+    })
+}
+
+pub fn parse_bool_option(name: &str, value: &str) -> Result<bool> {
+    match value {
+        "yes" | "true" | "on" => Ok(true),
+        "no" | "false" | "off" => Ok(false),
+        _ => Err(anyhow::anyhow!(
+            "{} is either yes or no, not `{}`",
+            name,
+            value
+        )),
+    }
+}
+
+/// Expands a leading `~` to the home directory.
+pub fn expand_path(path: &str) -> PathBuf {
+    match (path.strip_prefix('~'), dirs::home_dir()) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+            home.join(rest.trim_start_matches('/'))
+        }
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Parses the arguments of the export command, e.g. `csv ~/out.csv delimiter=; quote=all`.
+pub fn parse_export(args: &str) -> Result<Export> {
+    let usage = "Usage: :export <csv|json|ndjson|markdown|sql> <path> [option=value ...]";
+    let mut parts = args.split_whitespace();
+    let (format, path) = match (parts.next(), parts.next()) {
+        (Some(format), Some(path)) => (format.to_lowercase(), path),
+        _ => return Err(anyhow::anyhow!(usage)),
+    };
+    let mut format = match format.as_str() {
+        "csv" => ExportFormat::Csv {
+            delimiter: ',',
+            quoting: CsvQuoting::Minimal,
+            header: true,
+        },
+        "tsv" => ExportFormat::Csv {
+            delimiter: '\t',
+            quoting: CsvQuoting::Minimal,
+            header: true,
+        },
+        "json" => ExportFormat::Json,
+        "ndjson" | "jsonl" => ExportFormat::Ndjson,
+        "markdown" | "md" => ExportFormat::Markdown,
+        "sql" => ExportFormat::Sql,
+        _ => return Err(anyhow::anyhow!("Unknown format `{}`. {}", format, usage)),
+    };
+    let mut export = Export {
+        format: ExportFormat::Json,
+        path: expand_path(path),
+        loaded_only: false,
+        table: None,
+    };
+    for option in parts {
+        let (name, value) = option.split_once('=').ok_or(anyhow::anyhow!(
+            "Options are written as name=value, not `{}`",
+            option
+        ))?;
+        match (name, &mut format) {
+            ("rows", _) => {
+                export.loaded_only = match value {
+                    "loaded" => true,
+                    "all" => false,
+                    _ => return Err(anyhow::anyhow!("rows is either loaded or all")),
+                }
+            }
+            ("delimiter", ExportFormat::Csv { delimiter, .. }) => {
+                *delimiter = parse_delimiter(value)?
+            }
+            ("quote", ExportFormat::Csv { quoting, .. }) => {
+                *quoting = match value {
+                    "minimal" => CsvQuoting::Minimal,
+                    "all" => CsvQuoting::All,
+                    "nonnumeric" => CsvQuoting::NonNumeric,
+                    _ => {
+                        return Err(anyhow::anyhow!(
+                            "quote is one of minimal, all or nonnumeric"
+                        ))
+                    }
+                }
+            }
+            ("header", ExportFormat::Csv { header, .. }) => {
+                *header = parse_bool_option(name, value)?
+            }
+            ("table", ExportFormat::Sql) => export.table = Some(value.to_string()),
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "The {} format has no {} option",
+                    args.split_whitespace().next().unwrap_or_default(),
+                    name
+                ))
+            }
+        }
+    }
+    export.format = format;
+    Ok(export)
+}
+
+/// The row as a JSON object, written by hand to keep the columns in order.
+pub fn json_object(columns: &[String], row: &[Value]) -> Result<String> {
+    let fields = columns
+        .iter()
+        .zip(row.iter())
+        .map(|(column, value)| {
+            Ok(format!(
+                "{}: {}",
+                serde_json::to_string(column)?,
+                serde_json::to_string(&value.to_json())?
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(format!("{{{}}}", fields.join(", ")))
+}
+
+fn is_numeric(value: &Value) -> bool {
+    matches!(value, Value::Int(_) | Value::Float(_) | Value::Decimal(_))
+}
+
+/// Writes rows to a file one at a time in one of the export formats.
+pub struct Exporter<'a> {
+    format: ExportFormat,
+    out: BufWriter<File>,
+    columns: Vec<String>,
+    // the start of the INSERTs of the sql format, up to the values
+    insert: String,
+    // writes the names and values of the INSERTs the way the database reads them
+    db: &'a dyn ConnectionType,
+    rows: u64,
+}
+
+impl<'a> Exporter<'a> {
+    /// Creates the file and writes what comes before the rows.
+    pub fn create(
+        export: &Export,
+        columns: Vec<String>,
+        db: &'a dyn ConnectionType,
+    ) -> Result<Self> {
+        let insert = match (&export.format, &export.table) {
+            (ExportFormat::Sql, Some(table)) => format!(
+                "INSERT INTO {} ({}) VALUES ",
+                db.quote_identifier(table),
+                columns
+                    .iter()
+                    .map(|column| db.quote_identifier(column))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            (ExportFormat::Sql, None) => {
+                return Err(anyhow::anyhow!(
+                    "Name the table to write INSERTs for with table=<name>"
+                ))
+            }
+            _ => String::new(),
+        };
+        let file = File::create(&export.path)
+            .map_err(|err| anyhow::anyhow!("{}: {}", export.path.display(), err))?;
+        let mut exporter = Exporter {
+            format: export.format.clone(),
+            out: BufWriter::new(file),
+            columns,
+            insert,
+            db,
+            rows: 0,
+        };
+        exporter.write_header()?;
+        Ok(exporter)
+    }
+
+    fn write_header(&mut self) -> Result<()> {
+        match self.format {
+            ExportFormat::Csv {
+                delimiter, header, ..
+            } if header => {
+                let fields = self
+                    .columns
+                    .iter()
+                    .map(|column| self.csv_field(&Value::Text(column.clone())))
+                    .collect::<Vec<_>>();
+                writeln!(self.out, "{}", fields.join(&delimiter.to_string()))?;
+            }
+            ExportFormat::Json => write!(self.out, "[")?,
+            ExportFormat::Markdown => {
+                let names = self
+                    .columns
+                    .iter()
+                    .map(|column| markdown_cell(column))
+                    .collect::<Vec<_>>();
+                writeln!(self.out, "| {} |", names.join(" | "))?;
+                writeln!(self.out, "|{}", " --- |".repeat(self.columns.len()))?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn csv_field(&self, value: &Value) -> String {
+        let (delimiter, quoting) = match self.format {
+            ExportFormat::Csv {
+                delimiter, quoting, ..
+            } => (delimiter, quoting),
+            _ => return value.to_string(),
+        };
+        // NULL is an empty field, told apart from an empty string by the quotes
+        if value.is_null() {
+            return String::new();
+        }
+        let text = value.to_string();
+        let quote = match quoting {
+            CsvQuoting::All => true,
+            CsvQuoting::NonNumeric => !is_numeric(value),
+            CsvQuoting::Minimal => {
+                text.is_empty()
+                    || text.contains(delimiter)
+                    || text.contains(['"', '\n', '\r'])
+                    || text.starts_with(' ')
+                    || text.ends_with(' ')
+            }
+        };
+        if quote {
+            format!("\"{}\"", text.replace('"', "\"\""))
+        } else {
+            text
+        }
+    }
+
+    pub fn write_row(&mut self, row: &[Value]) -> Result<()> {
+        match &self.format {
+            ExportFormat::Csv { delimiter, .. } => {
+                let fields = row
+                    .iter()
+                    .map(|value| self.csv_field(value))
+                    .collect::<Vec<_>>();
+                writeln!(self.out, "{}", fields.join(&delimiter.to_string()))?;
+            }
+            ExportFormat::Json => {
+                let object = json_object(&self.columns, row)?;
+                let separator = if self.rows == 0 { "" } else { "," };
+                write!(self.out, "{}\n  {}", separator, object)?;
+            }
+            ExportFormat::Ndjson => {
+                let object = json_object(&self.columns, row)?;
+                writeln!(self.out, "{}", object)?;
+            }
+            ExportFormat::Markdown => {
+                let cells = row
+                    .iter()
+                    .map(|value| match value {
+                        Value::Null => String::new(),
+                        value => markdown_cell(&value.to_string()),
+                    })
+                    .collect::<Vec<_>>();
+                writeln!(self.out, "| {} |", cells.join(" | "))?;
+            }
+            ExportFormat::Sql => {
+                let values = row
+                    .iter()
+                    .map(|value| self.db.sql_literal(value))
+                    .collect::<Vec<_>>();
+                writeln!(self.out, "{}({});", self.insert, values.join(", "))?;
+            }
+        }
+        self.rows += 1;
+        Ok(())
+    }
+
+    pub fn rows(&self) -> u64 {
+        self.rows
+    }
+
+    /// Writes what comes after the rows and flushes the file, returns the number of rows.
+    pub fn finish(mut self) -> Result<u64> {
+        if self.format == ExportFormat::Json {
+            writeln!(self.out, "{}]", if self.rows == 0 { "" } else { "\n" })?;
+        }
+        self.out.flush()?;
+        Ok(self.rows)
+    }
+}
+
+/// Runs a statement again and writes all of its rows page by page. `progress` is told the
+/// rows written after each page and stops the export by returning false.
+/// Returns `None` when the export was stopped.
+pub fn export_all(
+    db: &dyn ConnectionType,
+    export: &Export,
+    statement: &str,
+    params: &QueryParams,
+    mut progress: impl FnMut(u64) -> bool,
+) -> Result<Option<u64>> {
+    let mut results = db.execute(statement, params)?;
+    let mut exporter = Exporter::create(export, results.columns.clone(), db)?;
+    loop {
+        for row in results.rows.iter() {
+            exporter.write_row(&row.data)?;
+        }
+        if !progress(exporter.rows()) {
+            return Ok(None);
+        }
+        match results.next_page {
+            Some(cursor) => results = db.fetch_page(statement, params, &cursor)?,
+            None => break,
+        }
+    }
+    Ok(Some(exporter.finish()?))
+}
+
+fn markdown_cell(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace("\r\n", "<br>")
+        .replace('\n', "<br>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connectiontypes::session::SessionManager;
+    use crate::connectiontypes::utils::get_connection_type;
+    use crate::data::{Connection, Settings};
+    use std::collections::HashMap;
+
+    // the connection types are only created, nothing connects to a server
+    fn connection_type(dsn: &str) -> Box<dyn ConnectionType> {
+        get_connection_type(
+            Connection::new("test".to_string(), dsn.to_string()),
+            HashMap::new(),
+            &SessionManager::new(),
+            &Settings::default(),
+        )
+        .unwrap()
+    }
+
+    // exports the rows to a file of the test's own and returns what was written
+    fn export_text(
+        name: &str,
+        options: &str,
+        columns: &[&str],
+        rows: &[Vec<Value>],
+        dsn: &str,
+    ) -> String {
+        let path =
+            std::env::temp_dir().join(format!("db9s-export-{}-{}", std::process::id(), name));
+        let format = name.rsplit('.').next().unwrap();
+        let export = parse_export(&format!("{} {} {}", format, path.display(), options)).unwrap();
+        let db = connection_type(dsn);
+        let columns = columns.iter().map(|c| c.to_string()).collect();
+        let mut exporter = Exporter::create(&export, columns, db.as_ref()).unwrap();
+        for row in rows {
+            exporter.write_row(row).unwrap();
+        }
+        assert_eq!(exporter.finish().unwrap(), rows.len() as u64);
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        text
+    }
+
+    #[test]
+    fn parse_options() {
+        let export = parse_export("tsv out.tsv quote=all header=no rows=loaded").unwrap();
+        assert_eq!(
+            export.format,
+            ExportFormat::Csv {
+                delimiter: '\t',
+                quoting: CsvQuoting::All,
+                header: false
+            }
+        );
+        assert!(export.loaded_only);
+        let export = parse_export("SQL out.sql table=users").unwrap();
+        assert_eq!(export.format, ExportFormat::Sql);
+        assert_eq!(export.table.as_deref(), Some("users"));
+        assert!(parse_export("csv").is_err());
+        assert!(parse_export("xml out.xml").is_err());
+        assert!(parse_export("json out.json delimiter=;").is_err());
+        assert!(parse_export("csv out.csv delimiter=ab").is_err());
+    }
+
+    #[test]
+    fn csv_quotes_only_what_needs_it() {
+        let row = vec![
+            Value::Null,
+            Value::Text(String::new()),
+            Value::Text("a,b".to_string()),
+            Value::Text("say \"hi\"".to_string()),
+            Value::Text("two\nlines".to_string()),
+            Value::Text(" padded".to_string()),
+            Value::Int(1),
+        ];
+        let columns = ["a", "b", "c", "d", "e", "f", "g"];
+        assert_eq!(
+            export_text(
+                "minimal.csv",
+                "",
+                &columns,
+                std::slice::from_ref(&row),
+                "sqlite://test.db"
+            ),
+            "a,b,c,d,e,f,g\n,\"\",\"a,b\",\"say \"\"hi\"\"\",\"two\nlines\",\" padded\",1\n"
+        );
+        assert_eq!(
+            export_text(
+                "nonnumeric.csv",
+                "quote=nonnumeric header=no delimiter=;",
+                &columns,
+                &[row],
+                "sqlite://test.db"
+            ),
+            ";\"\";\"a,b\";\"say \"\"hi\"\"\";\"two\nlines\";\" padded\";1\n"
+        );
+    }
+
+    #[test]
+    fn json_keeps_types() {
+        let rows = vec![
+            vec![Value::Int(1), Value::Null],
+            vec![
+                Value::Decimal("1.10".to_string()),
+                Value::Text("x".to_string()),
+            ],
+        ];
+        let text = export_text("out.json", "", &["a", "b"], &rows, "sqlite://test.db");
+        let parsed: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(
+            parsed,
+            serde_json::json!([{"a": 1, "b": null}, {"a": "1.10", "b": "x"}])
+        );
+        let text = export_text("out.ndjson", "", &["a", "b"], &rows, "sqlite://test.db");
+        assert_eq!(text.lines().count(), 2);
+        assert_eq!(
+            export_text("empty.json", "", &["a"], &[], "sqlite://test.db"),
+            "[]\n"
+        );
+    }
+
+    #[test]
+    fn sql_literals_of_each_database() {
+        let row = vec![
+            Value::Text("it's a \\ path".to_string()),
+            Value::Bytes(vec![0, 255]),
+            Value::Float(f64::NAN),
+            Value::Float(f64::INFINITY),
+            Value::Null,
+        ];
+        let columns = ["t", "b", "n", "i", "z"];
+        let insert = |name: &str, dsn: &str| {
+            export_text(name, "table=t", &columns, std::slice::from_ref(&row), dsn)
+                .trim_end()
+                .to_string()
+        };
+        assert_eq!(
+            insert("sqlite.sql", "sqlite://test.db"),
+            "INSERT INTO \"t\" (\"t\", \"b\", \"n\", \"i\", \"z\") \
+             VALUES ('it''s a \\ path', X'00ff', NULL, 9e999, NULL);"
+        );
+        assert_eq!(
+            insert("pg.sql", "postgres://localhost/test"),
+            "INSERT INTO \"t\" (\"t\", \"b\", \"n\", \"i\", \"z\") \
+             VALUES ('it''s a \\ path', '\\x00ff', 'NaN', 'Infinity', NULL);"
+        );
+        assert_eq!(
+            insert("mysql.sql", "mysql://localhost/test"),
+            "INSERT INTO `t` (`t`, `b`, `n`, `i`, `z`) \
+             VALUES ('it''s a \\\\ path', X'00ff', 'NaN', 'Infinity', NULL);"
+        );
+    }
+
+    #[test]
+    fn sql_needs_a_table() {
+        let path = std::env::temp_dir().join("db9s-no-table.sql");
+        let export = parse_export(&format!("sql {}", path.display())).unwrap();
+        let db = connection_type("sqlite://test.db");
+        assert!(Exporter::create(&export, vec!["a".to_string()], db.as_ref()).is_err());
+    }
+}
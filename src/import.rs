@@ -0,0 +1,588 @@
+use crate::connectiontypes::base::{
+    parse_param, parse_timestamp, ColumnInfo, ConnectionType, Value,
+};
+use crate::export::{expand_path, parse_bool_option, parse_delimiter};
+use anyhow::{Context, Result};
+use chrono::DateTime;
+use log::error;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+// rows written per batch of an import, progress is reported after each
+const IMPORT_BATCH_ROWS: usize = 1000;
+
+/// What `:import <path> [option=value ...]` asked for.
+#[derive(Clone, Debug)]
+pub struct ImportOptions {
+    pub path: PathBuf,
+    // guessed from the file name when not given
+    delimiter: Option<char>,
+    header: bool,
+}
+
+/// Parses the arguments of the import command, e.g. `~/users.csv delimiter=; header=no`.
+pub fn parse_import(args: &str) -> Result<ImportOptions> {
+    let mut parts = args.split_whitespace();
+    let path = parts.next().ok_or(anyhow::anyhow!(
+        "Usage: :import <path> [delimiter=<char>] [header=no]"
+    ))?;
+    let mut options = ImportOptions {
+        path: expand_path(path),
+        delimiter: None,
+        header: true,
+    };
+    for option in parts {
+        let (name, value) = option.split_once('=').ok_or(anyhow::anyhow!(
+            "Options are written as name=value, not `{}`",
+            option
+        ))?;
+        match name {
+            "delimiter" => options.delimiter = Some(parse_delimiter(value)?),
+            "header" => options.header = parse_bool_option(name, value)?,
+            _ => return Err(anyhow::anyhow!("Imports have no {} option", name)),
+        }
+    }
+    Ok(options)
+}
+
+/// The rows read from a file, every row holds a value for each column.
+pub struct ImportFile {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+/// Reads a CSV file, or an NDJSON file or JSON array of objects when the name says so.
+pub fn read_file(options: &ImportOptions) -> Result<ImportFile> {
+    let text = std::fs::read_to_string(&options.path)
+        .map_err(|err| anyhow::anyhow!("{}: {}", options.path.display(), err))?;
+    let text = text.trim_start_matches('\u{feff}');
+    let extension = options
+        .path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let file = match extension.as_str() {
+        "json" | "ndjson" | "jsonl" => read_json(text)?,
+        "tsv" => read_csv(text, options.delimiter.unwrap_or('\t'), options.header)?,
+        _ => read_csv(text, options.delimiter.unwrap_or(','), options.header)?,
+    };
+    if file.rows.is_empty() {
+        return Err(anyhow::anyhow!("{} holds no rows", options.path.display()));
+    }
+    Ok(file)
+}
+
+/// Splits CSV text into records. An empty field is NULL unless it is quoted,
+/// the way the export writes them.
+fn csv_records(text: &str, delimiter: char) -> Result<Vec<Vec<Value>>> {
+    let mut records = vec![];
+    let mut record = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+    let mut line = 1;
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                c => {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    field.push(c);
+                }
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() && !quoted => {
+                quoted = true;
+                in_quotes = true;
+            }
+            c if c == delimiter => {
+                record.push(csv_value(std::mem::take(&mut field), quoted));
+                quoted = false;
+            }
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                line += 1;
+                record.push(csv_value(std::mem::take(&mut field), quoted));
+                quoted = false;
+                records.push(std::mem::take(&mut record));
+            }
+            c => field.push(c),
+        }
+    }
+    if in_quotes {
+        return Err(anyhow::anyhow!("Unterminated quote on line {}", line));
+    }
+    if !field.is_empty() || quoted || !record.is_empty() {
+        record.push(csv_value(field, quoted));
+        records.push(record);
+    }
+    // blank lines hold no row
+    records.retain(|record| !(record.len() == 1 && record[0].is_null()));
+    Ok(records)
+}
+
+fn csv_value(field: String, quoted: bool) -> Value {
+    if field.is_empty() && !quoted {
+        Value::Null
+    } else {
+        Value::Text(field)
+    }
+}
+
+fn read_csv(text: &str, delimiter: char, header: bool) -> Result<ImportFile> {
+    let mut records = csv_records(text, delimiter)?;
+    let width = records.iter().map(|record| record.len()).max().unwrap_or(0);
+    let columns: Vec<String> = if header && !records.is_empty() {
+        records
+            .remove(0)
+            .into_iter()
+            .enumerate()
+            .map(|(idx, name)| match name {
+                Value::Null => format!("column{}", idx + 1),
+                name => name.to_string(),
+            })
+            .collect()
+    } else {
+        (1..=width).map(|idx| format!("column{}", idx)).collect()
+    };
+    let rows = records
+        .into_iter()
+        .map(|mut record| {
+            record.resize(columns.len(), Value::Null);
+            record
+        })
+        .collect();
+    Ok(ImportFile { columns, rows })
+}
+
+fn read_json(text: &str) -> Result<ImportFile> {
+    let objects: Vec<serde_json::Value> = if text.trim_start().starts_with('[') {
+        serde_json::from_str(text)?
+    } else {
+        text.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(idx, line)| {
+                serde_json::from_str(line)
+                    .map_err(|err| anyhow::anyhow!("Line {}: {}", idx + 1, err))
+            })
+            .collect::<Result<_>>()?
+    };
+    // the columns are the keys of all objects in the order they are first seen
+    let mut columns: Vec<String> = vec![];
+    for (idx, object) in objects.iter().enumerate() {
+        let object = object
+            .as_object()
+            .ok_or(anyhow::anyhow!("Row {} is not a JSON object", idx + 1))?;
+        for key in object.keys() {
+            if !columns.contains(key) {
+                columns.push(key.clone());
+            }
+        }
+    }
+    let rows = objects
+        .iter()
+        .map(|object| {
+            columns
+                .iter()
+                .map(|column| {
+                    object
+                        .get(column)
+                        .map(Value::from_json)
+                        .unwrap_or(Value::Null)
+                })
+                .collect()
+        })
+        .collect();
+    Ok(ImportFile { columns, rows })
+}
+
+/// Converts a value read from a file to the type of the column it goes into.
+/// Types the value can't be checked against locally are left to the server.
+pub fn coerce(value: &Value, column: &ColumnInfo) -> Result<Value> {
+    let data_type = column.data_type.to_lowercase();
+    // `bigint(20) unsigned` and `double precision` are named by their first word
+    let base = data_type.split(['(', ' ']).next().unwrap_or_default();
+    let text = match value {
+        Value::Null if !column.nullable => {
+            return Err(anyhow::anyhow!("{} can't be NULL", column.name))
+        }
+        Value::Null => return Ok(Value::Null),
+        Value::Text(text) => text.clone(),
+        value if ["json", "jsonb", "object", "nested"].contains(&base) => {
+            return Ok(Value::Json(value.to_json()))
+        }
+        value => value.to_string(),
+    };
+    let cast = match base {
+        "bool" | "boolean" => "bool",
+        "tinyint" if data_type.starts_with("tinyint(1)") => "bool",
+        "int" | "integer" | "bigint" | "smallint" | "tinyint" | "mediumint" | "int2" | "int4"
+        | "int8" | "serial" | "bigserial" | "smallserial" | "long" | "short" | "byte" => "int",
+        "real" | "double" | "float" | "float4" | "float8" | "half_float" | "scaled_float" => {
+            "float"
+        }
+        "numeric" | "decimal" => "decimal",
+        "json" | "jsonb" | "object" | "nested" => "json",
+        "timestamptz" | "timestamp"
+            if base == "timestamptz" || data_type.contains("with time zone") =>
+        {
+            // the offset is kept, the server converts it
+            if DateTime::parse_from_rfc3339(text.trim()).is_err() {
+                parse_timestamp(&text)?;
+            }
+            return Ok(Value::Text(text));
+        }
+        "timestamp" | "datetime" => "timestamp",
+        "date" => {
+            // ElasticSearch dates may carry a time as well
+            let date = parse_param(&format!("{}::date", text));
+            if date.is_err()
+                && (DateTime::parse_from_rfc3339(text.trim()).is_ok()
+                    || parse_timestamp(&text).is_ok())
+            {
+                return Ok(Value::Text(text));
+            }
+            return date;
+        }
+        "time" if !data_type.contains("with time zone") => "time",
+        "uuid" => {
+            return Ok(Value::Uuid(
+                uuid::Uuid::parse_str(text.trim())
+                    .map_err(|_| anyhow::anyhow!("`{}` is not a uuid", text))?,
+            ))
+        }
+        _ => return Ok(Value::Text(text)),
+    };
+    parse_param(&format!("{}::{}", text, cast))
+}
+
+/// A file about to be imported into a table, with the file column each table column is filled from.
+#[derive(Clone)]
+pub struct ImportPlan {
+    pub table: String,
+    pub path: PathBuf,
+    pub file: Arc<ImportFile>,
+    pub columns: Vec<ColumnInfo>,
+    // the file column of each table column, columns without one get their defaults
+    pub mapping: Vec<Option<usize>>,
+    pub current: usize,
+}
+
+impl ImportPlan {
+    /// Table columns are matched with the file columns of the same name, ignoring case.
+    pub fn new(table: String, path: PathBuf, file: ImportFile, columns: Vec<ColumnInfo>) -> Self {
+        let mapping = columns
+            .iter()
+            .map(|column| {
+                file.columns
+                    .iter()
+                    .position(|name| name.trim().eq_ignore_ascii_case(&column.name))
+            })
+            .collect();
+        ImportPlan {
+            table,
+            path,
+            file: Arc::new(file),
+            columns,
+            mapping,
+            current: 0,
+        }
+    }
+
+    pub fn move_current(&mut self, offset: i32) {
+        let last = self.columns.len().saturating_sub(1) as i32;
+        self.current = (self.current as i32 + offset).clamp(0, last) as usize;
+    }
+
+    /// Fill the current table column from the next file column, after the last one from none.
+    pub fn cycle_mapping(&mut self, forward: bool) {
+        let count = self.file.columns.len();
+        let mapping = &mut self.mapping[self.current];
+        *mapping = match (*mapping, forward) {
+            (None, true) => Some(0),
+            (None, false) => count.checked_sub(1),
+            (Some(idx), true) if idx + 1 < count => Some(idx + 1),
+            (Some(idx), false) if idx > 0 => Some(idx - 1),
+            (Some(_), _) => None,
+        };
+    }
+
+    /// The table columns filled from the file.
+    pub fn target_columns(&self) -> Vec<&ColumnInfo> {
+        self.columns
+            .iter()
+            .zip(self.mapping.iter())
+            .filter(|(_, mapping)| mapping.is_some())
+            .map(|(column, _)| column)
+            .collect()
+    }
+
+    /// The values of a row of the file for the target columns, converted to their types.
+    pub fn coerce_row(&self, row: usize) -> Vec<Result<Value>> {
+        let values = &self.file.rows[row];
+        self.columns
+            .iter()
+            .zip(self.mapping.iter())
+            .filter_map(|(column, mapping)| mapping.map(|idx| coerce(&values[idx], column)))
+            .collect()
+    }
+}
+
+/// Writes the rows of the file in batches, in a transaction of its own unless one is open.
+/// `progress` is told the rows read after each batch and stops the import by returning
+/// false. Returns `None` when the import was stopped, which undoes it.
+pub fn import_all(
+    db: &dyn ConnectionType,
+    in_transaction: bool,
+    plan: &ImportPlan,
+    progress: impl FnMut(usize) -> bool,
+) -> Result<Option<u64>> {
+    let transaction = db.supports_transactions() && !in_transaction;
+    if transaction {
+        db.begin_transaction()?;
+    }
+    let result = import_batches(db, plan, progress);
+    if !transaction {
+        return result;
+    }
+    match result {
+        Ok(Some(_)) => db.commit_transaction()?,
+        _ => {
+            if let Err(err) = db.rollback_transaction() {
+                error!("Rollback failed: {:?}", err);
+            }
+        }
+    }
+    result.context("Nothing was imported")
+}
+
+fn import_batches(
+    db: &dyn ConnectionType,
+    plan: &ImportPlan,
+    mut progress: impl FnMut(usize) -> bool,
+) -> Result<Option<u64>> {
+    let columns = plan
+        .target_columns()
+        .into_iter()
+        .map(|column| column.name.clone())
+        .collect::<Vec<_>>();
+    let total = plan.file.rows.len();
+    let mut imported = 0;
+    for start in (0..total).step_by(IMPORT_BATCH_ROWS) {
+        let batch = (start..total.min(start + IMPORT_BATCH_ROWS))
+            .map(|row| {
+                plan.coerce_row(row)
+                    .into_iter()
+                    .collect::<Result<Vec<_>>>()
+                    .with_context(|| format!("Row {}", row + 1))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        imported += db.import_rows(&plan.table, &columns, &batch)?;
+        if !progress(start + batch.len()) {
+            return Ok(None);
+        }
+    }
+    Ok(Some(imported))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connectiontypes::session::SessionManager;
+    use crate::connectiontypes::utils::get_connection_type;
+    use crate::data::{Connection, Settings};
+    use crate::export::{parse_export, Exporter};
+    use std::collections::HashMap;
+
+    fn text(value: &str) -> Value {
+        Value::Text(value.to_string())
+    }
+
+    fn column(data_type: &str, nullable: bool) -> ColumnInfo {
+        ColumnInfo {
+            data_type: data_type.to_string(),
+            nullable,
+            ..ColumnInfo::named("c".to_string())
+        }
+    }
+
+    #[test]
+    fn quoted_fields() {
+        let records = csv_records("a,\"b,c\",\"say \"\"hi\"\"\"\n\"two\nlines\",x\n", ',').unwrap();
+        assert_eq!(
+            records,
+            vec![
+                vec![text("a"), text("b,c"), text("say \"hi\"")],
+                vec![text("two\nlines"), text("x")],
+            ]
+        );
+    }
+
+    #[test]
+    fn null_is_an_unquoted_empty_field() {
+        let records = csv_records(",\"\",x\r\n\r\n\n;a\r\n", ',').unwrap();
+        assert_eq!(
+            records,
+            vec![vec![Value::Null, text(""), text("x")], vec![text(";a")]]
+        );
+        assert_eq!(
+            csv_records("a;;b", ';').unwrap(),
+            vec![vec![text("a"), Value::Null, text("b")]]
+        );
+        assert_eq!(csv_records("\"\"", ',').unwrap(), vec![vec![text("")]]);
+    }
+
+    #[test]
+    fn unterminated_quote() {
+        let err = csv_records("a\nb,\"c\nd", ',').unwrap_err();
+        assert_eq!(err.to_string(), "Unterminated quote on line 3");
+    }
+
+    #[test]
+    fn header_and_short_rows() {
+        let file = read_csv("id,,name\n1\n2,x,y\n", ',', true).unwrap();
+        assert_eq!(file.columns, vec!["id", "column2", "name"]);
+        assert_eq!(
+            file.rows,
+            vec![
+                vec![text("1"), Value::Null, Value::Null],
+                vec![text("2"), text("x"), text("y")],
+            ]
+        );
+        let file = read_csv("1\n2,x\n", ',', false).unwrap();
+        assert_eq!(file.columns, vec!["column1", "column2"]);
+        assert_eq!(file.rows[0], vec![text("1"), Value::Null]);
+    }
+
+    #[test]
+    fn json_columns_in_order_seen() {
+        let file = read_json("{\"b\": 1}\n\n{\"a\": \"x\", \"b\": null}\n").unwrap();
+        assert_eq!(file.columns, vec!["b", "a"]);
+        assert_eq!(
+            file.rows,
+            vec![
+                vec![Value::Int(1), Value::Null],
+                vec![Value::Null, text("x")]
+            ]
+        );
+        assert!(read_json("[1]").is_err());
+    }
+
+    #[test]
+    fn coerce_to_column_types() {
+        assert_eq!(
+            coerce(&text(" 42"), &column("bigint(20) unsigned", true)).unwrap(),
+            Value::Int(42)
+        );
+        assert_eq!(
+            coerce(&text("1"), &column("tinyint(1)", true)).unwrap(),
+            Value::Bool(true)
+        );
+        assert_eq!(
+            coerce(&text("7"), &column("tinyint", true)).unwrap(),
+            Value::Int(7)
+        );
+        assert_eq!(
+            coerce(&text("1.50"), &column("numeric(10,2)", true)).unwrap(),
+            Value::Decimal("1.50".to_string())
+        );
+        assert_eq!(
+            coerce(&Value::Int(3), &column("double precision", true)).unwrap(),
+            Value::Float(3.0)
+        );
+        assert_eq!(
+            coerce(&Value::Int(1), &column("jsonb", true)).unwrap(),
+            Value::Json(serde_json::json!(1))
+        );
+        assert_eq!(
+            coerce(&text("2024-01-02"), &column("date", true)).unwrap(),
+            Value::Date(chrono::NaiveDate::from_ymd_opt(2024, 1, 2).unwrap())
+        );
+        assert_eq!(
+            coerce(
+                &text("2024-01-02T03:04:05+02:00"),
+                &column("timestamp with time zone", true)
+            )
+            .unwrap(),
+            text("2024-01-02T03:04:05+02:00")
+        );
+        assert!(matches!(
+            coerce(
+                &text("67e55044-10b1-426f-9247-bb680e5fe0c8"),
+                &column("uuid", true)
+            )
+            .unwrap(),
+            Value::Uuid(_)
+        ));
+        // types not checked here go to the server as they are
+        assert_eq!(
+            coerce(&text("(1,2)"), &column("point", true)).unwrap(),
+            text("(1,2)")
+        );
+        assert_eq!(
+            coerce(&Value::Null, &column("int", true)).unwrap(),
+            Value::Null
+        );
+    }
+
+    #[test]
+    fn coerce_errors() {
+        assert_eq!(
+            coerce(&Value::Null, &column("text", false))
+                .unwrap_err()
+                .to_string(),
+            "c can't be NULL"
+        );
+        assert!(coerce(&text("abc"), &column("integer", true)).is_err());
+        assert!(coerce(&text("2024-13-01"), &column("date", true)).is_err());
+        assert!(coerce(&text("nope"), &column("uuid", true)).is_err());
+        assert!(coerce(&text("yesterday"), &column("timestamptz", true)).is_err());
+    }
+
+    #[test]
+    fn export_round_trip() {
+        let path = std::env::temp_dir().join(format!("db9s-round-trip-{}.csv", std::process::id()));
+        let export = parse_export(&format!("csv {}", path.display())).unwrap();
+        let db = get_connection_type(
+            Connection::new("test".to_string(), "sqlite://test.db".to_string()),
+            HashMap::new(),
+            &SessionManager::new(),
+            &Settings::default(),
+        )
+        .unwrap();
+        let columns = vec!["id".to_string(), "note".to_string()];
+        let rows = vec![
+            vec![Value::Int(1), Value::Null],
+            vec![Value::Int(2), text("")],
+            vec![Value::Int(3), text(" a, \"b\"\r\nc ")],
+        ];
+        let mut exporter = Exporter::create(&export, columns.clone(), db.as_ref()).unwrap();
+        for row in &rows {
+            exporter.write_row(row).unwrap();
+        }
+        exporter.finish().unwrap();
+
+        let file = read_file(&parse_import(&path.display().to_string()).unwrap());
+        std::fs::remove_file(&path).unwrap();
+        let file = file.unwrap();
+        assert_eq!(file.columns, columns);
+        assert_eq!(
+            file.rows,
+            vec![
+                vec![text("1"), Value::Null],
+                vec![text("2"), text("")],
+                vec![text("3"), text(" a, \"b\"\r\nc ")],
+            ]
+        );
+    }
+}
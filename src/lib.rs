@@ -1,5 +1,8 @@
 pub mod app;
+pub mod changes;
 pub mod connectiontypes;
 pub mod data;
+pub mod export;
+pub mod import;
 pub mod types;
 pub mod ui;
@@ -1,7 +1,7 @@
 use crossterm::event::Event;
 use ratatui::{layout::Rect, Frame};
 
-trait ListArea {
+pub trait ListArea {
     fn render(&mut self, frame: &mut Frame, rect: Rect, event: Option<Event>);
     fn get_key(&self) -> &'static str;
 }
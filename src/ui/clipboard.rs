@@ -0,0 +1,50 @@
+use anyhow::Result;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Puts text on the system clipboard through the terminal with the OSC 52 escape sequence,
+/// so it also works over ssh. When a `command` is configured the text is piped to it too.
+pub fn copy(text: &str, command: Option<&str>) -> Result<()> {
+    let mut sequence = format!("\x1b]52;c;{}\x07", base64::encode(text));
+    // tmux only passes the sequence on to the terminal when it is wrapped
+    if std::env::var_os("TMUX").is_some() {
+        sequence = format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"));
+    }
+    let mut stdout = std::io::stdout();
+    stdout.write_all(sequence.as_bytes())?;
+    stdout.flush()?;
+    match command {
+        Some(command) if !command.trim().is_empty() => pipe_to(text, command),
+        _ => Ok(()),
+    }
+}
+
+fn pipe_to(text: &str, command: &str) -> Result<()> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| anyhow::anyhow!("{}: {}", command, err))?;
+    child
+        .stdin
+        .take()
+        .ok_or(anyhow::anyhow!("{}: no stdin", command))?
+        .write_all(text.as_bytes())?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(anyhow::anyhow!(
+            "{} failed: {}",
+            command,
+            if stderr.is_empty() {
+                output.status.to_string()
+            } else {
+                stderr
+            }
+        ));
+    }
+    Ok(())
+}
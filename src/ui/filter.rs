@@ -0,0 +1,161 @@
+use super::main::columns::cell_text;
+use super::state::WindowDataRow;
+use anyhow::Result;
+use regex::{Regex, RegexBuilder};
+
+/// How the text typed after `/` is matched against the cells.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum FilterMode {
+    #[default]
+    Substring,
+    // the characters in order with anything between them, within one cell
+    Fuzzy,
+    Regex,
+}
+
+impl FilterMode {
+    pub fn next(&self) -> FilterMode {
+        match self {
+            FilterMode::Substring => FilterMode::Fuzzy,
+            FilterMode::Fuzzy => FilterMode::Regex,
+            FilterMode::Regex => FilterMode::Substring,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FilterMode::Substring => "substring",
+            FilterMode::Fuzzy => "fuzzy",
+            FilterMode::Regex => "regex",
+        }
+    }
+}
+
+/// Narrows the rows of a window to those with a cell matching the text, ignoring case.
+#[derive(Clone)]
+pub struct Filter {
+    pub text: String,
+    pub mode: FilterMode,
+    // substrings are found with an escaped pattern so the matches are byte ranges of the cell
+    regex: Option<Regex>,
+}
+
+impl Filter {
+    /// Fails on a regular expression that does not compile.
+    pub fn new(text: &str, mode: FilterMode) -> Result<Filter> {
+        let pattern = match mode {
+            FilterMode::Substring => Some(regex::escape(text)),
+            FilterMode::Fuzzy => None,
+            FilterMode::Regex => Some(text.to_string()),
+        };
+        let regex = match pattern {
+            Some(pattern) => Some(RegexBuilder::new(&pattern).case_insensitive(true).build()?),
+            None => None,
+        };
+        Ok(Filter {
+            text: text.to_string(),
+            mode,
+            regex,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// The byte ranges of the text that match, none when the text does not match.
+    pub fn find(&self, text: &str) -> Option<Vec<(usize, usize)>> {
+        if self.is_empty() {
+            return None;
+        }
+        let ranges = match self.regex.as_ref() {
+            Some(regex) => regex
+                .find_iter(text)
+                .filter(|found| !found.is_empty())
+                .map(|found| (found.start(), found.end()))
+                .collect::<Vec<_>>(),
+            None => fuzzy_find(&self.text, text),
+        };
+        Some(ranges).filter(|ranges| !ranges.is_empty())
+    }
+
+    /// Whether the text of a cell of the row is found, `sizes` tells the size columns.
+    pub fn matches(&self, row: &WindowDataRow, sizes: &[bool]) -> bool {
+        self.is_empty()
+            || row.data.iter().enumerate().any(|(idx, value)| {
+                self.find(&cell_text(value, sizes.get(idx) == Some(&true)))
+                    .is_some()
+            })
+    }
+}
+
+/// The characters of the pattern found in order in the text, each as a range.
+fn fuzzy_find(pattern: &str, text: &str) -> Vec<(usize, usize)> {
+    let mut wanted = pattern.chars().filter(|c| !c.is_whitespace()).peekable();
+    let mut ranges = vec![];
+    for (start, c) in text.char_indices() {
+        match wanted.peek() {
+            Some(w) if w.to_lowercase().eq(c.to_lowercase()) => {
+                ranges.push((start, start + c.len_utf8()));
+                wanted.next();
+            }
+            Some(_) => {}
+            None => break,
+        }
+    }
+    if wanted.peek().is_some() {
+        return vec![];
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connectiontypes::base::Value;
+
+    fn find(text: &str, mode: FilterMode, cell: &str) -> Option<Vec<(usize, usize)>> {
+        Filter::new(text, mode).unwrap().find(cell)
+    }
+
+    #[test]
+    fn ranges_of_each_mode() {
+        use FilterMode::{Fuzzy, Regex, Substring};
+        for (text, mode, cell, ranges) in [
+            ("", Substring, "abc", None),
+            ("B", Substring, "abcb", Some(vec![(1, 2), (3, 4)])),
+            // the text is not a pattern
+            ("a.b", Substring, "axb", None),
+            ("a.b", Substring, "A.B", Some(vec![(0, 3)])),
+            ("é", Substring, "CAFÉ", Some(vec![(3, 5)])),
+            ("a.b", Regex, "axb", Some(vec![(0, 3)])),
+            ("^\\d+$", Regex, "42", Some(vec![(0, 2)])),
+            // empty matches mark nothing and match nothing
+            ("x*", Regex, "abc", None),
+            ("x*", Regex, "axxb", Some(vec![(1, 3)])),
+            ("^", Regex, "abc", None),
+            ("nme", Fuzzy, "Name", Some(vec![(0, 1), (2, 3), (3, 4)])),
+            ("n m", Fuzzy, "Name", Some(vec![(0, 1), (2, 3)])),
+            ("cé", Fuzzy, "Café", Some(vec![(0, 1), (3, 5)])),
+            ("men", Fuzzy, "Name", None),
+            ("namex", Fuzzy, "Name", None),
+        ] {
+            assert_eq!(find(text, mode, cell), ranges, "{:?} in {:?}", text, cell);
+        }
+        assert!(Filter::new("(", Regex).is_err());
+        assert!(Filter::new("(", Substring).is_ok());
+    }
+
+    #[test]
+    fn rows_match_on_any_cell() {
+        let row = WindowDataRow::new("0".to_string(), vec![Value::Null, Value::Int(2048)]);
+        let filter = |text: &str| Filter::new(text, FilterMode::Substring).unwrap();
+        assert!(filter("").matches(&row, &[]));
+        assert!(filter("null").matches(&row, &[]));
+        assert!(filter("2048").matches(&row, &[]));
+        // size columns are matched as they are shown
+        assert!(filter("2.0 kB").matches(&row, &[false, true]));
+        assert!(!filter("2048").matches(&row, &[false, true]));
+        assert!(!filter("kB").matches(&row, &[]));
+    }
+}
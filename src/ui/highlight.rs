@@ -0,0 +1,172 @@
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+
+static KEYWORDS: [&str; 100] = [
+    "ACTION",
+    "ADD",
+    "AFTER",
+    "ALL",
+    "ALTER",
+    "ALWAYS",
+    "AND",
+    "AS",
+    "ASC",
+    "AUTO_INCREMENT",
+    "BEFORE",
+    "BEGIN",
+    "BY",
+    "CACHE",
+    "CASCADE",
+    "CASE",
+    "CHARSET",
+    "CHECK",
+    "COLLATE",
+    "COMMENT",
+    "CONSTRAINT",
+    "CREATE",
+    "CYCLE",
+    "DECLARE",
+    "DEFAULT",
+    "DEFERRABLE",
+    "DEFERRED",
+    "DELETE",
+    "DESC",
+    "DISTINCT",
+    "EACH",
+    "ELSE",
+    "END",
+    "ENGINE",
+    "ENUM",
+    "EXECUTE",
+    "EXISTS",
+    "FOR",
+    "FOREIGN",
+    "FROM",
+    "FUNCTION",
+    "GENERATED",
+    "GROUP",
+    "HAVING",
+    "IDENTITY",
+    "IF",
+    "IMMEDIATE",
+    "IN",
+    "INCLUDE",
+    "INCREMENT",
+    "INDEX",
+    "INITIALLY",
+    "INSERT",
+    "INSTEAD",
+    "INTO",
+    "IS",
+    "JOIN",
+    "KEY",
+    "LANGUAGE",
+    "LEFT",
+    "LIMIT",
+    "MATERIALIZED",
+    "MAXVALUE",
+    "MINVALUE",
+    "NO",
+    "NOT",
+    "NULL",
+    "OF",
+    "ON",
+    "OR",
+    "ORDER",
+    "PARTITION",
+    "PRIMARY",
+    "PROCEDURE",
+    "PUT",
+    "REFERENCES",
+    "REPLACE",
+    "RESTRICT",
+    "RETURN",
+    "RETURNS",
+    "ROW",
+    "SELECT",
+    "SEQUENCE",
+    "SERVER",
+    "SET",
+    "START",
+    "STORED",
+    "TABLE",
+    "THEN",
+    "TRIGGER",
+    "TYPE",
+    "UNIQUE",
+    "UNLOGGED",
+    "UPDATE",
+    "USING",
+    "VALUES",
+    "VIEW",
+    "WHEN",
+    "WHERE",
+    "WITH",
+];
+
+/// Colours the keywords, strings, numbers and comments of a line of SQL.
+/// Every line stands on its own, strings and comments spanning lines are not followed.
+pub fn highlight_sql(line: &str) -> Line<'static> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut spans = vec![];
+    let mut plain = String::new();
+    let mut pos = 0;
+    while pos < chars.len() {
+        let c = chars[pos];
+        let start = pos;
+        let style = if c == '-' && chars.get(pos + 1) == Some(&'-') {
+            pos = chars.len();
+            Style::default()
+                .fg(Color::DarkGray)
+                .add_modifier(Modifier::ITALIC)
+        } else if c == '\'' || c == '"' || c == '`' {
+            pos += 1;
+            while pos < chars.len() {
+                pos += 1;
+                if chars[pos - 1] == c {
+                    // a doubled quote is an escaped quote
+                    if chars.get(pos) != Some(&c) {
+                        break;
+                    }
+                    pos += 1;
+                }
+            }
+            // quoted identifiers apart from strings
+            if c == '\'' {
+                Style::default().fg(Color::Green)
+            } else {
+                Style::default().fg(Color::Cyan)
+            }
+        } else if c.is_alphanumeric() || c == '_' {
+            while pos < chars.len() && (chars[pos].is_alphanumeric() || chars[pos] == '_') {
+                pos += 1;
+            }
+            let word: String = chars[start..pos].iter().collect();
+            if word.chars().all(|c| c.is_ascii_digit()) {
+                Style::default().fg(Color::Magenta)
+            } else if KEYWORDS.contains(&word.to_uppercase().as_str()) {
+                Style::default()
+                    .fg(Color::LightBlue)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                plain.push_str(&word);
+                continue;
+            }
+        } else {
+            plain.push(c);
+            pos += 1;
+            continue;
+        };
+        if !plain.is_empty() {
+            spans.push(Span::raw(std::mem::take(&mut plain)));
+        }
+        spans.push(Span::styled(
+            chars[start..pos].iter().collect::<String>(),
+            style,
+        ));
+    }
+    if !plain.is_empty() {
+        spans.push(Span::raw(plain));
+    }
+    Line::from(spans)
+}
@@ -3,22 +3,28 @@ use crate::connectiontypes::utils::validate_dsn;
 use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
 use ratatui::prelude::Line;
 use ratatui::{
-    layout::Rect,
-    style::{Color, Style},
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
     text::Span,
-    widgets::{Block, Borders, Paragraph},
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table},
     Frame,
 };
 use ratatui_textarea::{CursorMove, TextArea};
-use std::cmp;
 use std::{sync::Arc, sync::RwLock};
 
-use crate::ui::state::LayoutState;
+use crate::connectiontypes::base::Value;
+use crate::import::ImportPlan;
+use crate::ui::filter::{Filter, FilterMode};
+use crate::ui::state::{LayoutState, PendingAction};
 use crate::ui::types;
 
 trait InputReceiver {
     fn receive_input(&mut self, event: Event) -> bool;
     fn active(&self, event: Option<Event>) -> bool;
+    // passive receivers only display something and never take keys away from the main area
+    fn passive(&self) -> bool {
+        false
+    }
     fn clear(&mut self);
     fn layout_size(&self) -> u16;
     fn render(&mut self, frame: &mut Frame, rect: Rect);
@@ -27,16 +33,14 @@ trait InputReceiver {
 struct CommandInputReceiver {
     input: String,
     active: bool,
-    app: Arc<Application>,
     state: Arc<RwLock<LayoutState>>,
 }
 
 impl CommandInputReceiver {
-    pub fn new(app: Arc<Application>, state: Arc<RwLock<LayoutState>>) -> CommandInputReceiver {
+    pub fn new(state: Arc<RwLock<LayoutState>>) -> CommandInputReceiver {
         CommandInputReceiver {
             input: String::new(),
             active: false,
-            app,
             state,
         }
     }
@@ -115,6 +119,75 @@ impl CommandInputReceiver {
                 self.input.clear();
                 true
             }
+            ":plan" => {
+                self.state
+                    .write()
+                    .unwrap()
+                    .change_window(types::WindowTypeID::PLAN);
+                self.input.clear();
+                true
+            }
+            ":views" => {
+                self.state
+                    .write()
+                    .unwrap()
+                    .change_window(types::WindowTypeID::VIEWS);
+                self.input.clear();
+                true
+            }
+            ":functions" => {
+                self.state
+                    .write()
+                    .unwrap()
+                    .change_window(types::WindowTypeID::FUNCTIONS);
+                self.input.clear();
+                true
+            }
+            ":triggers" => {
+                self.state
+                    .write()
+                    .unwrap()
+                    .change_window(types::WindowTypeID::TRIGGERS);
+                self.input.clear();
+                true
+            }
+            ":sequences" => {
+                self.state
+                    .write()
+                    .unwrap()
+                    .change_window(types::WindowTypeID::SEQUENCES);
+                self.input.clear();
+                true
+            }
+            ":enums" => {
+                self.state
+                    .write()
+                    .unwrap()
+                    .change_window(types::WindowTypeID::ENUMS);
+                self.input.clear();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Commands taking arguments, e.g. `:export csv out.csv`.
+    fn run_command(&mut self) -> bool {
+        let (command, args) = self
+            .input
+            .split_once(' ')
+            .unwrap_or((self.input.as_str(), ""));
+        match command {
+            ":export" => {
+                self.state.write().unwrap().export(args);
+                self.input.clear();
+                true
+            }
+            ":import" => {
+                self.state.write().unwrap().import(args);
+                self.input.clear();
+                true
+            }
             _ => false,
         }
     }
@@ -126,7 +199,7 @@ impl InputReceiver for CommandInputReceiver {
             match code {
                 KeyCode::Char(c) => {
                     if self.active {
-                        if c != ':' || self.input != ":".to_string() {
+                        if c != ':' || self.input != ":" {
                             self.input.push(c);
                         }
                         return true;
@@ -156,24 +229,36 @@ impl InputReceiver for CommandInputReceiver {
                         self.input = ":columns".to_string();
                     } else if ":history".starts_with(&self.input) {
                         self.input = ":history".to_string();
+                    } else if ":plan".starts_with(&self.input) {
+                        self.input = ":plan".to_string();
+                    } else if ":views".starts_with(&self.input) {
+                        self.input = ":views".to_string();
+                    } else if ":functions".starts_with(&self.input) {
+                        self.input = ":functions".to_string();
+                    } else if ":triggers".starts_with(&self.input) {
+                        self.input = ":triggers".to_string();
+                    } else if ":sequences".starts_with(&self.input) {
+                        self.input = ":sequences".to_string();
+                    } else if ":enums".starts_with(&self.input) {
+                        self.input = ":enums".to_string();
+                    } else if ":export".starts_with(&self.input) {
+                        self.input = ":export ".to_string();
+                    } else if ":import".starts_with(&self.input) {
+                        self.input = ":import ".to_string();
                     }
                 }
-                KeyCode::Backspace => {
-                    if self.active {
-                        self.input.pop();
-                        if self.input.is_empty() {
-                            self.clear();
-                        }
-                        return true;
+                KeyCode::Backspace if self.active => {
+                    self.input.pop();
+                    if self.input.is_empty() {
+                        self.clear();
                     }
+                    return true;
                 }
-                KeyCode::Enter => {
-                    if self.active {
-                        if self.select_window() {
-                            self.clear();
-                        }
-                        return true;
+                KeyCode::Enter if self.active => {
+                    if self.select_window() || self.run_command() {
+                        self.clear();
                     }
+                    return true;
                 }
 
                 KeyCode::Esc => {
@@ -184,7 +269,7 @@ impl InputReceiver for CommandInputReceiver {
         }
         false
     }
-    fn active(&self, event: Option<Event>) -> bool {
+    fn active(&self, _event: Option<Event>) -> bool {
         self.active
     }
     fn clear(&mut self) {
@@ -211,6 +296,136 @@ impl InputReceiver for CommandInputReceiver {
     }
 }
 
+struct FilterInputReceiver {
+    input: String,
+    mode: FilterMode,
+    // typing the filter, once it is kept the keys go to the main area again
+    editing: bool,
+    error: Option<String>,
+    state: Arc<RwLock<LayoutState>>,
+}
+
+impl FilterInputReceiver {
+    pub fn new(state: Arc<RwLock<LayoutState>>) -> FilterInputReceiver {
+        FilterInputReceiver {
+            input: String::new(),
+            mode: FilterMode::default(),
+            editing: false,
+            error: None,
+            state,
+        }
+    }
+
+    /// Filters the rows as the text is typed, a regular expression that does not compile
+    /// leaves the rows filtered by the last one that did.
+    fn apply(&mut self) {
+        match Filter::new(&self.input, self.mode) {
+            Ok(filter) => {
+                self.error = None;
+                self.state.write().unwrap().set_filter(Some(filter));
+            }
+            Err(err) => {
+                // the error of the regex crate ends with the reason below the pattern
+                let message = err.to_string();
+                self.error = message.lines().last().map(|line| line.to_string());
+            }
+        }
+    }
+}
+
+impl InputReceiver for FilterInputReceiver {
+    fn receive_input(&mut self, event: Event) -> bool {
+        let code = match event {
+            Event::Key(KeyEvent { code, .. }) => code,
+            _ => return self.editing,
+        };
+        if !self.editing {
+            let mut state = self.state.write().unwrap();
+            return match code {
+                KeyCode::Char('/') => {
+                    let filter = state.get_filter();
+                    self.input = filter.as_ref().map(|f| f.text.clone()).unwrap_or_default();
+                    self.mode = filter.map(|f| f.mode).unwrap_or_default();
+                    self.error = None;
+                    self.editing = true;
+                    state.set_dirty(true);
+                    true
+                }
+                // the record view closes with Esc before the filter is cleared
+                KeyCode::Esc if state.get_filter().is_some() && state.get_record().is_none() => {
+                    state.set_filter(None);
+                    true
+                }
+                _ => false,
+            };
+        }
+        match code {
+            KeyCode::Char(c) => {
+                self.input.push(c);
+                self.apply();
+            }
+            KeyCode::Backspace => {
+                self.input.pop();
+                self.apply();
+            }
+            KeyCode::Tab => {
+                self.mode = self.mode.next();
+                self.apply();
+            }
+            KeyCode::Enter => {
+                if self.input.is_empty() {
+                    self.state.write().unwrap().set_filter(None);
+                }
+                self.editing = false;
+                self.error = None;
+            }
+            KeyCode::Esc => self.clear(),
+            _ => {}
+        }
+        true
+    }
+    fn active(&self, _event: Option<Event>) -> bool {
+        self.editing
+    }
+    fn clear(&mut self) {
+        self.input.clear();
+        self.editing = false;
+        self.error = None;
+        self.state.write().unwrap().set_filter(None);
+    }
+
+    fn render(&mut self, frame: &mut Frame, rect: Rect) {
+        let mut spans = vec![Span::styled(
+            format!("/{}_", self.input),
+            Style::default().fg(Color::Green),
+        )];
+        if let Some(error) = self.error.as_ref() {
+            spans.push(Span::styled(
+                format!("  {}", error),
+                Style::default().fg(Color::Red),
+            ));
+        }
+        let lines = vec![
+            Line::from(spans),
+            Line::from(Span::styled(
+                "Tab to change the mode, Enter to keep the filter, Esc to clear it",
+                Style::default().fg(Color::Gray),
+            )),
+        ];
+        let para = Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default())
+                .title(format!("Filter ({})", self.mode.name())),
+        );
+        frame.render_widget(para, rect);
+    }
+
+    fn layout_size(&self) -> u16 {
+        4
+    }
+}
+
 struct ConnectionInputReceiver {
     input: String,
     name: String,
@@ -245,7 +460,7 @@ impl InputReceiver for ConnectionInputReceiver {
                     if self.active {
                         self.input.push(c);
                         if self.name.is_empty() {
-                            self.name_validated = self.input.len() > 0;
+                            self.name_validated = !self.input.is_empty();
                         } else {
                             (self.dsn_validated, self.dsn_validation_error) =
                                 validate_dsn(self.input.clone());
@@ -259,33 +474,27 @@ impl InputReceiver for ConnectionInputReceiver {
                         }
                     }
                 }
-                KeyCode::Backspace => {
-                    if self.active {
-                        if !self.input.is_empty() {
-                            self.input.pop();
-                        }
-                        return true;
+                KeyCode::Backspace if self.active => {
+                    if !self.input.is_empty() {
+                        self.input.pop();
                     }
+                    return true;
                 }
-                KeyCode::Enter => {
-                    if self.active {
-                        if self.name.is_empty() {
-                            if self.name_validated {
-                                self.name = self.input.clone();
-                                self.input.clear();
-                            }
-                        } else if !self.input.is_empty() {
-                            if self.dsn_validated {
-                                let dsn = self.input.clone();
-                                let name = self.name.clone();
-                                self.clear();
-                                let mut data = self.app.persistent_data.write().unwrap();
-                                data.add_connection(name, dsn);
-                                self.state.write().unwrap().refresh();
-                            }
+                KeyCode::Enter if self.active => {
+                    if self.name.is_empty() {
+                        if self.name_validated {
+                            self.name = self.input.clone();
+                            self.input.clear();
                         }
-                        return true;
+                    } else if !self.input.is_empty() && self.dsn_validated {
+                        let dsn = self.input.clone();
+                        let name = self.name.clone();
+                        self.clear();
+                        let mut data = self.app.persistent_data.write().unwrap();
+                        data.add_connection(name, dsn);
+                        self.state.write().unwrap().refresh();
                     }
+                    return true;
                 }
 
                 KeyCode::Esc => {
@@ -296,7 +505,7 @@ impl InputReceiver for ConnectionInputReceiver {
         }
         false
     }
-    fn active(&self, event: Option<Event>) -> bool {
+    fn active(&self, _event: Option<Event>) -> bool {
         self.active
     }
     fn clear(&mut self) {
@@ -308,14 +517,13 @@ impl InputReceiver for ConnectionInputReceiver {
     }
 
     fn render(&mut self, frame: &mut Frame, rect: Rect) {
-        let lines;
-        if self.name.is_empty() {
+        let lines = if self.name.is_empty() {
             let color = if self.name_validated {
                 Color::Green
             } else {
                 Color::Red
             };
-            lines = vec![
+            vec![
                 Line::from(Span::styled(
                     "Connection Name: ",
                     Style::default().fg(color),
@@ -324,14 +532,14 @@ impl InputReceiver for ConnectionInputReceiver {
                     self.input.clone(),
                     Style::default().fg(Color::White),
                 )),
-            ];
+            ]
         } else {
             let color = if self.dsn_validated {
                 Color::Green
             } else {
                 Color::Red
             };
-            lines = vec![
+            vec![
                 Line::from(Span::styled(
                     format!("DSN: {}", self.dsn_validation_error),
                     Style::default().fg(color),
@@ -340,8 +548,8 @@ impl InputReceiver for ConnectionInputReceiver {
                     self.input.clone(),
                     Style::default().fg(Color::White),
                 )),
-            ];
-        }
+            ]
+        };
         let para = Paragraph::new(lines).block(
             Block::default()
                 .borders(Borders::ALL)
@@ -385,8 +593,9 @@ impl InputReceiver for DeleteConnectionInputReceiver {
                             self.clear();
                             let mut data = self.app.persistent_data.write().unwrap();
                             let pos = self.state.read().unwrap().get_position();
-                            data.connections.remove(pos as usize);
+                            let removed = data.connections.remove(pos as usize);
                             data.save();
+                            self.app.sessions.close_connection(&removed.id);
                             self.state.write().unwrap().refresh();
                             return true;
                         } else if c == 'n' {
@@ -411,7 +620,7 @@ impl InputReceiver for DeleteConnectionInputReceiver {
         }
         false
     }
-    fn active(&self, event: Option<Event>) -> bool {
+    fn active(&self, _event: Option<Event>) -> bool {
         self.active
     }
     fn clear(&mut self) {
@@ -443,29 +652,784 @@ impl InputReceiver for DeleteConnectionInputReceiver {
     }
 }
 
+struct TransactionInputReceiver {
+    state: Arc<RwLock<LayoutState>>,
+}
+
+impl TransactionInputReceiver {
+    pub fn new(state: Arc<RwLock<LayoutState>>) -> TransactionInputReceiver {
+        TransactionInputReceiver { state }
+    }
+}
+
+impl InputReceiver for TransactionInputReceiver {
+    fn receive_input(&mut self, event: Event) -> bool {
+        if let Event::Key(KeyEvent {
+            code, modifiers, ..
+        }) = event
+        {
+            if self.active(None) {
+                let mut state = self.state.write().unwrap();
+                match code {
+                    KeyCode::Char('c') => state.resolve_pending(Some(true)),
+                    KeyCode::Char('r') => state.resolve_pending(Some(false)),
+                    KeyCode::Esc => state.resolve_pending(None),
+                    _ => {}
+                }
+                return true;
+            }
+            if modifiers != KeyModifiers::CONTROL {
+                return false;
+            }
+            let mut state = self.state.write().unwrap();
+            match code {
+                KeyCode::Char('t') => state.toggle_transaction(),
+                KeyCode::Char('y') if state.in_transaction() => {
+                    state.end_transaction(true);
+                }
+                KeyCode::Char('z') if state.in_transaction() => {
+                    state.end_transaction(false);
+                }
+                _ => return false,
+            }
+            return true;
+        }
+        false
+    }
+    fn active(&self, _event: Option<Event>) -> bool {
+        self.state.read().unwrap().get_pending().is_some()
+    }
+    fn clear(&mut self) {
+        self.state.write().unwrap().resolve_pending(None);
+    }
+
+    fn render(&mut self, frame: &mut Frame, rect: Rect) {
+        let question = match self.state.read().unwrap().get_pending() {
+            Some(PendingAction::Quit) => "A transaction is open, end it before quitting?",
+            Some(PendingAction::Select { .. }) => {
+                "A transaction is open, end it before switching connections?"
+            }
+            _ => "End the open transaction?",
+        };
+        let lines = vec![
+            Line::from(Span::styled(question, Style::default().fg(Color::Red))),
+            Line::from(Span::styled(
+                "Press 'c' to commit, 'r' to roll back, Esc to keep it open",
+                Style::default().fg(Color::White),
+            )),
+        ];
+        let para = Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default())
+                .title("Open Transaction"),
+        );
+        frame.render_widget(para, rect);
+    }
+
+    fn layout_size(&self) -> u16 {
+        4
+    }
+}
+
+struct ParamsInputReceiver {
+    state: Arc<RwLock<LayoutState>>,
+}
+
+impl ParamsInputReceiver {
+    pub fn new(state: Arc<RwLock<LayoutState>>) -> ParamsInputReceiver {
+        ParamsInputReceiver { state }
+    }
+}
+
+impl InputReceiver for ParamsInputReceiver {
+    fn receive_input(&mut self, event: Event) -> bool {
+        if !self.active(None) {
+            return false;
+        }
+        if let Event::Key(KeyEvent { code, .. }) = event {
+            let mut state = self.state.write().unwrap();
+            match code {
+                KeyCode::Char(c) => state.edit_param(Some(c)),
+                KeyCode::Backspace => state.edit_param(None),
+                KeyCode::Enter | KeyCode::Tab | KeyCode::Down => state.next_param(),
+                KeyCode::BackTab | KeyCode::Up => state.previous_param(),
+                KeyCode::Esc => state.cancel_params(),
+                _ => {}
+            }
+        }
+        true
+    }
+    fn active(&self, _event: Option<Event>) -> bool {
+        self.state.read().unwrap().get_param_prompt().is_some()
+    }
+    fn clear(&mut self) {
+        self.state.write().unwrap().cancel_params();
+    }
+
+    fn render(&mut self, frame: &mut Frame, rect: Rect) {
+        let prompt = match self.state.read().unwrap().get_param_prompt() {
+            Some(prompt) => prompt,
+            None => return,
+        };
+        let mut lines = prompt
+            .names
+            .iter()
+            .zip(prompt.inputs.iter())
+            .enumerate()
+            .map(|(idx, (name, input))| {
+                if idx == prompt.current {
+                    Line::from(Span::styled(
+                        format!("{} = {}_", name, input),
+                        Style::default().fg(Color::Yellow),
+                    ))
+                } else {
+                    Line::from(Span::styled(
+                        format!("{} = {}", name, input),
+                        Style::default().fg(Color::White),
+                    ))
+                }
+            })
+            .collect::<Vec<_>>();
+        lines.push(Line::from(Span::styled(
+            "Enter to confirm, Esc to cancel. Cast with ::type, e.g. 42::int, 2024-01-31::date or ::null",
+            Style::default().fg(Color::Gray),
+        )));
+        let para = Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default())
+                .title("Query Parameters"),
+        );
+        frame.render_widget(para, rect);
+    }
+
+    fn layout_size(&self) -> u16 {
+        match self.state.read().unwrap().get_param_prompt() {
+            Some(prompt) => (prompt.names.len() as u16 + 3).min(15),
+            None => 3,
+        }
+    }
+}
+
+struct CellEditInputReceiver {
+    state: Arc<RwLock<LayoutState>>,
+}
+
+impl CellEditInputReceiver {
+    pub fn new(state: Arc<RwLock<LayoutState>>) -> CellEditInputReceiver {
+        CellEditInputReceiver { state }
+    }
+}
+
+impl InputReceiver for CellEditInputReceiver {
+    fn receive_input(&mut self, event: Event) -> bool {
+        if !self.active(None) {
+            return false;
+        }
+        if let Event::Key(KeyEvent { code, .. }) = event {
+            let mut state = self.state.write().unwrap();
+            match code {
+                KeyCode::Char(c) => state.type_cell(Some(c)),
+                KeyCode::Backspace => state.type_cell(None),
+                KeyCode::Enter => state.confirm_cell_edit(),
+                KeyCode::Esc => state.cancel_cell_edit(),
+                _ => {}
+            }
+        }
+        true
+    }
+    fn active(&self, _event: Option<Event>) -> bool {
+        self.state.read().unwrap().get_cell_edit().is_some()
+    }
+    fn clear(&mut self) {
+        self.state.write().unwrap().cancel_cell_edit();
+    }
+
+    fn render(&mut self, frame: &mut Frame, rect: Rect) {
+        let cell_edit = match self.state.read().unwrap().get_cell_edit() {
+            Some(cell_edit) => cell_edit,
+            None => return,
+        };
+        let lines = vec![
+            Line::from(Span::styled(
+                format!("{} = {}_", cell_edit.edit.column, cell_edit.input),
+                Style::default().fg(Color::Yellow),
+            )),
+            Line::from(Span::styled(
+                "Enter to stage the change, Esc to cancel. Cast with ::type, e.g. 42::int or ::null",
+                Style::default().fg(Color::Gray),
+            )),
+        ];
+        let para = Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default())
+                .title(format!("Edit {}", cell_edit.edit.table)),
+        );
+        frame.render_widget(para, rect);
+    }
+
+    fn layout_size(&self) -> u16 {
+        4
+    }
+}
+
+struct ReviewInputReceiver {
+    state: Arc<RwLock<LayoutState>>,
+}
+
+impl ReviewInputReceiver {
+    pub fn new(state: Arc<RwLock<LayoutState>>) -> ReviewInputReceiver {
+        ReviewInputReceiver { state }
+    }
+
+    fn get_lines(&self) -> Vec<Line<'static>> {
+        let review = match self.state.read().unwrap().get_review() {
+            Ok(review) => review,
+            Err(err) => {
+                return vec![Line::from(Span::styled(
+                    format!("{:#}", err),
+                    Style::default().fg(Color::Red),
+                ))]
+            }
+        };
+        review
+            .into_iter()
+            .map(|(marker, text)| {
+                let color = match marker {
+                    '-' => Color::Red,
+                    '+' => Color::Green,
+                    _ => Color::White,
+                };
+                Line::from(Span::styled(
+                    format!("{} {}", marker, text),
+                    Style::default().fg(color),
+                ))
+            })
+            .collect()
+    }
+}
+
+impl InputReceiver for ReviewInputReceiver {
+    fn receive_input(&mut self, event: Event) -> bool {
+        if !self.active(None) {
+            return false;
+        }
+        if let Event::Key(KeyEvent { code, .. }) = event {
+            let mut state = self.state.write().unwrap();
+            match code {
+                KeyCode::Char('c') => state.commit_staged(),
+                KeyCode::Char('d') => state.discard_staged(),
+                KeyCode::Esc => state.close_review(),
+                _ => {}
+            }
+        }
+        true
+    }
+    fn active(&self, _event: Option<Event>) -> bool {
+        self.state.read().unwrap().is_reviewing()
+    }
+    fn clear(&mut self) {
+        self.state.write().unwrap().close_review();
+    }
+
+    fn render(&mut self, frame: &mut Frame, rect: Rect) {
+        let mut lines = self.get_lines();
+        // keep the keys in sight when the changes don't fit
+        lines.truncate(rect.height.saturating_sub(3) as usize);
+        lines.push(Line::from(Span::styled(
+            "Press 'c' to commit, 'd' to discard the changes, Esc to keep editing",
+            Style::default().fg(Color::Gray),
+        )));
+        let para = Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default())
+                .title("Staged Changes"),
+        );
+        frame.render_widget(para, rect);
+    }
+
+    fn layout_size(&self) -> u16 {
+        (self.get_lines().len() as u16 + 3).min(20)
+    }
+}
+
+struct RowFormInputReceiver {
+    state: Arc<RwLock<LayoutState>>,
+}
+
+impl RowFormInputReceiver {
+    pub fn new(state: Arc<RwLock<LayoutState>>) -> RowFormInputReceiver {
+        RowFormInputReceiver { state }
+    }
+}
+
+impl InputReceiver for RowFormInputReceiver {
+    fn receive_input(&mut self, event: Event) -> bool {
+        if !self.active(None) {
+            return false;
+        }
+        if let Event::Key(KeyEvent { code, .. }) = event {
+            let mut state = self.state.write().unwrap();
+            match code {
+                KeyCode::Char(c) => state.type_row_form(Some(c)),
+                KeyCode::Backspace => state.type_row_form(None),
+                KeyCode::Enter | KeyCode::Tab | KeyCode::Down => state.next_row_form_field(),
+                KeyCode::BackTab | KeyCode::Up => state.previous_row_form_field(),
+                KeyCode::Esc => state.cancel_row_form(),
+                _ => {}
+            }
+        }
+        true
+    }
+    fn active(&self, _event: Option<Event>) -> bool {
+        self.state.read().unwrap().get_row_form().is_some()
+    }
+    fn clear(&mut self) {
+        self.state.write().unwrap().cancel_row_form();
+    }
+
+    fn render(&mut self, frame: &mut Frame, rect: Rect) {
+        let form = match self.state.read().unwrap().get_row_form() {
+            Some(form) => form,
+            None => return,
+        };
+        // scroll the fields so the current one stays in sight
+        let visible = (rect.height as usize).saturating_sub(3).max(1);
+        let first = (form.current + 1).saturating_sub(visible);
+        let mut lines = form
+            .columns
+            .iter()
+            .zip(form.inputs.iter())
+            .enumerate()
+            .skip(first)
+            .take(visible)
+            .map(|(idx, (column, input))| {
+                let mut about = vec![column.data_type.clone()];
+                if !column.nullable {
+                    about.push("not null".to_string());
+                }
+                if let Some(default) = &column.default {
+                    about.push(format!("default {}", default));
+                }
+                let current = idx == form.current;
+                Line::from(vec![
+                    Span::styled(
+                        format!(
+                            "{} = {}{}",
+                            column.name,
+                            input,
+                            if current { "_" } else { "" }
+                        ),
+                        Style::default().fg(if current { Color::Yellow } else { Color::White }),
+                    ),
+                    Span::styled(
+                        format!("  ({})", about.join(", ")),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                ])
+            })
+            .collect::<Vec<_>>();
+        lines.push(Line::from(Span::styled(
+            "Enter for the next field, inserts after the last. Empty fields get their default, ::null and ::text for NULL and ''",
+            Style::default().fg(Color::Gray),
+        )));
+        let para = Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default())
+                .title(format!("Insert into {}", form.table)),
+        );
+        frame.render_widget(para, rect);
+    }
+
+    fn layout_size(&self) -> u16 {
+        match self.state.read().unwrap().get_row_form() {
+            Some(form) => (form.columns.len() as u16 + 3).min(20),
+            None => 3,
+        }
+    }
+}
+
+// conditions listed below the prompt before they scroll
+const WHERE_CONDITION_ROWS: usize = 5;
+
+struct WhereInputReceiver {
+    state: Arc<RwLock<LayoutState>>,
+}
+
+impl WhereInputReceiver {
+    pub fn new(state: Arc<RwLock<LayoutState>>) -> WhereInputReceiver {
+        WhereInputReceiver { state }
+    }
+}
+
+impl InputReceiver for WhereInputReceiver {
+    fn receive_input(&mut self, event: Event) -> bool {
+        if !self.active(None) {
+            return false;
+        }
+        if let Event::Key(KeyEvent { code, .. }) = event {
+            let mut state = self.state.write().unwrap();
+            match code {
+                KeyCode::Char(c) => state.type_where(Some(c)),
+                KeyCode::Backspace => state.type_where(None),
+                KeyCode::Tab => state.complete_where_column(),
+                KeyCode::Up => state.move_where_condition(true),
+                KeyCode::Down => state.move_where_condition(false),
+                KeyCode::Delete => state.remove_where_condition(),
+                KeyCode::Enter => state.confirm_where(),
+                KeyCode::Esc => state.cancel_where(),
+                _ => {}
+            }
+        }
+        true
+    }
+    fn active(&self, _event: Option<Event>) -> bool {
+        self.state.read().unwrap().get_where_prompt().is_some()
+    }
+    fn clear(&mut self) {
+        self.state.write().unwrap().cancel_where();
+    }
+
+    fn render(&mut self, frame: &mut Frame, rect: Rect) {
+        let (prompt, conditions) = {
+            let state = self.state.read().unwrap();
+            match state.get_where_prompt() {
+                Some(prompt) => (prompt, state.get_conditions()),
+                None => return,
+            }
+        };
+        let mut input = vec![Span::styled(
+            format!(
+                "WHERE {}{}",
+                prompt.input,
+                if prompt.current.is_none() { "_" } else { "" }
+            ),
+            Style::default().fg(Color::Yellow),
+        )];
+        if let Some(error) = prompt.error.as_ref() {
+            input.push(Span::styled(
+                format!("  {}", error),
+                Style::default().fg(Color::Red),
+            ));
+        }
+        let completions = prompt.completions();
+        let hint = if completions.len() > 1 {
+            completions.join("  ")
+        } else if conditions.is_empty() {
+            "column op value or any condition, Tab completes a column, Enter to filter".to_string()
+        } else {
+            "Up/Down to pick a condition and Delete to remove it, Enter to add the typed one"
+                .to_string()
+        };
+        let mut lines = vec![
+            Line::from(input),
+            Line::from(Span::styled(hint, Style::default().fg(Color::Gray))),
+        ];
+        // scroll the conditions so the picked one stays in sight
+        let first = (prompt.current.unwrap_or(0) + 1).saturating_sub(WHERE_CONDITION_ROWS);
+        lines.extend(
+            conditions
+                .iter()
+                .enumerate()
+                .skip(first)
+                .take(WHERE_CONDITION_ROWS)
+                .map(|(idx, condition)| {
+                    let style = if prompt.current == Some(idx) {
+                        Style::default()
+                            .fg(Color::White)
+                            .add_modifier(Modifier::REVERSED)
+                    } else {
+                        Style::default().fg(Color::White)
+                    };
+                    Line::from(Span::styled(format!("AND {}", condition), style))
+                }),
+        );
+        let para = Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default())
+                .title(format!("Filter {}", prompt.table)),
+        );
+        frame.render_widget(para, rect);
+    }
+
+    fn layout_size(&self) -> u16 {
+        let conditions = self.state.read().unwrap().get_conditions().len();
+        conditions.min(WHERE_CONDITION_ROWS) as u16 + 4
+    }
+}
+
+struct DeleteRowsInputReceiver {
+    state: Arc<RwLock<LayoutState>>,
+}
+
+impl DeleteRowsInputReceiver {
+    pub fn new(state: Arc<RwLock<LayoutState>>) -> DeleteRowsInputReceiver {
+        DeleteRowsInputReceiver { state }
+    }
+}
+
+impl InputReceiver for DeleteRowsInputReceiver {
+    fn receive_input(&mut self, event: Event) -> bool {
+        if !self.active(None) {
+            return false;
+        }
+        if let Event::Key(KeyEvent { code, .. }) = event {
+            let mut state = self.state.write().unwrap();
+            match code {
+                KeyCode::Char('y') => state.confirm_delete(true),
+                KeyCode::Char('n') | KeyCode::Esc => state.confirm_delete(false),
+                _ => {}
+            }
+        }
+        true
+    }
+    fn active(&self, _event: Option<Event>) -> bool {
+        self.state.read().unwrap().get_pending_delete().is_some()
+    }
+    fn clear(&mut self) {
+        self.state.write().unwrap().confirm_delete(false);
+    }
+
+    fn render(&mut self, frame: &mut Frame, rect: Rect) {
+        let deletes = self
+            .state
+            .read()
+            .unwrap()
+            .get_pending_delete()
+            .unwrap_or_default();
+        let mut lines = vec![Line::from(Span::styled(
+            format!(
+                "Are you sure you want to delete {} rows? This will run:",
+                deletes.len()
+            ),
+            Style::default().fg(Color::Red),
+        ))];
+        let visible = (rect.height as usize).saturating_sub(4);
+        if deletes.len() > visible {
+            lines.extend(
+                deletes
+                    .iter()
+                    .take(visible.saturating_sub(1))
+                    .map(|delete| Line::from(delete.clone())),
+            );
+            lines.push(Line::from(Span::styled(
+                format!("... and {} more", deletes.len() + 1 - visible),
+                Style::default().fg(Color::Gray),
+            )));
+        } else {
+            lines.extend(deletes.iter().map(|delete| Line::from(delete.clone())));
+        }
+        lines.push(Line::from(Span::styled(
+            "Press 'y' to confirm, 'n' to cancel",
+            Style::default().fg(Color::White),
+        )));
+        let para = Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default())
+                .title("Delete Rows"),
+        );
+        frame.render_widget(para, rect);
+    }
+
+    fn layout_size(&self) -> u16 {
+        match self.state.read().unwrap().get_pending_delete() {
+            Some(deletes) => (deletes.len() as u16 + 4).min(20),
+            None => 4,
+        }
+    }
+}
+
+// rows of the file shown before importing it
+const IMPORT_PREVIEW_ROWS: usize = 5;
+const IMPORT_MAPPING_ROWS: usize = 8;
+
+struct ImportInputReceiver {
+    state: Arc<RwLock<LayoutState>>,
+}
+
+impl ImportInputReceiver {
+    pub fn new(state: Arc<RwLock<LayoutState>>) -> ImportInputReceiver {
+        ImportInputReceiver { state }
+    }
+
+    fn mapping_lines(plan: &ImportPlan) -> Vec<Line<'static>> {
+        let first = (plan.current + 1).saturating_sub(IMPORT_MAPPING_ROWS);
+        plan.columns
+            .iter()
+            .zip(plan.mapping.iter())
+            .enumerate()
+            .skip(first)
+            .take(IMPORT_MAPPING_ROWS)
+            .map(|(idx, (column, mapping))| {
+                let source = match mapping {
+                    Some(file_column) => plan.file.columns[*file_column].clone(),
+                    None => "(default)".to_string(),
+                };
+                let style = if idx == plan.current {
+                    Style::default().fg(Color::Yellow)
+                } else {
+                    Style::default().fg(Color::White)
+                };
+                Line::from(vec![
+                    Span::styled(format!("{} ← {}", column.name, source), style),
+                    Span::styled(
+                        format!("  ({})", column.data_type),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                ])
+            })
+            .collect()
+    }
+
+    /// The first rows as they will be written, values that don't fit their column in red.
+    fn preview(plan: &ImportPlan) -> (Vec<Row<'static>>, Row<'static>, Option<String>) {
+        let targets = plan.target_columns();
+        let sources = plan.mapping.iter().flatten().collect::<Vec<_>>();
+        let mut problem = None;
+        let rows = (0..plan.file.rows.len().min(IMPORT_PREVIEW_ROWS))
+            .map(|row| {
+                let cells = plan
+                    .coerce_row(row)
+                    .into_iter()
+                    .zip(sources.iter())
+                    .map(|(value, source)| match value {
+                        Ok(Value::Null) => Cell::from("NULL").style(
+                            Style::default()
+                                .fg(Color::DarkGray)
+                                .add_modifier(Modifier::ITALIC),
+                        ),
+                        Ok(value) => Cell::from(value.to_string()),
+                        Err(err) => {
+                            problem.get_or_insert(format!("Row {}: {:#}", row + 1, err));
+                            Cell::from(plan.file.rows[row][**source].to_string())
+                                .style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD))
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                Row::new(cells)
+            })
+            .collect::<Vec<_>>();
+        let header = Row::new(
+            targets
+                .iter()
+                .map(|column| column.name.clone())
+                .collect::<Vec<_>>(),
+        )
+        .style(Style::default().fg(Color::Yellow));
+        (rows, header, problem)
+    }
+}
+
+impl InputReceiver for ImportInputReceiver {
+    fn receive_input(&mut self, event: Event) -> bool {
+        if !self.active(None) {
+            return false;
+        }
+        if let Event::Key(KeyEvent { code, .. }) = event {
+            let mut state = self.state.write().unwrap();
+            match code {
+                KeyCode::Char('j') | KeyCode::Down => {
+                    state.update_import(|plan| plan.move_current(1))
+                }
+                KeyCode::Char('k') | KeyCode::Up => {
+                    state.update_import(|plan| plan.move_current(-1))
+                }
+                KeyCode::Char('l') | KeyCode::Right | KeyCode::Tab => {
+                    state.update_import(|plan| plan.cycle_mapping(true))
+                }
+                KeyCode::Char('h') | KeyCode::Left | KeyCode::BackTab => {
+                    state.update_import(|plan| plan.cycle_mapping(false))
+                }
+                KeyCode::Enter => state.run_import(),
+                KeyCode::Esc => state.cancel_import(),
+                _ => {}
+            }
+        }
+        true
+    }
+    fn active(&self, _event: Option<Event>) -> bool {
+        self.state.read().unwrap().get_import().is_some()
+    }
+    fn clear(&mut self) {
+        self.state.write().unwrap().cancel_import();
+    }
+
+    fn render(&mut self, frame: &mut Frame, rect: Rect) {
+        let plan = match self.state.read().unwrap().get_import() {
+            Some(plan) => plan,
+            None => return,
+        };
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default())
+            .title(format!(
+                "Import {} rows from {} into {}",
+                plan.file.rows.len(),
+                plan.path.display(),
+                plan.table
+            ));
+        let inner = block.inner(rect);
+        frame.render_widget(block, rect);
+        let mapping = Self::mapping_lines(&plan);
+        let areas = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(mapping.len() as u16),
+                Constraint::Min(1),
+                Constraint::Length(1),
+            ])
+            .split(inner);
+        frame.render_widget(Paragraph::new(mapping), areas[0]);
+        let (rows, header, problem) = Self::preview(&plan);
+        let count = plan.mapping.iter().flatten().count().max(1);
+        let widths = vec![Constraint::Percentage((100 / count) as u16); count];
+        frame.render_widget(Table::new(rows).header(header).widths(&widths), areas[1]);
+        let help = match problem {
+            Some(problem) => Span::styled(problem, Style::default().fg(Color::Red)),
+            None => Span::styled(
+                "j/k to pick a column, h/l to change the file column it takes, Enter to import, Esc to cancel",
+                Style::default().fg(Color::Gray),
+            ),
+        };
+        frame.render_widget(Paragraph::new(Line::from(help)), areas[2]);
+    }
+
+    fn layout_size(&self) -> u16 {
+        match self.state.read().unwrap().get_import() {
+            Some(plan) => {
+                let mapping = plan.columns.len().min(IMPORT_MAPPING_ROWS);
+                let preview = plan.file.rows.len().min(IMPORT_PREVIEW_ROWS) + 1;
+                (mapping + preview + 3) as u16
+            }
+            None => 3,
+        }
+    }
+}
+
 struct EditQueryInputReceiver<'a> {
     active: bool,
-    app: Arc<Application>,
     state: Arc<RwLock<LayoutState>>,
     textarea: TextArea<'a>,
 }
 
 impl<'a> EditQueryInputReceiver<'a> {
-    pub fn new(
-        app: Arc<Application>,
-        state: Arc<RwLock<LayoutState>>,
-    ) -> EditQueryInputReceiver<'a> {
+    pub fn new(state: Arc<RwLock<LayoutState>>) -> EditQueryInputReceiver<'a> {
         let ta = TextArea::default();
         EditQueryInputReceiver {
             active: false,
-            app,
             state,
             textarea: ta,
         }
     }
 }
 
-impl<'a> InputReceiver for EditQueryInputReceiver<'a> {
+impl InputReceiver for EditQueryInputReceiver<'_> {
     fn receive_input(&mut self, event: Event) -> bool {
         if self.active {
             if let Event::Key(KeyEvent {
@@ -500,7 +1464,7 @@ impl<'a> InputReceiver for EditQueryInputReceiver<'a> {
                         let aw = state.get_active_window();
                         if aw.id() == types::WindowTypeID::QUERY {
                             self.active = true;
-                            for line in state.get_current_query().split("\n") {
+                            for line in state.get_current_query().split('\n') {
                                 self.textarea.insert_str(line);
                                 self.textarea.insert_newline();
                             }
@@ -525,7 +1489,7 @@ impl<'a> InputReceiver for EditQueryInputReceiver<'a> {
         }
         false
     }
-    fn active(&self, event: Option<Event>) -> bool {
+    fn active(&self, _event: Option<Event>) -> bool {
         self.active
     }
     fn clear(&mut self) {
@@ -546,41 +1510,30 @@ impl<'a> InputReceiver for EditQueryInputReceiver<'a> {
     }
 
     fn layout_size(&self) -> u16 {
-        cmp::max(
-            cmp::min(
-                self.state
-                    .read()
-                    .unwrap()
-                    .get_current_query()
-                    .split("\n")
-                    .collect::<Vec<_>>()
-                    .len() as u16
-                    + 1,
-                30,
-            ),
-            5,
-        )
+        (self
+            .state
+            .read()
+            .unwrap()
+            .get_current_query()
+            .split('\n')
+            .count() as u16
+            + 1)
+        .clamp(5, 30)
     }
 }
 
 struct ViewQueryInputReceiver {
-    active: bool,
-    app: Arc<Application>,
     state: Arc<RwLock<LayoutState>>,
 }
 
 impl ViewQueryInputReceiver {
-    pub fn new(app: Arc<Application>, state: Arc<RwLock<LayoutState>>) -> ViewQueryInputReceiver {
-        ViewQueryInputReceiver {
-            active: false,
-            app,
-            state,
-        }
+    pub fn new(state: Arc<RwLock<LayoutState>>) -> ViewQueryInputReceiver {
+        ViewQueryInputReceiver { state }
     }
 }
 
-impl<'a> InputReceiver for ViewQueryInputReceiver {
-    fn receive_input(&mut self, event: Event) -> bool {
+impl InputReceiver for ViewQueryInputReceiver {
+    fn receive_input(&mut self, _event: Event) -> bool {
         false
     }
     fn active(&self, event: Option<Event>) -> bool {
@@ -594,6 +1547,9 @@ impl<'a> InputReceiver for ViewQueryInputReceiver {
         }
         false
     }
+    fn passive(&self) -> bool {
+        true
+    }
     fn clear(&mut self) {
         //
     }
@@ -602,8 +1558,7 @@ impl<'a> InputReceiver for ViewQueryInputReceiver {
         let state = self.state.read().unwrap();
         let text = state.get_current_query();
         let para = Paragraph::new(
-            text.split("\n")
-                .into_iter()
+            text.split('\n')
                 .map(|s| Line::from(Span::styled(s, Style::default().fg(Color::Gray))))
                 .collect::<Vec<_>>(),
         )
@@ -622,8 +1577,6 @@ impl<'a> InputReceiver for ViewQueryInputReceiver {
 }
 
 pub struct InputBar {
-    app: Arc<Application>,
-    state: Arc<RwLock<LayoutState>>,
     input_receivers: Vec<Box<dyn InputReceiver>>,
 }
 
@@ -631,10 +1584,16 @@ impl InputBar {
     pub fn new(app: Arc<Application>, state: Arc<RwLock<LayoutState>>) -> InputBar {
         InputBar {
             input_receivers: vec![
-                Box::new(CommandInputReceiver::new(
-                    Arc::clone(&app),
-                    Arc::clone(&state),
-                )),
+                Box::new(TransactionInputReceiver::new(Arc::clone(&state))),
+                Box::new(ParamsInputReceiver::new(Arc::clone(&state))),
+                Box::new(CellEditInputReceiver::new(Arc::clone(&state))),
+                Box::new(ReviewInputReceiver::new(Arc::clone(&state))),
+                Box::new(RowFormInputReceiver::new(Arc::clone(&state))),
+                Box::new(WhereInputReceiver::new(Arc::clone(&state))),
+                Box::new(DeleteRowsInputReceiver::new(Arc::clone(&state))),
+                Box::new(ImportInputReceiver::new(Arc::clone(&state))),
+                Box::new(CommandInputReceiver::new(Arc::clone(&state))),
+                Box::new(FilterInputReceiver::new(Arc::clone(&state))),
                 Box::new(ConnectionInputReceiver::new(
                     Arc::clone(&app),
                     Arc::clone(&state),
@@ -643,17 +1602,9 @@ impl InputBar {
                     Arc::clone(&app),
                     Arc::clone(&state),
                 )),
-                Box::new(EditQueryInputReceiver::new(
-                    Arc::clone(&app),
-                    Arc::clone(&state),
-                )),
-                Box::new(ViewQueryInputReceiver::new(
-                    Arc::clone(&app),
-                    Arc::clone(&state),
-                )),
+                Box::new(EditQueryInputReceiver::new(Arc::clone(&state))),
+                Box::new(ViewQueryInputReceiver::new(Arc::clone(&state))),
             ],
-            app,
-            state,
         }
     }
 
@@ -673,7 +1624,7 @@ impl InputBar {
 
     pub fn active(&self) -> bool {
         for receiver in self.input_receivers.iter() {
-            if receiver.active(None) {
+            if receiver.active(None) && !receiver.passive() {
                 return true;
             }
         }
@@ -10,7 +10,6 @@ use super::{input::InputBar, main::MainArea, status::StatusArea, top::TopArea};
 use crate::ui::state::LayoutState;
 
 pub struct LayoutController {
-    app: Arc<Application>,
     state: Arc<RwLock<LayoutState>>,
     input_bar: InputBar,
     main_area: MainArea,
@@ -24,10 +23,9 @@ impl LayoutController {
         let state = Arc::new(RwLock::new(LayoutState::new(Arc::clone(&app))));
         LayoutController {
             input_bar: InputBar::new(Arc::clone(&app), Arc::clone(&state)),
-            main_area: MainArea::new(Arc::clone(&app), Arc::clone(&state)),
-            top_area: TopArea::new(Arc::clone(&app), Arc::clone(&state)),
-            status_area: StatusArea::new(Arc::clone(&app), Arc::clone(&state)),
-            app,
+            main_area: MainArea::new(Arc::clone(&state)),
+            top_area: TopArea::new(Arc::clone(&state)),
+            status_area: StatusArea::new(Arc::clone(&state)),
             state,
         }
     }
@@ -45,12 +43,19 @@ impl LayoutController {
                 ..
             })) = event_result
             {
-                // Handle Control+C here
-                return Ok(false);
+                // Control+C stops a running query or import, with nothing running it quits
+                let mut state = self.state.write().unwrap();
+                if !state.cancel() && state.request_quit() {
+                    return Ok(false);
+                }
+                event_result = None;
             }
         }
         {
             let mut state = self.state.write().unwrap();
+            if state.should_quit() {
+                return Ok(false);
+            }
             if event_result.is_none() && !state.is_dirty() {
                 return Ok(true);
             }
@@ -67,7 +72,7 @@ impl LayoutController {
             let areas = Layout::default()
                 .direction(Direction::Vertical)
                 .constraints([
-                    Constraint::Length(5),
+                    Constraint::Length(self.top_area.layout_size()),
                     Constraint::Length(self.input_bar.layout_size()),
                     Constraint::Min(1),
                     Constraint::Length(self.status_area.layout_size()),
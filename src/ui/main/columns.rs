@@ -0,0 +1,254 @@
+use crate::connectiontypes::base::{format_size, Value};
+use crate::ui::state::{ColumnLayout, WindowDataRow};
+use crate::ui::types::WindowTypeID;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+// rows measured to size the columns, enough to get the shape of the data
+const SAMPLE_ROWS: usize = 200;
+// narrowest a column is drawn, also when it is resized or squeezed in at the right edge
+pub const MIN_WIDTH: usize = 3;
+
+/// Which columns of a window hold byte counts, kept as numbers to sort them by amount.
+pub fn size_columns(window: WindowTypeID, columns: &[String]) -> Vec<bool> {
+    let sizes = [WindowTypeID::TABLES, WindowTypeID::INDEXES].contains(&window);
+    columns.iter().map(|name| sizes && name == "Size").collect()
+}
+
+/// The text a value is shown as in the grid, `size` for the byte counts of a size column.
+pub fn cell_text(value: &Value, size: bool) -> String {
+    match value {
+        Value::Null => "NULL".to_string(),
+        Value::Int(bytes) if size => format_size(*bytes as u64),
+        value => value.to_string(),
+    }
+}
+
+/// The width of the text on screen, only the first line of multi-line text is shown.
+pub fn text_width(text: &str) -> usize {
+    text.lines().next().unwrap_or_default().width()
+}
+
+/// Cuts the text to `width` cells of the terminal, an ellipsis marks what was cut off,
+/// including the lines after the first.
+pub fn truncate(text: &str, width: usize) -> String {
+    let mut lines = text.lines();
+    let first = lines.next().unwrap_or_default();
+    let more = lines.next().is_some();
+    if first.width() <= width && !more {
+        return first.to_string();
+    }
+    let mut cut = String::new();
+    let mut used = 0;
+    for c in first.chars() {
+        let char_width = c.width().unwrap_or(0);
+        if used + char_width + 1 > width {
+            break;
+        }
+        cut.push(c);
+        used += char_width;
+    }
+    if width > 0 {
+        cut.push('…');
+    }
+    cut
+}
+
+/// The width each column needs for its name and the values of the first rows, up to
+/// `max_width`, changed by the resizing of the layout. The `sorted` column has room
+/// for the sort indicator after its name.
+pub fn column_widths(
+    columns: &[String],
+    rows: &[WindowDataRow],
+    sizes: &[bool],
+    layout: &ColumnLayout,
+    max_width: usize,
+    sorted: Option<usize>,
+) -> Vec<usize> {
+    columns
+        .iter()
+        .enumerate()
+        .map(|(idx, name)| {
+            let content = rows
+                .iter()
+                .take(SAMPLE_ROWS)
+                .filter_map(|row| row.data.get(idx))
+                .map(|value| text_width(&cell_text(value, sizes[idx])))
+                .max()
+                .unwrap_or(0);
+            let indicator = if sorted == Some(idx) { 2 } else { 0 };
+            let width = (name.width() + indicator)
+                .max(content)
+                .min(max_width.max(MIN_WIDTH));
+            let resized = width as i32 + layout.resized.get(name).cloned().unwrap_or(0);
+            resized.max(MIN_WIDTH as i32) as usize
+        })
+        .collect()
+}
+
+/// The columns that fit in `available` cells as pairs of index and width: the frozen ones,
+/// then the others from the first scrolled to. `offset` counts the columns scrolled past
+/// and is moved to keep the `cursor` column in view. The last column shown takes the rest
+/// of the room when there is nothing right of it.
+pub fn visible_columns(
+    columns: &[String],
+    widths: &[usize],
+    layout: &ColumnLayout,
+    cursor: usize,
+    offset: &mut usize,
+    available: usize,
+) -> Vec<(usize, usize)> {
+    let shown = (0..columns.len())
+        .filter(|idx| !layout.hidden.contains(&columns[*idx]))
+        .collect::<Vec<_>>();
+    let (frozen, scrolled): (Vec<usize>, Vec<usize>) =
+        shown.iter().partition(|idx| **idx < layout.frozen);
+    // every column is followed by a space
+    let mut room = available.saturating_sub(frozen.iter().map(|idx| widths[*idx] + 1).sum());
+    *offset = (*offset).min(scrolled.len().saturating_sub(1));
+    if let Some(position) = scrolled.iter().position(|idx| *idx == cursor) {
+        if position < *offset {
+            *offset = position;
+        }
+        while *offset < position
+            && scrolled[*offset..=position]
+                .iter()
+                .map(|idx| widths[*idx] + 1)
+                .sum::<usize>()
+                > room
+        {
+            *offset += 1;
+        }
+    }
+    let mut visible = frozen
+        .iter()
+        .map(|idx| (*idx, widths[*idx]))
+        .collect::<Vec<_>>();
+    for (position, idx) in scrolled.iter().enumerate().skip(*offset) {
+        let last = position + 1 == scrolled.len();
+        let width = widths[*idx];
+        if last || width + 1 > room {
+            // squeezed in at the right edge, or stretched to it
+            if room >= MIN_WIDTH || visible.is_empty() {
+                visible.push((*idx, room));
+            }
+            break;
+        }
+        visible.push((*idx, width));
+        room -= width + 1;
+    }
+    visible
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn truncated_to_terminal_cells() {
+        for (text, width, cut) in [
+            ("abc", 3, "abc"),
+            ("abcd", 3, "ab…"),
+            ("abcd", 1, "…"),
+            ("abcd", 0, ""),
+            ("", 0, ""),
+            ("日本語", 6, "日本語"),
+            ("日本語", 5, "日本…"),
+            ("日本語", 4, "日…"),
+            ("日本語", 2, "…"),
+            // the accent takes no cell of its own
+            ("e\u{301}x", 2, "e\u{301}x"),
+            ("e\u{301}xy", 2, "e\u{301}…"),
+            ("ab\ncd", 5, "ab…"),
+            ("ab\r\n", 5, "ab"),
+        ] {
+            assert_eq!(truncate(text, width), cut, "{:?} in {}", text, width);
+        }
+    }
+
+    #[test]
+    fn widths_fit_the_content() {
+        let columns = names(&["id", "name", "Size"]);
+        let rows = vec![
+            WindowDataRow::new(
+                "0".to_string(),
+                vec![
+                    Value::Int(1),
+                    "日本語テキスト".to_string().into(),
+                    Value::Int(2048),
+                ],
+            ),
+            WindowDataRow::new(
+                "1".to_string(),
+                vec![
+                    Value::Int(100),
+                    "a\nlong second line".to_string().into(),
+                    Value::Null,
+                ],
+            ),
+        ];
+        let sizes = [false, false, true];
+        let mut layout = ColumnLayout::default();
+        // the sort indicator widens the name of the sorted column
+        assert_eq!(
+            column_widths(&columns, &rows, &sizes, &layout, 10, Some(0)),
+            vec![4, 10, 6]
+        );
+        assert_eq!(
+            column_widths(&columns, &rows, &sizes, &layout, 1, None),
+            vec![MIN_WIDTH; 3]
+        );
+        layout.resized.insert("name".to_string(), 2);
+        layout.resized.insert("Size".to_string(), -10);
+        assert_eq!(
+            column_widths(&columns, &rows, &sizes, &layout, 10, None),
+            vec![3, 12, MIN_WIDTH]
+        );
+    }
+
+    #[test]
+    fn columns_in_view() {
+        let columns = names(&["a", "b", "c", "d", "e"]);
+        let widths = [5; 5];
+        let layout = |frozen: usize, hidden: &[&str]| ColumnLayout {
+            frozen,
+            hidden: hidden.iter().map(|name| name.to_string()).collect(),
+            ..ColumnLayout::default()
+        };
+        // (frozen, hidden, cursor, offset, available) -> (visible, offset)
+        for ((frozen, hidden, cursor, offset, available), (visible, moved)) in [
+            ((0, vec![], 0, 0, 20), (vec![(0, 5), (1, 5), (2, 5)], 0)),
+            // scrolled right to the cursor, the last column takes the rest
+            ((0, vec![], 4, 0, 20), (vec![(2, 5), (3, 5), (4, 8)], 2)),
+            ((1, vec![], 4, 0, 20), (vec![(0, 5), (3, 5), (4, 8)], 2)),
+            // scrolled back left to the cursor
+            ((0, vec![], 1, 3, 20), (vec![(1, 5), (2, 5), (3, 5)], 1)),
+            (
+                (0, vec!["b"], 0, 0, 30),
+                (vec![(0, 5), (2, 5), (3, 5), (4, 12)], 0),
+            ),
+            // a column squeezed in at the right edge
+            ((0, vec![], 0, 0, 15), (vec![(0, 5), (1, 5), (2, 3)], 0)),
+            ((0, vec![], 0, 0, 4), (vec![(0, 4)], 0)),
+            ((0, vec![], 4, 10, 20), (vec![(4, 20)], 4)),
+            // frozen columns stay when everything else is scrolled past
+            ((2, vec![], 4, 0, 14), (vec![(0, 5), (1, 5)], 2)),
+        ] {
+            let mut offset = offset;
+            let layout = layout(frozen, &hidden);
+            assert_eq!(
+                visible_columns(&columns, &widths, &layout, cursor, &mut offset, available),
+                visible,
+                "frozen {} hidden {:?} cursor {} in {}",
+                frozen,
+                hidden,
+                cursor,
+                available
+            );
+            assert_eq!(offset, moved);
+        }
+    }
+}
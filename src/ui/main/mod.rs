@@ -1,26 +1,129 @@
-use crate::app::Application;
+use crate::connectiontypes::base::{command_tag, Value};
 use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use ratatui::layout::{Direction, Layout};
 use ratatui::prelude::Constraint;
-use ratatui::style::{Color, Style};
-use ratatui::widgets::{Block, Borders, Cell, Row, Table, TableState};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Cell, Row, Table, TableState, Tabs};
 use ratatui::{layout::Rect, Frame};
+use std::collections::HashMap;
 use std::{sync::Arc, sync::RwLock};
 
+use crate::ui::filter::Filter;
+use crate::ui::highlight::highlight_sql;
 use crate::ui::state::LayoutState;
 
 use super::types;
 
+pub mod columns;
+mod record;
+
+use columns::{cell_text, column_widths, size_columns, truncate, visible_columns};
+use record::{record_lines, render_record, RecordLine};
+
 pub struct MainArea {
-    app: Arc<Application>,
     state: Arc<RwLock<LayoutState>>,
+    // the columns each window is scrolled past
+    offsets: HashMap<types::WindowTypeID, usize>,
+}
+
+fn value_cell(
+    value: &Value,
+    size: bool,
+    style: Style,
+    width: usize,
+    filter: Option<&Filter>,
+) -> Cell<'static> {
+    let text = truncate(&cell_text(value, size), width);
+    let style = match value {
+        Value::Null => style.fg(Color::DarkGray).add_modifier(Modifier::ITALIC),
+        _ => style,
+    };
+    match filter.and_then(|filter| filter.find(&text)) {
+        Some(ranges) => Cell::from(highlight_matches(&text, &ranges)).style(style),
+        None => Cell::from(text).style(style),
+    }
+}
+
+/// Marks the parts of the text the filter matched.
+fn highlight_matches(text: &str, ranges: &[(usize, usize)]) -> Line<'static> {
+    let mut spans = vec![];
+    let mut end_of_last = 0;
+    for (start, end) in ranges {
+        if *start > end_of_last {
+            spans.push(Span::raw(text[end_of_last..*start].to_string()));
+        }
+        spans.push(Span::styled(
+            text[*start..*end].to_string(),
+            Style::default().fg(Color::Black).bg(Color::Yellow),
+        ));
+        end_of_last = *end;
+    }
+    if end_of_last < text.len() {
+        spans.push(Span::raw(text[end_of_last..].to_string()));
+    }
+    Line::from(spans)
 }
 
 impl MainArea {
-    pub fn new(app: Arc<Application>, state: Arc<RwLock<LayoutState>>) -> MainArea {
-        MainArea { app, state }
+    pub fn new(state: Arc<RwLock<LayoutState>>) -> MainArea {
+        MainArea {
+            state,
+            offsets: HashMap::new(),
+        }
+    }
+
+    /// The lines of the record view of the row under the cursor, with the line of the cursor.
+    fn get_record_lines(&self) -> Option<(Vec<String>, Vec<RecordLine>, usize)> {
+        let state = self.state.read().unwrap();
+        let record = state.get_record()?;
+        let data = state.get_window_data();
+        let row = usize::try_from(state.get_position())
+            .ok()
+            .and_then(|position| data.rows.get(position))?;
+        let sizes = size_columns(state.get_active_window().id(), &data.columns);
+        let lines = record_lines(&data.columns, &sizes, &row.data, &record.folded);
+        let cursor = record.line.min(lines.len().saturating_sub(1));
+        Some((data.columns, lines, cursor))
+    }
+
+    /// Keys of the record view, the rest work on the row as they do in the grid.
+    fn handle_record_event(&mut self, code: KeyCode) -> bool {
+        let (_, lines, cursor) = match self.get_record_lines() {
+            Some(record) => record,
+            None => return false,
+        };
+        let mut state = self.state.write().unwrap();
+        match code {
+            KeyCode::Esc | KeyCode::Char('v') => state.close_record(),
+            KeyCode::Down | KeyCode::Up => {
+                let line = if code == KeyCode::Down {
+                    (cursor + 1).min(lines.len().saturating_sub(1))
+                } else {
+                    cursor.saturating_sub(1)
+                };
+                state.set_record_line(line);
+                // the cell cursor follows, so copying and editing act on the column of the line
+                if let Some(line) = lines.get(line) {
+                    state.set_column_position(line.column);
+                }
+            }
+            KeyCode::Enter | KeyCode::Char(' ') => {
+                if let Some(fold) = lines.get(cursor).and_then(|line| line.fold.clone()) {
+                    state.toggle_record_fold(fold);
+                }
+            }
+            _ => return false,
+        }
+        true
     }
 
     fn handle_event(&mut self, event: Event) {
+        if let Event::Key(KeyEvent { code, .. }) = event {
+            if self.handle_record_event(code) {
+                return;
+            }
+        }
         if let Event::Key(KeyEvent {
             code: KeyCode::Char('j'),
             modifiers: KeyModifiers::CONTROL,
@@ -28,10 +131,7 @@ impl MainArea {
         }) = event
         {
             let num_rows = self.state.read().unwrap().get_number_of_rows();
-            self.state
-                .write()
-                .unwrap()
-                .set_position(num_rows as i32 - 1);
+            self.state.write().unwrap().set_position(num_rows - 1);
             log::debug!("Setting position to {}", num_rows - 1);
             return;
         }
@@ -45,11 +145,102 @@ impl MainArea {
             return;
         }
 
-        if let Event::Key(KeyEvent { code, .. }) = event {
+        let window = self.state.read().unwrap().get_active_window().id();
+        let plan = window == types::WindowTypeID::PLAN;
+        let query = window == types::WindowTypeID::QUERY;
+        if let Event::Key(KeyEvent {
+            code, modifiers, ..
+        }) = event
+        {
+            // Control+y commits a transaction instead
+            let control = modifiers == KeyModifiers::CONTROL;
             match code {
+                KeyCode::Char('d')
+                    if [
+                        types::WindowTypeID::TABLES,
+                        types::WindowTypeID::INDEXES,
+                        types::WindowTypeID::COLUMNS,
+                    ]
+                    .contains(&window) =>
+                {
+                    self.state.write().unwrap().show_ddl();
+                }
+                KeyCode::Char('y') if window == types::WindowTypeID::DEFINITION => {
+                    self.state.write().unwrap().yank_definition();
+                }
+                KeyCode::Char('y') if !control => {
+                    self.state.write().unwrap().yank(false);
+                }
+                KeyCode::Char('Y') => {
+                    self.state.write().unwrap().yank(true);
+                }
+                KeyCode::Char('s') if !control => {
+                    self.state.write().unwrap().sort_column();
+                }
+                KeyCode::Char(' ') | KeyCode::Enter if plan => {
+                    self.state.write().unwrap().toggle_plan_node();
+                }
+                KeyCode::Char('a') if plan => {
+                    self.state.write().unwrap().toggle_plan_analyze();
+                }
+                KeyCode::Char('h') | KeyCode::Left => {
+                    self.state.write().unwrap().move_column(-1);
+                }
+                KeyCode::Char('l') | KeyCode::Right => {
+                    self.state.write().unwrap().move_column(1);
+                }
+                KeyCode::Char('<') => {
+                    self.state.write().unwrap().resize_column(-4);
+                }
+                KeyCode::Char('>') => {
+                    self.state.write().unwrap().resize_column(4);
+                }
+                KeyCode::Char('-') => {
+                    self.state.write().unwrap().hide_column();
+                }
+                KeyCode::Char('=') => {
+                    self.state.write().unwrap().reset_columns();
+                }
+                KeyCode::Char('z') if !control => {
+                    self.state.write().unwrap().toggle_freeze_columns();
+                }
+                KeyCode::Char('f') if query => {
+                    self.state.write().unwrap().follow_foreign_key(false);
+                }
+                KeyCode::Char('F') if query => {
+                    self.state.write().unwrap().follow_foreign_key(true);
+                }
+                KeyCode::Backspace if query => {
+                    self.state.write().unwrap().go_back();
+                }
+                KeyCode::Char('c') if query => {
+                    self.state.write().unwrap().edit_cell();
+                }
+                KeyCode::Char('w') if query => {
+                    self.state.write().unwrap().review_staged();
+                }
+                KeyCode::Char('i') if query => {
+                    self.state.write().unwrap().insert_row(false);
+                }
+                KeyCode::Char('W') if query => {
+                    self.state.write().unwrap().open_where_prompt();
+                }
+                KeyCode::Char('I') if query => {
+                    self.state.write().unwrap().insert_row(true);
+                }
+                KeyCode::Char('d') if query => {
+                    self.state.write().unwrap().delete_rows();
+                }
+                KeyCode::Char('v') | KeyCode::Enter if query => {
+                    self.state.write().unwrap().open_record();
+                }
                 KeyCode::Char('j') | KeyCode::Down => {
                     let mut state = self.state.write().unwrap();
                     let current = state.get_position();
+                    if current >= state.get_number_of_rows() - 1 {
+                        // scrolling past the last row loads the next page
+                        state.fetch_next_page();
+                    }
                     if current == -1 {
                         state.set_position(0);
                     } else {
@@ -63,9 +254,15 @@ impl MainArea {
                         state.set_position(current - 1);
                     }
                 }
+                KeyCode::Tab => {
+                    self.state.write().unwrap().switch_tab(true);
+                }
+                KeyCode::BackTab => {
+                    self.state.write().unwrap().switch_tab(false);
+                }
                 KeyCode::Char('r') => {
                     let mut state = self.state.write().unwrap();
-                    state.refresh();
+                    state.reload();
                 }
                 KeyCode::Char(' ') => {
                     // spacebar pressed
@@ -79,45 +276,178 @@ impl MainArea {
                     self.state.write().unwrap().select_for_next_window();
                 }
                 _ => {}
-                _ => {}
             }
         }
     }
 
+    /// One tab per statement when the query window ran a script, failed statements in red.
+    fn get_tabs(&self) -> Option<Tabs<'_>> {
+        let state = self.state.read().unwrap();
+        let inner = state.inner.read().unwrap();
+        if inner.active_window != types::WindowTypeID::QUERY || inner.query_tabs.len() < 2 {
+            return None;
+        }
+        let titles = inner
+            .query_tabs
+            .iter()
+            .enumerate()
+            .map(|(idx, tab)| {
+                let style = if tab.error.is_some() {
+                    Style::default().fg(Color::Red)
+                } else {
+                    Style::default()
+                };
+                // lookups of a followed key are told apart by their table
+                let title = match tab.table.as_ref() {
+                    Some(table) => table.clone(),
+                    None => command_tag(&tab.statement),
+                };
+                Line::styled(format!("{} {}", idx + 1, title), style)
+            })
+            .collect::<Vec<_>>();
+        Some(
+            Tabs::new(titles).select(inner.active_tab).highlight_style(
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
+        )
+    }
+
     pub fn render(&mut self, frame: &mut Frame, rect: Rect, event: Option<Event>) {
         if let Some(event) = event.clone() {
             self.handle_event(event);
         }
+        let mut rect = rect;
+        if let Some(tabs) = self.get_tabs() {
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(1), Constraint::Min(0)])
+                .split(rect);
+            frame.render_widget(tabs, chunks[0]);
+            rect = chunks[1];
+        }
+        if let Some((columns, lines, cursor)) = self.get_record_lines() {
+            let state = self.state.read().unwrap();
+            let wd = state.get_window_data();
+            let title = format!(
+                "{} · row {} of {}{}",
+                state.get_active_window().title(),
+                state.get_position() + 1,
+                wd.rows.len(),
+                if wd.next_page.is_some() { "+" } else { "" }
+            );
+            render_record(frame, rect, title, &columns, &lines, cursor);
+            return;
+        }
         let wd = self.state.read().unwrap().get_window_data();
         let state = self.state.read().unwrap();
 
         let mut selected_rows = Vec::new();
         let window = state.get_active_window();
+        let definition = window.id() == types::WindowTypeID::DEFINITION;
+        let current = state.get_position();
+        // the cell cursor is only shown where there are cells to act on
+        let (cursor, staged) = if window.id() == types::WindowTypeID::QUERY {
+            (Some(state.get_column_position()), state.get_staged_cells())
+        } else {
+            (None, HashMap::new())
+        };
+        let hotspots = if window.id() == types::WindowTypeID::PLAN {
+            state.get_plan_hotspots()
+        } else {
+            vec![]
+        };
         if window.selection_type() == types::ItemSelectionType::MULTI {
             selected_rows = state
                 .inner
                 .read()
                 .unwrap()
                 .get_selection(window.id())
-                .unwrap_or(Vec::new());
+                .unwrap_or_default();
         }
 
+        // lay the columns out to their content, scrolled to keep the cursor column in view
+        let layout = state.get_column_layout();
+        let max_width = state
+            .inner
+            .read()
+            .unwrap()
+            .app
+            .get_settings()
+            .max_column_width;
+        let sort = state.get_sort();
+        let filter = state.get_filter();
+        let sorted = sort
+            .as_ref()
+            .and_then(|sort| wd.columns.iter().position(|c| *c == sort.column));
+        let sizes = size_columns(window.id(), &wd.columns);
+        let widths = column_widths(&wd.columns, &wd.rows, &sizes, &layout, max_width, sorted);
+        let column_position = state.get_column_position();
+        let offset = self.offsets.entry(window.id()).or_insert(0);
+        let visible = visible_columns(
+            &wd.columns,
+            &widths,
+            &layout,
+            column_position,
+            offset,
+            rect.width.saturating_sub(2) as usize,
+        );
+
         let rows: Vec<Row> = wd
             .rows
             .iter()
-            .map(|dr| {
-                if selected_rows.contains(&dr.id.clone()) {
-                    return Row::new(
-                        dr.data
-                            .iter()
-                            .map(|d| Cell::from(d.clone()).style(Style::default().fg(Color::Green)))
-                            .collect::<Vec<Cell>>(),
-                    );
-                }
+            .enumerate()
+            .map(|(row_idx, dr)| {
+                let style = if selected_rows.contains(&dr.id) {
+                    Style::default().fg(Color::Green)
+                } else if hotspots.contains(&dr.id) {
+                    Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
                 Row::new(
-                    dr.data
+                    visible
                         .iter()
-                        .map(|d| Cell::from(d.clone()))
+                        .filter_map(|(column_idx, width)| {
+                            dr.data.get(*column_idx).map(|d| (*column_idx, *width, d))
+                        })
+                        .map(|(column_idx, width, d)| match d {
+                            Value::Text(line) if definition => Cell::from(highlight_sql(line)),
+                            // changed values show until they are written
+                            _ if staged.contains_key(&(row_idx, column_idx)) => {
+                                let value = &staged[&(row_idx, column_idx)];
+                                let cell = value_cell(
+                                    value,
+                                    sizes[column_idx],
+                                    style,
+                                    width,
+                                    filter.as_ref(),
+                                );
+                                if row_idx as i32 == current && cursor == Some(column_idx) {
+                                    cell.style(
+                                        Style::default()
+                                            .fg(Color::Yellow)
+                                            .add_modifier(Modifier::REVERSED | Modifier::ITALIC),
+                                    )
+                                } else {
+                                    cell.style(
+                                        Style::default()
+                                            .fg(Color::Yellow)
+                                            .add_modifier(Modifier::ITALIC),
+                                    )
+                                }
+                            }
+                            _ if row_idx as i32 == current && cursor == Some(column_idx) => {
+                                value_cell(d, sizes[column_idx], style, width, filter.as_ref())
+                                    .style(
+                                        Style::default()
+                                            .fg(Color::Yellow)
+                                            .add_modifier(Modifier::REVERSED | Modifier::BOLD),
+                                    )
+                            }
+                            _ => value_cell(d, sizes[column_idx], style, width, filter.as_ref()),
+                        })
                         .collect::<Vec<Cell>>(),
                 )
             })
@@ -125,25 +455,91 @@ impl MainArea {
 
         let mut table_state = TableState::default();
 
-        let current = state.get_position();
         if current >= 0 && current < rows.len() as i32 {
-            table_state.select(Some(current.clone() as usize));
+            table_state.select(Some(current as usize));
         }
 
-        let mut column_size = 100;
-        if wd.columns.len() > 0 {
-            column_size = (100 / wd.columns.len()) as u16;
-        }
-        let widths = wd
-            .columns
+        let header = Row::new(
+            visible
+                .iter()
+                .map(|(column_idx, width)| {
+                    let name = match sort.as_ref().filter(|_| sorted == Some(*column_idx)) {
+                        Some(sort) => format!(
+                            "{} {}",
+                            wd.columns[*column_idx],
+                            if sort.descending { "▼" } else { "▲" }
+                        ),
+                        None => wd.columns[*column_idx].clone(),
+                    };
+                    let name = truncate(&name, *width);
+                    // without a cell cursor the header shows the column h/l and resizing act on
+                    if cursor.is_none() && wd.columns.len() > 1 && *column_idx == column_position {
+                        Cell::from(name).style(Style::default().add_modifier(Modifier::REVERSED))
+                    } else if *column_idx < layout.frozen {
+                        Cell::from(name).style(Style::default().add_modifier(Modifier::UNDERLINED))
+                    } else {
+                        Cell::from(name)
+                    }
+                })
+                .collect::<Vec<_>>(),
+        )
+        .style(Style::default().fg(Color::Yellow));
+        let widths = visible
             .iter()
-            .map(|_| Constraint::Percentage(column_size))
+            .map(|(_, width)| Constraint::Length(*width as u16))
             .collect::<Vec<_>>();
+        let more = if wd.next_page.is_some() { "+" } else { "" };
+        // a filtered window counts the rows that match of those loaded
+        let count = match filter.as_ref() {
+            Some(_) => format!("{}/{}{}", wd.rows.len(), state.get_total_rows(), more),
+            None => format!("{}{}", wd.rows.len(), more),
+        };
+        let mut title = format!("{} ({} rows)", window.title(), count);
+        if window.id() == types::WindowTypeID::PLAN {
+            let analyze = state.inner.read().unwrap().plan_analyze;
+            title = format!(
+                "{} ({})",
+                window.title(),
+                if analyze { "ANALYZE" } else { "estimated" }
+            );
+        }
+        if let Some(saved) = state
+            .inner
+            .read()
+            .unwrap()
+            .followed
+            .last()
+            .filter(|_| window.id() == types::WindowTypeID::QUERY)
+        {
+            title = format!("{} · {} ({} rows)", window.title(), saved.label, count);
+        }
+        if let Some((_, id)) = state
+            .inner
+            .read()
+            .unwrap()
+            .definition_of
+            .as_ref()
+            .filter(|_| definition)
+        {
+            title = format!("{} of {}", window.title(), id);
+        }
+
+        if let Some(filter) = filter.as_ref() {
+            title = format!("{} · /{}", title, filter.text);
+        }
+        if visible.len() < wd.columns.len() {
+            title = format!(
+                "{} · {} of {} columns",
+                title,
+                visible.len(),
+                wd.columns.len()
+            );
+        }
 
         let table = Table::new(rows)
             .widths(&widths)
-            .header(Row::new(wd.columns.clone()).style(Style::default().fg(Color::Yellow)))
-            .block(Block::default().title(window.title()).borders(Borders::ALL))
+            .header(header)
+            .block(Block::default().title(title).borders(Borders::ALL))
             .column_spacing(1)
             .style(Style::default().fg(Color::White))
             .highlight_style(Style::default().bg(Color::Gray));
@@ -0,0 +1,234 @@
+use super::columns::cell_text;
+use crate::connectiontypes::base::Value;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Paragraph};
+use ratatui::{layout::Rect, Frame};
+use std::collections::HashSet;
+
+// long column names are cut so the values keep most of the width
+const MAX_NAME_WIDTH: usize = 30;
+
+/// A line of the record view, before it is wrapped to the width of the screen.
+pub struct RecordLine {
+    pub column: usize,
+    pub text: String,
+    pub null: bool,
+    // the folding key of the JSON object or array starting on this line
+    pub fold: Option<String>,
+}
+
+/// Lists the columns of a row one value after the other, JSON pretty printed and
+/// multi-line text split into its lines.
+pub fn record_lines(
+    columns: &[String],
+    sizes: &[bool],
+    row: &[Value],
+    folded: &HashSet<String>,
+) -> Vec<RecordLine> {
+    let mut lines = vec![];
+    for (column, (name, value)) in columns.iter().zip(row.iter()).enumerate() {
+        match json_of(value) {
+            Some(json) => json_lines(&json, None, name.clone(), 0, "", folded, column, &mut lines),
+            None if value.is_null() => lines.push(RecordLine {
+                column,
+                text: "NULL".to_string(),
+                null: true,
+                fold: None,
+            }),
+            None => {
+                let text = cell_text(value, sizes[column]);
+                lines.extend(text.split('\n').map(|line| RecordLine {
+                    column,
+                    text: line.trim_end_matches('\r').to_string(),
+                    null: false,
+                    fold: None,
+                }))
+            }
+        }
+    }
+    lines
+}
+
+/// JSON values, and text holding a JSON object or array as MySQL and SQLite return them.
+fn json_of(value: &Value) -> Option<serde_json::Value> {
+    match value {
+        Value::Json(json) => Some(json.clone()),
+        Value::Text(text) if text.starts_with('{') || text.starts_with('[') => {
+            serde_json::from_str::<serde_json::Value>(text)
+                .ok()
+                .filter(|json| json.is_object() || json.is_array())
+        }
+        _ => None,
+    }
+}
+
+/// Objects and arrays are folded by their path below the column, e.g. `data/items/0`.
+#[allow(clippy::too_many_arguments)]
+fn json_lines(
+    json: &serde_json::Value,
+    key: Option<&str>,
+    path: String,
+    depth: usize,
+    comma: &str,
+    folded: &HashSet<String>,
+    column: usize,
+    lines: &mut Vec<RecordLine>,
+) {
+    let indent = "  ".repeat(depth);
+    let key = match key {
+        Some(key) => format!("{}: ", serde_json::Value::String(key.to_string())),
+        None => String::new(),
+    };
+    let children: Vec<(Option<String>, &serde_json::Value)> = match json {
+        serde_json::Value::Object(object) => object
+            .iter()
+            .map(|(key, value)| (Some(key.clone()), value))
+            .collect(),
+        serde_json::Value::Array(array) => array.iter().map(|value| (None, value)).collect(),
+        scalar => {
+            lines.push(RecordLine {
+                column,
+                text: format!("{}{}{}{}", indent, key, scalar, comma),
+                null: false,
+                fold: None,
+            });
+            return;
+        }
+    };
+    let (open, close) = if json.is_object() {
+        ("{", "}")
+    } else {
+        ("[", "]")
+    };
+    if children.is_empty() {
+        lines.push(RecordLine {
+            column,
+            text: format!("{}{}{}{}{}", indent, key, open, close, comma),
+            null: false,
+            fold: None,
+        });
+        return;
+    }
+    if folded.contains(&path) {
+        lines.push(RecordLine {
+            column,
+            text: format!(
+                "{}▸ {}{} … {} {} {}{}",
+                indent,
+                key,
+                open,
+                children.len(),
+                if json.is_object() { "keys" } else { "items" },
+                close,
+                comma
+            ),
+            null: false,
+            fold: Some(path),
+        });
+        return;
+    }
+    lines.push(RecordLine {
+        column,
+        text: format!("{}▾ {}{}", indent, key, open),
+        null: false,
+        fold: Some(path.clone()),
+    });
+    let last = children.len() - 1;
+    for (idx, (child_key, child)) in children.into_iter().enumerate() {
+        let child_path = format!("{}/{}", path, child_key.clone().unwrap_or(idx.to_string()));
+        json_lines(
+            child,
+            child_key.as_deref(),
+            child_path,
+            depth + 1,
+            if idx == last { "" } else { "," },
+            folded,
+            column,
+            lines,
+        );
+    }
+    lines.push(RecordLine {
+        column,
+        text: format!("{}{}{}", indent, close, comma),
+        null: false,
+        fold: None,
+    });
+}
+
+/// Splits text into pieces of at most `width` characters, empty text is one empty piece.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let chars = text.chars().collect::<Vec<_>>();
+    if chars.is_empty() {
+        return vec![String::new()];
+    }
+    chars
+        .chunks(width.max(1))
+        .map(|chunk| chunk.iter().collect())
+        .collect()
+}
+
+/// Draws the lines with the column names on the left, scrolled to keep the `cursor` line in view.
+pub fn render_record(
+    frame: &mut Frame,
+    rect: Rect,
+    title: String,
+    columns: &[String],
+    lines: &[RecordLine],
+    cursor: usize,
+) {
+    let name_width = columns
+        .iter()
+        .map(|name| name.chars().count())
+        .max()
+        .unwrap_or(0)
+        .min(MAX_NAME_WIDTH);
+    let value_width = (rect.width as usize).saturating_sub(name_width + 4);
+    let mut rows = vec![];
+    let mut cursor_rows = (0, 0);
+    for (idx, line) in lines.iter().enumerate() {
+        let first_of_column = idx == 0 || lines[idx - 1].column != line.column;
+        let style = if idx == cursor {
+            Style::default().bg(Color::Gray)
+        } else {
+            Style::default()
+        };
+        let value_style = if line.null {
+            style.fg(Color::DarkGray).add_modifier(Modifier::ITALIC)
+        } else if line.fold.is_some() {
+            style.fg(Color::Magenta)
+        } else {
+            style
+        };
+        if idx == cursor {
+            cursor_rows.0 = rows.len();
+        }
+        for (piece_idx, piece) in wrap(&line.text, value_width).into_iter().enumerate() {
+            let name = if first_of_column && piece_idx == 0 {
+                columns[line.column].chars().take(name_width).collect()
+            } else {
+                String::new()
+            };
+            rows.push(Line::from(vec![
+                Span::styled(
+                    format!("{:<width$}  ", name, width = name_width),
+                    Style::default().fg(Color::Yellow),
+                ),
+                Span::styled(piece, value_style),
+            ]));
+        }
+        if idx == cursor {
+            cursor_rows.1 = rows.len() - 1;
+        }
+    }
+    // scroll just far enough for the last row of the cursor line to show
+    let height = (rect.height as usize).saturating_sub(2);
+    let scroll = (cursor_rows.1 + 1)
+        .saturating_sub(height)
+        .min(cursor_rows.0);
+    let paragraph = Paragraph::new(rows)
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .style(Style::default().fg(Color::White))
+        .scroll((scroll as u16, 0));
+    frame.render_widget(paragraph, rect);
+}
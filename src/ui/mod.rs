@@ -10,6 +10,9 @@ use std::{
     sync::Mutex,
 };
 pub mod base;
+pub mod clipboard;
+pub mod filter;
+pub mod highlight;
 pub mod input;
 pub mod layout;
 pub mod main;
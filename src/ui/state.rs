@@ -1,24 +1,199 @@
-use super::types;
-use crate::connectiontypes::base::ConnectionType;
+use super::filter::Filter;
+use super::main::columns::size_columns;
+use super::{clipboard, types};
+use crate::changes::{
+    apply_changes, primary_key, row_deletes, row_insert, row_key, staged_updates, where_condition,
+    RowChange, StagedEdit,
+};
+use crate::connectiontypes::base::{
+    format_param, parse_edit, parse_param, ColumnInfo, ConnectionType, ExecutionOutcome,
+    ObjectKind, PageCursor, PlanNode, QueryParams, QueryResult, Value,
+};
+use crate::connectiontypes::session::{SessionKey, SessionStatus, IDLE_TIMEOUT};
 use crate::connectiontypes::utils::get_connection_type;
-use crate::data::Connection;
+use crate::data::{Connection, CopyFormat};
+use crate::export::{export_all, json_object, parse_export, Export, ExportFormat, Exporter};
+use crate::import::{import_all, parse_import, read_file, ImportPlan};
 use crate::{app::Application, connectiontypes::utils::feature_supported};
-use anyhow::Result;
-use log::{error, info};
+use anyhow::{Context, Result};
+use log::error;
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     sync::{Arc, RwLock},
+    time::Duration,
 };
 
+const SESSION_REAP_INTERVAL: Duration = Duration::from_secs(30);
+// how many of the most expensive plan nodes are highlighted
+const PLAN_HOTSPOTS: usize = 3;
+
 #[derive(Clone)]
 pub struct WindowDataRow {
     pub id: String,
-    pub data: Vec<String>,
+    pub data: Vec<Value>,
 }
 #[derive(Clone)]
 pub struct WindowData {
     pub columns: Vec<String>,
     pub rows: Vec<WindowDataRow>,
+    pub next_page: Option<PageCursor>,
+}
+
+impl WindowData {
+    pub fn empty() -> Self {
+        WindowData {
+            columns: vec![],
+            rows: vec![],
+            next_page: None,
+        }
+    }
+}
+
+/// The result of one statement of the script run in the query window.
+#[derive(Clone)]
+pub struct QueryTab {
+    pub statement: String,
+    pub params: QueryParams,
+    pub data: WindowData,
+    pub outcome: Option<ExecutionOutcome>,
+    pub error: Option<String>,
+    // the table the rows were browsed or looked up in by following a foreign key,
+    // the rows of any other query are not known to come from one table
+    pub table: Option<String>,
+}
+
+impl QueryTab {
+    fn new(statement: &str, params: QueryParams, results: QueryResult) -> Self {
+        QueryTab {
+            statement: statement.to_string(),
+            params,
+            data: WindowData {
+                columns: results.columns,
+                // rows are told apart by their position, query results needn't have a key
+                rows: results
+                    .rows
+                    .into_iter()
+                    .enumerate()
+                    .map(|(idx, r)| WindowDataRow::new(idx.to_string(), r.data))
+                    .collect(),
+                next_page: results.next_page,
+            },
+            outcome: Some(results.outcome),
+            error: None,
+            table: None,
+        }
+    }
+
+    fn failed(statement: &str, params: QueryParams, error: String) -> Self {
+        QueryTab {
+            statement: statement.to_string(),
+            params,
+            data: WindowData::empty(),
+            outcome: None,
+            error: Some(error),
+            table: None,
+        }
+    }
+}
+
+/// The value typed for a cell, staged once it is confirmed.
+#[derive(Clone)]
+pub struct CellEdit {
+    pub edit: StagedEdit,
+    pub input: String,
+}
+
+/// A new row being filled in, a field left empty gets the default of its column.
+#[derive(Clone)]
+pub struct RowForm {
+    pub table: String,
+    pub columns: Vec<ColumnInfo>,
+    pub inputs: Vec<String>,
+    // the values of the duplicated row, kept as they are while their input is not changed
+    copied: Vec<Option<Value>>,
+    pub current: usize,
+}
+
+/// The column a window is sorted by.
+#[derive(Clone)]
+pub struct Sort {
+    pub column: String,
+    pub descending: bool,
+    // the query runs again with ORDER BY instead of the loaded rows being sorted
+    server: bool,
+}
+
+/// The row under the cursor shown one column per line.
+#[derive(Clone, Default)]
+pub struct RecordView {
+    // the line under the cursor
+    pub line: usize,
+    // folding keys of the JSON objects and arrays that are hidden, they stay folded on other rows
+    pub folded: HashSet<String>,
+}
+
+/// A statement db9s wrote to look up the rows of a table a foreign key leads to.
+#[derive(Clone)]
+struct Lookup {
+    table: String,
+    statement: String,
+    params: QueryParams,
+}
+
+/// The result on screen before following a foreign key, going back restores it.
+#[derive(Clone)]
+pub struct SavedResult {
+    // how the result after it was reached, e.g. `orders.customer_id → customers`
+    pub label: String,
+    tabs: Vec<QueryTab>,
+    active_tab: usize,
+    position: i32,
+    column: usize,
+    sort: Option<Sort>,
+}
+
+/// The prompt for a condition the browsed table is filtered by.
+#[derive(Clone)]
+pub struct WherePrompt {
+    pub table: String,
+    pub columns: Vec<ColumnInfo>,
+    pub input: String,
+    // the condition picked to be removed, none while typing
+    pub current: Option<usize>,
+    pub error: Option<String>,
+}
+
+impl WherePrompt {
+    /// The column names the last word typed can be completed to.
+    pub fn completions(&self) -> Vec<String> {
+        let word = self.input.split_whitespace().last().unwrap_or_default();
+        if word.is_empty() || self.input.ends_with(char::is_whitespace) {
+            return vec![];
+        }
+        let word = word.to_lowercase();
+        self.columns
+            .iter()
+            .filter(|column| column.name.to_lowercase().starts_with(&word))
+            .map(|column| column.name.clone())
+            .collect()
+    }
+}
+
+/// Values asked for the placeholders of the query before it runs.
+#[derive(Clone)]
+pub struct ParamPrompt {
+    pub names: Vec<String>,
+    pub inputs: Vec<String>,
+    pub current: usize,
+}
+
+/// What to do once the user decided what happens to the open transaction.
+#[derive(Clone, PartialEq)]
+pub enum PendingAction {
+    EndTransaction,
+    Quit,
+    // selecting another connection or database leaves the session of the transaction
+    Select { value: String, open: bool },
 }
 
 pub struct LayoutStateInner {
@@ -27,52 +202,77 @@ pub struct LayoutStateInner {
     pub custom_queries: HashMap<String, String>,
     pub data: HashMap<types::WindowTypeID, WindowData>,
     pub app: Arc<Application>,
-    pub databases: HashMap<String, Box<dyn ConnectionType>>,
     pub dirty: bool,
     pub error: Option<String>,
+    pub status: Option<String>,
+    // one tab per statement of the script run in the query window
+    pub query_tabs: Vec<QueryTab>,
+    pub active_tab: usize,
+    // bumped for every pull, results of older pulls are thrown away
+    pub generation: u64,
+    pub running: bool,
+    pub pending: Option<PendingAction>,
+    pub quit: bool,
+    pub param_prompt: Option<ParamPrompt>,
+    // what was typed at the prompt, taken by the next run of the query
+    param_inputs: Option<HashMap<String, String>>,
+    pub plan: Option<PlanNode>,
+    pub plan_analyze: bool,
+    // paths of the plan nodes whose children are hidden
+    plan_collapsed: HashSet<String>,
+    sorts: HashMap<types::WindowTypeID, Sort>,
+    // the WHERE clause of the browsed table, joined with AND
+    pub conditions: Vec<String>,
+    pub where_prompt: Option<WherePrompt>,
+    // typed after `/`, kept for each window until cleared
+    filters: HashMap<types::WindowTypeID, Filter>,
+    // the window and id of the object the definition window shows
+    pub definition_of: Option<(types::WindowTypeID, String)>,
+    // results left by following foreign keys, the last one is restored first
+    pub followed: Vec<SavedResult>,
+    // taken by the next query pull, which runs them instead of the query
+    lookups: Option<Vec<Lookup>>,
+    pub cell_edit: Option<CellEdit>,
+    // changes made in the result grid, not written yet
+    pub staged: Vec<StagedEdit>,
+    pub reviewing: bool,
+    pub row_form: Option<RowForm>,
+    pub import: Option<ImportPlan>,
+    pub record: Option<RecordView>,
+    // rows waiting for the deletion to be confirmed
+    pending_delete: Option<Vec<RowChange>>,
 }
 
 impl WindowDataRow {
-    pub fn new(id: String, data: Vec<String>) -> Self {
+    pub fn new(id: String, data: Vec<Value>) -> Self {
         WindowDataRow { id, data }
     }
     pub fn from_string(data: String) -> Self {
         WindowDataRow {
             id: data.clone(),
-            data: vec![data],
+            data: vec![Value::Text(data)],
         }
     }
-    pub fn from_str(data: &str) -> Self {
-        WindowDataRow {
-            id: data.to_string(),
-            data: vec![data.to_string()],
-        }
+}
+
+impl From<&str> for WindowDataRow {
+    fn from(data: &str) -> Self {
+        WindowDataRow::from_string(data.to_string())
     }
 }
 impl LayoutStateInner {
     pub fn get_active(&self, selection: types::WindowTypeID) -> Option<String> {
         match self.selections.get(&selection) {
-            Some(selected) => {
-                if selected.len() > 0 {
-                    return Some(selected[0].clone());
-                } else {
-                    None
-                }
-            }
+            Some(selected) => selected.first().cloned(),
             None => None,
         }
     }
     pub fn get_selection(&self, selection: types::WindowTypeID) -> Option<Vec<String>> {
-        match self.selections.get(&selection) {
-            Some(selected) => return Some(selected.clone()),
-            None => None,
-        }
+        self.selections.get(&selection).cloned()
     }
 
     pub fn toggle_selection(&mut self, selection: types::WindowTypeID, value: String) -> &mut Self {
-        if !self.selections.contains_key(&selection) {
-            self.selections.insert(selection.clone(), vec![]);
-        }
+        self.selections.entry(selection).or_default();
         if self.selections.get(&selection).unwrap().contains(&value) {
             let index = self
                 .selections
@@ -92,7 +292,22 @@ impl LayoutStateInner {
     }
 
     pub fn set_active(&mut self, selection: types::WindowTypeID, active: String) -> &mut Self {
-        self.selections.insert(selection.clone(), vec![active]);
+        // staged changes name their table without the connection, database or schema
+        let scopes = [
+            types::WindowTypeID::CONNECTIONS,
+            types::WindowTypeID::DATABASES,
+            types::WindowTypeID::SCHEMAS,
+        ];
+        let changed = self.get_active(selection).as_ref() != Some(&active);
+        if scopes.contains(&selection) && changed {
+            self.staged.clear();
+        }
+        // the columns of another table are sorted and filtered differently
+        if changed && (scopes.contains(&selection) || selection == types::WindowTypeID::TABLES) {
+            self.sorts.remove(&types::WindowTypeID::QUERY);
+            self.conditions.clear();
+        }
+        self.selections.insert(selection, vec![active]);
         self
     }
 
@@ -111,17 +326,44 @@ impl LayoutStateInner {
             .app
             .get_connection(&active_connection)
             .ok_or(anyhow::anyhow!("No active connection"))?;
-        return Ok(conn_info);
+        Ok(conn_info)
     }
     fn get_active_connection_type(&self) -> Result<Box<dyn ConnectionType>> {
         let conn_info = self.get_active_connection_config()?;
         let conn_type = get_connection_type(
             conn_info.clone(),
             self.selections.clone(),
-            self.custom_queries.clone(),
+            &self.app.sessions,
+            &self.app.get_settings(),
         )?;
         Ok(conn_type)
     }
+    /// A connection to read the metadata of `table` with, which needn't be the selected one.
+    fn get_table_connection_type(&self, table: &str) -> Result<Box<dyn ConnectionType>> {
+        let mut selections = self.selections.clone();
+        selections.insert(types::WindowTypeID::TABLES, vec![table.to_string()]);
+        selections.remove(&types::WindowTypeID::COLUMNS);
+        get_connection_type(
+            self.get_active_connection_config()?,
+            selections,
+            &self.app.sessions,
+            &self.app.get_settings(),
+        )
+    }
+    fn get_session_key(&self) -> Option<SessionKey> {
+        let conn_info = self.get_active_connection_config().ok()?;
+        Some(SessionKey::new(&conn_info, &self.selections))
+    }
+    pub fn get_session_status(&self) -> Option<SessionStatus> {
+        let key = self.get_session_key()?;
+        Some(self.app.sessions.status(&key))
+    }
+    pub fn in_transaction(&self) -> bool {
+        match self.get_session_key() {
+            Some(key) => self.app.sessions.in_transaction(&key),
+            None => false,
+        }
+    }
     fn get_custom_query(&self) -> Option<(String, String)> {
         let cc = self.get_active_connection_config();
         if cc.is_err() {
@@ -132,38 +374,606 @@ impl LayoutStateInner {
             .get(&cc.id)
             .map(|s| (cc.id, s.to_string()))
     }
+    fn get_script(&self, db: &dyn ConnectionType) -> String {
+        if let Some((_, query)) = self.get_custom_query() {
+            return query;
+        }
+        let mut query = db.default_query_string();
+        match self.conditions.as_slice() {
+            [] => {}
+            [condition] => query = format!("{} WHERE {}", query, condition),
+            // a typed fragment may hold an OR
+            conditions => {
+                query = format!(
+                    "{} WHERE {}",
+                    query,
+                    conditions
+                        .iter()
+                        .map(|condition| format!("({})", condition))
+                        .collect::<Vec<_>>()
+                        .join(" AND ")
+                )
+            }
+        }
+        if let Some(sort) = self
+            .sorts
+            .get(&types::WindowTypeID::QUERY)
+            .filter(|sort| sort.server)
+        {
+            query = format!(
+                "{} ORDER BY {} {}",
+                query,
+                db.quote_identifier(&sort.column),
+                if sort.descending { "DESC" } else { "ASC" }
+            );
+        }
+        query
+    }
+
+    /// Sort the loaded rows of the query results, unless the query sorts them itself.
+    fn sort_query_tabs(&mut self) {
+        if let Some(sort) = self
+            .sorts
+            .get(&types::WindowTypeID::QUERY)
+            .filter(|sort| !sort.server)
+        {
+            for tab in self.query_tabs.iter_mut() {
+                sort_rows(&tab.data.columns, &mut tab.data.rows, sort);
+            }
+        }
+    }
+    pub fn get_active_tab(&self) -> Option<&QueryTab> {
+        self.query_tabs.get(self.active_tab)
+    }
+    /// The table the result on screen comes from, the selected one when it is browsed
+    /// or the one a followed key led to. Only its rows can be changed.
+    fn get_result_table(&self) -> Option<String> {
+        self.get_active_tab().and_then(|tab| tab.table.clone())
+    }
+    /// The statement the plan window explains: the result tab on screen when it belongs
+    /// to the current query, otherwise the first statement of the query.
+    fn get_plan_statement(&self, db: &dyn ConnectionType) -> Result<(String, QueryParams)> {
+        let statements = db.split_script(&self.get_script(db));
+        if let Some(tab) = self.get_active_tab() {
+            if statements.contains(&tab.statement) {
+                return Ok((tab.statement.clone(), tab.params.clone()));
+            }
+        }
+        let statement = statements
+            .into_iter()
+            .next()
+            .ok_or(anyhow::anyhow!("There is no statement to explain"))?;
+        if !db.placeholders(&statement).is_empty() {
+            return Err(anyhow::anyhow!(
+                "Run the query first to give values for its parameters"
+            ));
+        }
+        Ok((statement, vec![]))
+    }
+}
+
+/// How the columns of a window are laid out, columns are known by name so it stays
+/// when the data is reloaded.
+#[derive(Clone, Default)]
+pub struct ColumnLayout {
+    // the number of columns that stay on the left when scrolling sideways
+    pub frozen: usize,
+    // cells added to or taken from the width a column gets for its content
+    pub resized: HashMap<String, i32>,
+    pub hidden: HashSet<String>,
 }
 
 pub struct LayoutState {
     positions: HashMap<types::WindowTypeID, i32>,
+    // the column of the cell cursor
+    columns: HashMap<types::WindowTypeID, usize>,
+    column_layouts: HashMap<types::WindowTypeID, ColumnLayout>,
     pub inner: Arc<RwLock<LayoutStateInner>>,
 }
+/// The placeholders of every statement together with the name their value is asked by.
+/// Positional `?` placeholders are numbered across the whole script.
+fn script_placeholders(
+    db: &dyn ConnectionType,
+    statements: &[String],
+) -> Vec<Vec<(String, String)>> {
+    let mut position = 0;
+    statements
+        .iter()
+        .map(|statement| {
+            db.placeholders(statement)
+                .into_iter()
+                .map(|placeholder| {
+                    let name = if placeholder == "?" {
+                        position += 1;
+                        format!("?{}", position)
+                    } else {
+                        placeholder.clone()
+                    };
+                    (placeholder, name)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Lists the visible nodes of a plan, each one indented below its parent.
+/// Rows are identified by the path of child indexes leading to the node, e.g. `0.2.1`.
+fn plan_rows(
+    node: &PlanNode,
+    path: String,
+    depth: usize,
+    collapsed: &HashSet<String>,
+    rows: &mut Vec<WindowDataRow>,
+) {
+    let folded = collapsed.contains(&path);
+    let marker = if node.children.is_empty() {
+        "•"
+    } else if folded {
+        "▸"
+    } else {
+        "▾"
+    };
+    let number = |value: Option<f64>| match value {
+        Some(value) => Value::Float((value * 100.0).round() / 100.0),
+        None => Value::Text(String::new()),
+    };
+    rows.push(WindowDataRow::new(
+        path.clone(),
+        vec![
+            format!("{}{} {}", "  ".repeat(depth), marker, node.node_type).into(),
+            number(node.cost),
+            number(node.rows),
+            number(node.actual_rows),
+            number(node.actual_time),
+            node.detail.clone().into(),
+        ],
+    ));
+    if folded {
+        return;
+    }
+    for (idx, child) in node.children.iter().enumerate() {
+        plan_rows(
+            child,
+            format!("{}.{}", path, idx),
+            depth + 1,
+            collapsed,
+            rows,
+        );
+    }
+}
+
+fn plan_costs(node: &PlanNode, path: String, costs: &mut Vec<(String, f64)>) {
+    if let Some(cost) = node.own_cost() {
+        costs.push((path.clone(), cost));
+    } else if node.node_type.starts_with("SCAN ") && !node.node_type.contains("INDEX") {
+        // sqlite reports no costs, a full table scan is what is expensive there
+        costs.push((path.clone(), 1.0));
+    }
+    for (idx, child) in node.children.iter().enumerate() {
+        plan_costs(child, format!("{}.{}", path, idx), costs);
+    }
+}
+
 fn update_state(
     state: Arc<RwLock<LayoutStateInner>>,
+    generation: u64,
     window: types::WindowTypeID,
     data: WindowData,
 ) {
     let mut state = state.write().unwrap();
+    if state.generation != generation {
+        return;
+    }
+    let mut data = data;
+    if let Some(sort) = state.sorts.get(&window) {
+        sort_rows(&data.columns, &mut data.rows, sort);
+    }
     state.data.clear();
     state.data.insert(window, data);
     state.dirty = true;
 }
 
-fn safely_pull_data(state: Arc<RwLock<LayoutStateInner>>) {
-    let result = pull_data(Arc::clone(&state));
+/// The rows picked by their ids, the rows of a query result by their position in it.
+fn selected_rows(rows: &[WindowDataRow], selected: &[String]) -> Vec<WindowDataRow> {
+    rows.iter()
+        .filter(|row| selected.contains(&row.id))
+        .cloned()
+        .collect()
+}
+
+/// Sorts rows by a column, stable so rows with the same value keep their order.
+fn sort_rows(columns: &[String], rows: &mut [WindowDataRow], sort: &Sort) {
+    if let Some(idx) = columns.iter().position(|column| *column == sort.column) {
+        rows.sort_by(|a, b| {
+            let order = a.data[idx].compare(&b.data[idx]);
+            if sort.descending {
+                order.reverse()
+            } else {
+                order
+            }
+        });
+    }
+}
+
+/// Runs the query again, the results reached by following keys are looked up again.
+fn reload(inner: &Arc<RwLock<LayoutStateInner>>) {
+    {
+        let mut state = inner.write().unwrap();
+        if state.active_window == types::WindowTypeID::QUERY && !state.followed.is_empty() {
+            let lookups = state
+                .query_tabs
+                .iter()
+                .filter_map(|tab| {
+                    tab.table.as_ref().map(|table| Lookup {
+                        table: table.clone(),
+                        statement: tab.statement.clone(),
+                        params: tab.params.clone(),
+                    })
+                })
+                .collect();
+            state.lookups = Some(lookups);
+        }
+    }
+    spawn_pull(inner, None);
+}
+
+fn spawn_pull(inner: &Arc<RwLock<LayoutStateInner>>, page: Option<(usize, PageCursor)>) {
+    if page.is_none() && prompt_for_params(inner) {
+        return;
+    }
+    let generation = {
+        let mut state = inner.write().unwrap();
+        state.generation += 1;
+        state.running = true;
+        if state.active_window == types::WindowTypeID::QUERY {
+            state.status = Some("Running query... (Ctrl-C to cancel)".to_string());
+            if page.is_none() {
+                state.query_tabs.clear();
+                state.active_tab = 0;
+                state.del_active(types::WindowTypeID::QUERY);
+                state.record = None;
+                // running the query again leaves the results reached by following keys
+                if state.lookups.is_none() {
+                    state.followed.clear();
+                }
+            }
+        } else if state.active_window == types::WindowTypeID::PLAN {
+            state.status = Some("Explaining query... (Ctrl-C to cancel)".to_string());
+            state.plan = None;
+        }
+        state.generation
+    };
+    let moved_state: Arc<RwLock<LayoutStateInner>> = Arc::clone(inner);
+    std::thread::spawn(move || {
+        safely_pull_data(moved_state, generation, page);
+    });
+}
+
+/// Ask for the placeholders of the query when there are any and no values were given yet.
+fn prompt_for_params(inner: &Arc<RwLock<LayoutStateInner>>) -> bool {
+    let mut state = inner.write().unwrap();
+    if state.active_window != types::WindowTypeID::QUERY
+        || state.param_inputs.is_some()
+        || state.lookups.is_some()
+    {
+        return false;
+    }
+    let db = match state.get_active_connection_type() {
+        Ok(db) => db,
+        // the pull reports the error
+        Err(_) => return false,
+    };
+    let statements = db.split_script(&state.get_script(db.as_ref()));
+    let mut names: Vec<String> = vec![];
+    for (_, name) in script_placeholders(db.as_ref(), &statements)
+        .into_iter()
+        .flatten()
+    {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    if names.is_empty() {
+        return false;
+    }
+
+    // start from the values used last time with this query
+    let previous = state.get_custom_query().and_then(|(cc_id, query)| {
+        state
+            .app
+            .get_connection(&cc_id)
+            .and_then(|cc| cc.query_params.get(&query).cloned())
+    });
+    let inputs = names
+        .iter()
+        .map(|name| {
+            previous
+                .as_ref()
+                .and_then(|p| p.get(name).cloned())
+                .unwrap_or_default()
+        })
+        .collect();
+    state.param_prompt = Some(ParamPrompt {
+        names,
+        inputs,
+        current: 0,
+    });
+    state.dirty = true;
+    true
+}
+
+fn safely_pull_data(
+    state: Arc<RwLock<LayoutStateInner>>,
+    generation: u64,
+    page: Option<(usize, PageCursor)>,
+) {
+    let result = match page {
+        Some((tab, cursor)) => pull_page(Arc::clone(&state), generation, tab, cursor),
+        None => pull_data(Arc::clone(&state), generation),
+    };
     let mut state = state.write().unwrap();
+    if state.generation != generation {
+        // cancelled or superseded by a newer pull
+        return;
+    }
+    state.running = false;
+    state.status = None;
     state.dirty = true;
     if result.is_err() {
         let err = result.err().unwrap();
-        state.error = Some(err.to_string());
+        state.error = Some(format!("{:#}", err));
         error!("Error: {:?}", err);
     } else {
         state.error = None;
     }
 }
 
-fn pull_data(state: Arc<RwLock<LayoutStateInner>>) -> Result<()> {
-    let window = state.read().unwrap().active_window.clone();
+fn safely_export(
+    state: Arc<RwLock<LayoutStateInner>>,
+    generation: u64,
+    export: Export,
+    statement: String,
+    params: QueryParams,
+) {
+    let db = state.read().unwrap().get_active_connection_type();
+    let result = db.and_then(|db| {
+        export_all(db.as_ref(), &export, &statement, &params, |rows| {
+            let mut state = state.write().unwrap();
+            if state.generation != generation {
+                return false;
+            }
+            state.status = Some(format!(
+                "Exporting to {}... {} rows written (Ctrl-C to cancel)",
+                export.path.display(),
+                rows
+            ));
+            state.dirty = true;
+            true
+        })
+    });
+    let mut state = state.write().unwrap();
+    if state.generation != generation {
+        return;
+    }
+    state.running = false;
+    state.dirty = true;
+    // running the statement again took the place of the server side cursors of the results
+    for tab in state.query_tabs.iter_mut() {
+        if let Some(PageCursor::Cursor(_)) = tab.data.next_page {
+            tab.data.next_page = None;
+        }
+    }
+    match result {
+        Ok(Some(rows)) => {
+            state.error = None;
+            state.status = Some(format!(
+                "Exported {} rows to {}",
+                rows,
+                export.path.display()
+            ));
+        }
+        Ok(None) => {}
+        Err(err) => {
+            error!("Export failed: {:?}", err);
+            state.status = None;
+            state.error = Some(format!("Export failed: {:#}", err));
+        }
+    }
+}
+
+/// A value as it is copied, NULL is nothing.
+fn clipboard_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        value => value.to_string(),
+    }
+}
+
+/// Rows as they are copied: tab separated under a line of column names, or as JSON
+/// objects with more than one row in an array.
+fn clipboard_rows(
+    columns: &[String],
+    rows: &[WindowDataRow],
+    format: CopyFormat,
+) -> Result<String> {
+    match format {
+        CopyFormat::Tsv => {
+            // tabs and line breaks inside values would start new cells
+            let line = |values: Vec<String>| {
+                values
+                    .iter()
+                    .map(|value| value.replace(['\t', '\n', '\r'], " "))
+                    .collect::<Vec<_>>()
+                    .join("\t")
+            };
+            let mut lines = vec![line(columns.to_vec())];
+            lines.extend(
+                rows.iter()
+                    .map(|row| line(row.data.iter().map(clipboard_text).collect())),
+            );
+            Ok(lines.join("\n"))
+        }
+        CopyFormat::Json => {
+            let objects = rows
+                .iter()
+                .map(|row| json_object(columns, &row.data))
+                .collect::<Result<Vec<_>>>()?;
+            match objects.as_slice() {
+                [object] => Ok(object.clone()),
+                objects => Ok(format!("[\n  {}\n]", objects.join(",\n  "))),
+            }
+        }
+    }
+}
+
+fn safely_import(state: Arc<RwLock<LayoutStateInner>>, generation: u64, plan: ImportPlan) {
+    let db = {
+        let state = state.read().unwrap();
+        state
+            .get_table_connection_type(&plan.table)
+            .map(|db| (db, state.in_transaction()))
+    };
+    let result = db.and_then(|(db, in_transaction)| {
+        import_all(db.as_ref(), in_transaction, &plan, |rows| {
+            let mut state = state.write().unwrap();
+            if state.generation != generation {
+                return false;
+            }
+            state.status = Some(format!(
+                "Importing into {}... {} of {} rows (Ctrl-C to cancel)",
+                plan.table,
+                rows,
+                plan.file.rows.len()
+            ));
+            state.dirty = true;
+            true
+        })
+    });
+    let mut state = state.write().unwrap();
+    if state.generation != generation {
+        return;
+    }
+    state.running = false;
+    state.dirty = true;
+    match result {
+        Ok(Some(rows)) => {
+            state.error = None;
+            state.status = Some(format!(
+                "Imported {} rows from {} into {}",
+                rows,
+                plan.path.display(),
+                plan.table
+            ));
+        }
+        Ok(None) => {}
+        Err(err) => {
+            error!("Import failed: {:?}", err);
+            state.status = None;
+            state.error = Some(format!("Import failed: {:#}", err));
+        }
+    }
+}
+
+/// `SELECT *` of the rows of `table` whose `columns` hold `values`.
+fn lookup(db: &dyn ConnectionType, table: &str, columns: &[String], values: Vec<Value>) -> Lookup {
+    let predicates = columns
+        .iter()
+        .enumerate()
+        .map(|(idx, column)| {
+            format!(
+                "{} = {}",
+                db.quote_identifier(column),
+                db.placeholder(idx + 1)
+            )
+        })
+        .collect::<Vec<_>>();
+    Lookup {
+        table: table.to_string(),
+        statement: format!(
+            "SELECT * FROM {} WHERE {}",
+            db.quote_identifier(table),
+            predicates.join(" AND ")
+        ),
+        params: values
+            .into_iter()
+            .enumerate()
+            .map(|(idx, value)| (db.placeholder(idx + 1), value))
+            .collect(),
+    }
+}
+
+/// Runs the lookups of a followed foreign key, one result tab per table.
+fn pull_lookups(
+    state: Arc<RwLock<LayoutStateInner>>,
+    generation: u64,
+    lookups: Vec<Lookup>,
+) -> Result<()> {
+    let db = state.read().unwrap().get_active_connection_type()?;
+    let mut tabs = vec![];
+    for lookup in lookups {
+        if state.read().unwrap().generation != generation {
+            return Ok(());
+        }
+        let mut tab = match db.execute(&lookup.statement, &lookup.params) {
+            Ok(results) => QueryTab::new(&lookup.statement, lookup.params, results),
+            Err(err) => {
+                error!("Lookup failed: {:?}", err);
+                QueryTab::failed(&lookup.statement, lookup.params, format!("{:#}", err))
+            }
+        };
+        tab.table = Some(lookup.table);
+        tabs.push(tab);
+    }
+    let mut state = state.write().unwrap();
+    if state.generation != generation {
+        return Ok(());
+    }
+    state.data.clear();
+    state.query_tabs = tabs;
+    state.active_tab = 0;
+    state.sort_query_tabs();
+    state.dirty = true;
+    Ok(())
+}
+
+fn pull_page(
+    state: Arc<RwLock<LayoutStateInner>>,
+    generation: u64,
+    tab: usize,
+    cursor: PageCursor,
+) -> Result<()> {
+    let (db, statement, params) = {
+        let state = state.read().unwrap();
+        let (statement, params) = match state.query_tabs.get(tab) {
+            Some(query_tab) => (query_tab.statement.clone(), query_tab.params.clone()),
+            None => return Ok(()),
+        };
+        (state.get_active_connection_type()?, statement, params)
+    };
+    let results = db.fetch_page(&statement, &params, &cursor)?;
+    let mut state = state.write().unwrap();
+    if state.generation != generation {
+        return Ok(());
+    }
+    if let Some(query_tab) = state.query_tabs.get_mut(tab) {
+        let data = &mut query_tab.data;
+        let offset = data.rows.len();
+        data.rows.extend(
+            results
+                .rows
+                .into_iter()
+                .enumerate()
+                .map(|(idx, r)| WindowDataRow::new((offset + idx).to_string(), r.data)),
+        );
+        data.next_page = results.next_page;
+    }
+    state.sort_query_tabs();
+    state.dirty = true;
+    Ok(())
+}
+
+fn pull_data(state: Arc<RwLock<LayoutStateInner>>, generation: u64) -> Result<()> {
+    let window = state.read().unwrap().active_window;
     match window {
         types::WindowTypeID::CONNECTIONS => {
             let items = state
@@ -175,13 +985,17 @@ fn pull_data(state: Arc<RwLock<LayoutStateInner>>) -> Result<()> {
                 .map(|c| {
                     WindowDataRow::new(
                         c.id.clone(),
-                        vec![c.name.clone(), c.get_type(), c.get_addr()],
+                        vec![
+                            c.name.clone().into(),
+                            c.get_type().into(),
+                            c.get_addr().into(),
+                        ],
                     )
                 })
                 .collect();
-            let mut state = state.write().unwrap();
-            state.data.clear();
-            state.data.insert(
+            update_state(
+                state,
+                generation,
                 window,
                 WindowData {
                     columns: vec![
@@ -190,21 +1004,43 @@ fn pull_data(state: Arc<RwLock<LayoutStateInner>>) -> Result<()> {
                         "Address".to_string(),
                     ],
                     rows: items,
+                    next_page: None,
                 },
             );
         }
         types::WindowTypeID::TABLES => {
             let db = state.read().unwrap().get_active_connection_type()?;
+            let tables = db.list_tables()?;
             update_state(
                 state,
+                generation,
                 window,
                 WindowData {
-                    columns: vec!["Name".to_string()],
-                    rows: db
-                        .list_tables()?
+                    columns: vec![
+                        "Name".to_string(),
+                        "Kind".to_string(),
+                        "Rows (est.)".to_string(),
+                        "Size".to_string(),
+                        "Owner".to_string(),
+                        "Comment".to_string(),
+                    ],
+                    rows: tables
                         .into_iter()
-                        .map(|t| WindowDataRow::from_str(&t.name))
+                        .map(|t| {
+                            WindowDataRow::new(
+                                t.name.clone(),
+                                vec![
+                                    t.name.into(),
+                                    t.kind.into(),
+                                    t.rows.map(|rows| Value::Int(rows as i64)).into(),
+                                    t.size.map(|size| Value::Int(size as i64)).into(),
+                                    t.owner.into(),
+                                    t.comment.into(),
+                                ],
+                            )
+                        })
                         .collect(),
+                    next_page: None,
                 },
             );
         }
@@ -212,14 +1048,35 @@ fn pull_data(state: Arc<RwLock<LayoutStateInner>>) -> Result<()> {
             let db = state.read().unwrap().get_active_connection_type()?;
             update_state(
                 state,
+                generation,
                 window,
                 WindowData {
-                    columns: vec!["Name".to_string()],
+                    columns: vec![
+                        "Name".to_string(),
+                        "Columns".to_string(),
+                        "Unique".to_string(),
+                        "Method".to_string(),
+                        "Size".to_string(),
+                        "Definition".to_string(),
+                    ],
                     rows: db
                         .list_indexes()?
                         .into_iter()
-                        .map(|idx| WindowDataRow::from_str(&idx))
+                        .map(|idx| {
+                            WindowDataRow::new(
+                                idx.name.clone(),
+                                vec![
+                                    idx.name.into(),
+                                    idx.columns.join(", ").into(),
+                                    Value::Bool(idx.unique),
+                                    idx.method.into(),
+                                    idx.size.map(|size| Value::Int(size as i64)).into(),
+                                    idx.definition.into(),
+                                ],
+                            )
+                        })
                         .collect(),
+                    next_page: None,
                 },
             );
         }
@@ -227,14 +1084,16 @@ fn pull_data(state: Arc<RwLock<LayoutStateInner>>) -> Result<()> {
             let db = state.read().unwrap().get_active_connection_type()?;
             update_state(
                 state,
+                generation,
                 window,
                 WindowData {
                     columns: vec!["Name".to_string()],
                     rows: db
                         .list_schemas()?
                         .into_iter()
-                        .map(|t| WindowDataRow::from_str(&t.name))
+                        .map(|t| WindowDataRow::from(t.name.as_str()))
                         .collect(),
+                    next_page: None,
                 },
             );
         }
@@ -242,14 +1101,16 @@ fn pull_data(state: Arc<RwLock<LayoutStateInner>>) -> Result<()> {
             let db = state.read().unwrap().get_active_connection_type()?;
             update_state(
                 state,
+                generation,
                 window,
                 WindowData {
                     columns: vec!["Name".to_string()],
                     rows: db
                         .list_databases()?
                         .into_iter()
-                        .map(|t| WindowDataRow::from_str(&t.name))
+                        .map(|t| WindowDataRow::from(t.name.as_str()))
                         .collect(),
+                    next_page: None,
                 },
             );
         }
@@ -257,52 +1118,244 @@ fn pull_data(state: Arc<RwLock<LayoutStateInner>>) -> Result<()> {
             let db = state.read().unwrap().get_active_connection_type()?;
             update_state(
                 state,
+                generation,
                 window,
                 WindowData {
-                    columns: vec!["Name".to_string()],
+                    columns: vec![
+                        "Name".to_string(),
+                        "Type".to_string(),
+                        "Nullable".to_string(),
+                        "Default".to_string(),
+                        "Primary Key".to_string(),
+                        "References".to_string(),
+                        "Comment".to_string(),
+                    ],
+                    // rows are identified by the column name, that is what gets selected
                     rows: db
                         .list_columns()?
                         .into_iter()
-                        .map(|t| WindowDataRow::from_str(&t))
+                        .map(|c| {
+                            WindowDataRow::new(
+                                c.name.clone(),
+                                vec![
+                                    c.name.into(),
+                                    c.data_type.into(),
+                                    Value::Bool(c.nullable),
+                                    c.default.into(),
+                                    Value::Bool(c.primary_key),
+                                    c.foreign_key.into(),
+                                    c.comment.into(),
+                                ],
+                            )
+                        })
                         .collect(),
+                    next_page: None,
                 },
             );
         }
         types::WindowTypeID::QUERY => {
-            let db = state.read().unwrap().get_active_connection_type()?;
-            let results = db.query()?;
-            let rows = results
-                .rows
-                .iter()
-                .map(|r| {
-                    WindowDataRow::new(
-                        r.id.clone(),
-                        r.data
-                            .iter()
-                            .map(|c| c.clone().unwrap_or("".to_string()))
-                            .collect(),
-                    )
-                })
-                .collect();
+            let lookups = state.write().unwrap().lookups.take();
+            if let Some(lookups) = lookups {
+                return pull_lookups(state, generation, lookups);
+            }
+            let (db, settings, custom_query, browsed, inputs) = {
+                let mut state = state.write().unwrap();
+                let custom_query = state.get_custom_query();
+                // without a custom query the selected table is browsed
+                let browsed = match custom_query {
+                    Some(_) => None,
+                    None => state.get_active(types::WindowTypeID::TABLES),
+                };
+                (
+                    state.get_active_connection_type()?,
+                    state.app.get_settings(),
+                    custom_query,
+                    browsed,
+                    state.param_inputs.take().unwrap_or_default(),
+                )
+            };
+            let script = state.read().unwrap().get_script(db.as_ref());
+            let statements = db.split_script(&script);
+            if statements.is_empty() {
+                return Err(anyhow::anyhow!("There is no statement to run"));
+            }
+
+            let placeholders = script_placeholders(db.as_ref(), &statements);
+            let mut values = HashMap::new();
+            for (_, name) in placeholders.iter().flatten() {
+                let input = inputs.get(name).cloned().unwrap_or_default();
+                let value = parse_param(&input).with_context(|| format!("Parameter {}", name))?;
+                values.insert(name.clone(), value);
+            }
+
+            let mut tabs = vec![];
+            for (idx, statement) in statements.iter().enumerate() {
+                {
+                    let mut state = state.write().unwrap();
+                    if state.generation != generation {
+                        // cancelled, don't run the rest of the script
+                        return Ok(());
+                    }
+                    if statements.len() > 1 {
+                        state.status = Some(format!(
+                            "Running statement {}/{}... (Ctrl-C to cancel)",
+                            idx + 1,
+                            statements.len()
+                        ));
+                        state.dirty = true;
+                    }
+                }
+                let params: QueryParams = placeholders[idx]
+                    .iter()
+                    .map(|(placeholder, name)| (placeholder.clone(), values[name].clone()))
+                    .collect();
+                match db.execute(statement, &params) {
+                    Ok(results) => {
+                        let mut tab = QueryTab::new(statement, params, results);
+                        tab.table = browsed.clone();
+                        tabs.push(tab);
+                    }
+                    // a lone statement reports its error like any other pull
+                    Err(err) if statements.len() == 1 => return Err(err),
+                    Err(err) => {
+                        error!("Statement failed: {:?}", err);
+                        tabs.push(QueryTab::failed(statement, params, format!("{:#}", err)));
+                        if settings.stop_on_error {
+                            break;
+                        }
+                    }
+                }
+            }
 
-            let cc_cq = state.read().unwrap().get_custom_query();
-            if let Some((cc_id, cq)) = cc_cq {
-                state
-                    .read()
-                    .unwrap()
-                    .app
-                    .persistent_data
-                    .write()
-                    .unwrap()
-                    .add_query_history(cc_id, cq);
+            if let Some((cc_id, cq)) = custom_query {
+                if tabs.iter().any(|tab| tab.error.is_none()) {
+                    let app = Arc::clone(&state.read().unwrap().app);
+                    let mut data = app.persistent_data.write().unwrap();
+                    data.add_query_history(cc_id.clone(), cq.clone());
+                    if !inputs.is_empty() {
+                        data.set_query_params(cc_id, cq, inputs);
+                    }
+                }
             }
 
+            // show the first failure, otherwise the result of the last statement
+            let active_tab = tabs
+                .iter()
+                .position(|tab| tab.error.is_some())
+                .unwrap_or(tabs.len() - 1);
+            let mut state = state.write().unwrap();
+            if state.generation != generation {
+                return Ok(());
+            }
+            state.data.clear();
+            state.query_tabs = tabs;
+            state.active_tab = active_tab;
+            state.sort_query_tabs();
+            state.dirty = true;
+        }
+        types::WindowTypeID::PLAN => {
+            let (db, analyze, (statement, params)) = {
+                let state = state.read().unwrap();
+                let db = state.get_active_connection_type()?;
+                let target = state.get_plan_statement(db.as_ref())?;
+                (db, state.plan_analyze, target)
+            };
+            let plan = db.explain(&statement, &params, analyze)?;
+            let mut state = state.write().unwrap();
+            if state.generation != generation {
+                return Ok(());
+            }
+            state.plan = Some(plan);
+            state.plan_collapsed.clear();
+            state.dirty = true;
+        }
+        types::WindowTypeID::VIEWS
+        | types::WindowTypeID::FUNCTIONS
+        | types::WindowTypeID::TRIGGERS
+        | types::WindowTypeID::SEQUENCES
+        | types::WindowTypeID::ENUMS => {
+            let (config, db) = {
+                let state = state.read().unwrap();
+                (
+                    state.get_active_connection_config()?,
+                    state.get_active_connection_type()?,
+                )
+            };
+            if !feature_supported(config, window)? {
+                return Err(anyhow::anyhow!(
+                    "{} are not supported for this connection",
+                    types::get_window(window).title()
+                ));
+            }
+            let (objects, columns) = match window {
+                types::WindowTypeID::VIEWS => (db.list_views()?, ["Name", "Kind", "Owner"]),
+                types::WindowTypeID::FUNCTIONS => {
+                    (db.list_functions()?, ["Name", "Kind", "Signature"])
+                }
+                types::WindowTypeID::TRIGGERS => (db.list_triggers()?, ["Name", "Event", "Table"]),
+                types::WindowTypeID::SEQUENCES => {
+                    (db.list_sequences()?, ["Name", "Type", "Settings"])
+                }
+                _ => (db.list_enums()?, ["Name", "Kind", "Values"]),
+            };
             update_state(
                 state,
+                generation,
                 window,
                 WindowData {
-                    columns: results.columns,
-                    rows,
+                    columns: columns.iter().map(|c| c.to_string()).collect(),
+                    rows: objects
+                        .into_iter()
+                        .map(|o| {
+                            WindowDataRow::new(
+                                o.id,
+                                vec![o.name.into(), o.kind.into(), o.detail.into()],
+                            )
+                        })
+                        .collect(),
+                    next_page: None,
+                },
+            );
+        }
+        types::WindowTypeID::DEFINITION => {
+            let (db, target) = {
+                let state = state.read().unwrap();
+                (
+                    state.get_active_connection_type()?,
+                    state.definition_of.clone(),
+                )
+            };
+            let (source, id) =
+                target.ok_or(anyhow::anyhow!("Open an object to see its definition"))?;
+            let kind = match source {
+                types::WindowTypeID::VIEWS => ObjectKind::View,
+                types::WindowTypeID::FUNCTIONS => ObjectKind::Function,
+                types::WindowTypeID::TRIGGERS => ObjectKind::Trigger,
+                types::WindowTypeID::SEQUENCES => ObjectKind::Sequence,
+                types::WindowTypeID::ENUMS => ObjectKind::Enum,
+                types::WindowTypeID::TABLES => ObjectKind::Table,
+                types::WindowTypeID::INDEXES => ObjectKind::Index,
+                _ => return Err(anyhow::anyhow!("{:?} have no definition", source)),
+            };
+            let definition = db.get_definition(kind, &id)?;
+            update_state(
+                state,
+                generation,
+                window,
+                WindowData {
+                    columns: vec!["Definition".to_string()],
+                    // one row per line, tabs would not be expanded in a cell
+                    rows: definition
+                        .lines()
+                        .enumerate()
+                        .map(|(idx, line)| {
+                            WindowDataRow::new(
+                                idx.to_string(),
+                                vec![line.replace('\t', "    ").into()],
+                            )
+                        })
+                        .collect(),
+                    next_page: None,
                 },
             );
         }
@@ -314,17 +1367,19 @@ fn pull_data(state: Arc<RwLock<LayoutStateInner>>) -> Result<()> {
                 .map(|q| {
                     WindowDataRow::new(
                         q.clone(),
-                        vec![q.clone().split("\n").collect::<Vec<_>>().join(" ")],
+                        vec![q.split('\n').collect::<Vec<_>>().join(" ").into()],
                     )
                 })
                 .rev()
                 .collect();
             update_state(
                 state,
+                generation,
                 window,
                 WindowData {
                     columns: vec!["Query".to_string()],
                     rows,
+                    next_page: None,
                 },
             );
         }
@@ -336,23 +1391,54 @@ impl LayoutState {
     pub fn new(app: Arc<Application>) -> Self {
         let ls = LayoutState {
             positions: HashMap::new(),
+            columns: HashMap::new(),
+            column_layouts: HashMap::new(),
             inner: Arc::new(RwLock::new(LayoutStateInner {
                 active_window: types::WindowTypeID::CONNECTIONS,
                 selections: HashMap::new(),
                 data: HashMap::new(),
                 app: Arc::clone(&app),
-                databases: HashMap::new(),
                 custom_queries: HashMap::new(),
                 dirty: true,
                 error: None,
+                status: None,
+                query_tabs: vec![],
+                active_tab: 0,
+                generation: 0,
+                running: true,
+                pending: None,
+                quit: false,
+                param_prompt: None,
+                param_inputs: None,
+                plan: None,
+                plan_analyze: false,
+                plan_collapsed: HashSet::new(),
+                sorts: HashMap::new(),
+                conditions: vec![],
+                where_prompt: None,
+                filters: HashMap::new(),
+                definition_of: None,
+                followed: vec![],
+                lookups: None,
+                cell_edit: None,
+                staged: vec![],
+                reviewing: false,
+                row_form: None,
+                import: None,
+                record: None,
+                pending_delete: None,
             })),
         };
-        safely_pull_data(Arc::clone(&ls.inner));
-        ls
-    }
+        safely_pull_data(Arc::clone(&ls.inner), 0, None);
 
-    fn clear(&mut self) {
-        self.inner.write().unwrap().data.clear();
+        let reaper_state = Arc::clone(&ls.inner);
+        std::thread::spawn(move || loop {
+            std::thread::sleep(SESSION_REAP_INTERVAL);
+            let app = Arc::clone(&reaper_state.read().unwrap().app);
+            app.sessions.close_idle(IDLE_TIMEOUT);
+            reaper_state.write().unwrap().dirty = true;
+        });
+        ls
     }
 
     pub fn is_dirty(&self) -> bool {
@@ -368,7 +1454,7 @@ impl LayoutState {
             .positions
             .get(&self.inner.read().unwrap().active_window)
         {
-            Some(pos) => pos.clone(),
+            Some(pos) => *pos,
             None => -1,
         }
     }
@@ -389,6 +1475,107 @@ impl LayoutState {
             .insert(self.inner.read().unwrap().active_window, pos);
     }
 
+    pub fn get_column_position(&self) -> usize {
+        let window = self.inner.read().unwrap().active_window;
+        self.columns.get(&window).cloned().unwrap_or(0)
+    }
+
+    /// Move the cell cursor by `offset` columns, it stops at the first and the last one
+    /// and passes over hidden columns.
+    pub fn move_column(&mut self, offset: i32) {
+        let columns = self.get_window_data().columns;
+        let layout = self.get_column_layout();
+        let mut column = self
+            .get_column_position()
+            .min(columns.len().saturating_sub(1));
+        for _ in 0..offset.abs() {
+            let next = if offset > 0 {
+                (column + 1..columns.len()).find(|idx| !layout.hidden.contains(&columns[*idx]))
+            } else {
+                (0..column)
+                    .rev()
+                    .find(|idx| !layout.hidden.contains(&columns[*idx]))
+            };
+            match next {
+                Some(next) => column = next,
+                None => break,
+            }
+        }
+        self.set_column_position(column);
+    }
+
+    pub fn set_column_position(&mut self, column: usize) {
+        let window = self.inner.read().unwrap().active_window;
+        self.columns.insert(window, column);
+        self.set_dirty(true);
+    }
+
+    pub fn get_column_layout(&self) -> ColumnLayout {
+        let window = self.inner.read().unwrap().active_window;
+        self.column_layouts
+            .get(&window)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn update_column_layout(&mut self, update: impl FnOnce(&mut ColumnLayout, &str)) {
+        let columns = self.get_window_data().columns;
+        let column = match columns.get(self.get_column_position()) {
+            Some(column) => column.clone(),
+            None => return,
+        };
+        let window = self.inner.read().unwrap().active_window;
+        update(self.column_layouts.entry(window).or_default(), &column);
+        self.set_dirty(true);
+    }
+
+    /// Make the column under the cursor `delta` cells wider or narrower.
+    pub fn resize_column(&mut self, delta: i32) {
+        self.update_column_layout(|layout, column| {
+            *layout.resized.entry(column.to_string()).or_default() += delta;
+        });
+    }
+
+    /// Hide the column under the cursor, which moves on to the next one shown.
+    pub fn hide_column(&mut self) {
+        let columns = self.get_window_data().columns;
+        let layout = self.get_column_layout();
+        let shown = columns
+            .iter()
+            .filter(|column| !layout.hidden.contains(*column))
+            .count();
+        if shown < 2 {
+            let mut state = self.inner.write().unwrap();
+            state.error = Some("The last column can't be hidden".to_string());
+            state.dirty = true;
+            return;
+        }
+        let current = self.get_column_position();
+        self.update_column_layout(|layout, column| {
+            layout.hidden.insert(column.to_string());
+        });
+        self.move_column(1);
+        if self.get_column_position() == current {
+            self.move_column(-1);
+        }
+    }
+
+    /// Keep the columns up to the one under the cursor on the left when scrolling sideways,
+    /// or scroll all of them again.
+    pub fn toggle_freeze_columns(&mut self) {
+        let frozen = self.get_column_position() + 1;
+        self.update_column_layout(|layout, _| {
+            layout.frozen = if layout.frozen == frozen { 0 } else { frozen };
+        });
+    }
+
+    /// Show every column at the width of its content again.
+    pub fn reset_columns(&mut self) {
+        let window = self.inner.read().unwrap().active_window;
+        self.column_layouts.remove(&window);
+        self.set_dirty(true);
+    }
+
     pub fn get_number_of_rows(&self) -> i32 {
         let wd = self.get_window_data();
         wd.rows.len() as i32
@@ -396,12 +1583,113 @@ impl LayoutState {
 
     pub fn change_window(&mut self, window: types::WindowTypeID) {
         self.set_dirty(true);
+        {
+            let mut state = self.inner.write().unwrap();
+            state.active_window = window;
+            state.record = None;
+        }
+        spawn_pull(&self.inner, None);
+    }
+
+    pub fn get_param_prompt(&self) -> Option<ParamPrompt> {
+        self.inner.read().unwrap().param_prompt.clone()
+    }
+
+    /// Type into the value of the current parameter, `None` deletes the last character.
+    pub fn edit_param(&mut self, c: Option<char>) {
         let mut state = self.inner.write().unwrap();
-        state.active_window = window;
-        let moved_state: Arc<RwLock<LayoutStateInner>> = Arc::clone(&self.inner);
-        std::thread::spawn(move || {
-            safely_pull_data(moved_state);
-        });
+        if let Some(prompt) = state.param_prompt.as_mut() {
+            let input = &mut prompt.inputs[prompt.current];
+            match c {
+                Some(c) => input.push(c),
+                None => {
+                    input.pop();
+                }
+            }
+            state.dirty = true;
+        }
+    }
+
+    /// Move to the next parameter, the query runs once the last one is confirmed.
+    pub fn next_param(&mut self) {
+        {
+            let mut state = self.inner.write().unwrap();
+            let prompt = match state.param_prompt.as_mut() {
+                Some(prompt) => prompt,
+                None => return,
+            };
+            if prompt.current + 1 < prompt.names.len() {
+                prompt.current += 1;
+                state.dirty = true;
+                return;
+            }
+            let prompt = state.param_prompt.take().unwrap();
+            state.param_inputs = Some(prompt.names.into_iter().zip(prompt.inputs).collect());
+        }
+        spawn_pull(&self.inner, None);
+    }
+
+    pub fn previous_param(&mut self) {
+        let mut state = self.inner.write().unwrap();
+        if let Some(prompt) = state.param_prompt.as_mut() {
+            prompt.current = prompt.current.saturating_sub(1);
+            state.dirty = true;
+        }
+    }
+
+    pub fn cancel_params(&mut self) {
+        let mut state = self.inner.write().unwrap();
+        state.param_prompt = None;
+        state.dirty = true;
+    }
+
+    /// Load the next page of the query results, if there is one.
+    pub fn fetch_next_page(&mut self) {
+        let cursor = {
+            let state = self.inner.read().unwrap();
+            if state.running || state.active_window != types::WindowTypeID::QUERY {
+                return;
+            }
+            state.get_active_tab().and_then(|tab| {
+                tab.data
+                    .next_page
+                    .clone()
+                    .map(|cursor| (state.active_tab, cursor))
+            })
+        };
+        if cursor.is_some() {
+            spawn_pull(&self.inner, cursor);
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.inner.read().unwrap().running
+    }
+
+    /// Stop the pull in flight; whatever it returns afterwards is discarded.
+    pub fn cancel(&mut self) -> bool {
+        let mut state = self.inner.write().unwrap();
+        if !state.running {
+            return false;
+        }
+        state.generation += 1;
+        state.running = false;
+        state.status = Some("Query cancelled".to_string());
+        state.error = None;
+        state.dirty = true;
+        let key = state.get_session_key();
+        let app = Arc::clone(&state.app);
+        drop(state);
+
+        if let Some(key) = key {
+            // cancelling talks to the server, don't block the ui on it
+            std::thread::spawn(move || {
+                if let Err(err) = app.sessions.cancel(&key) {
+                    error!("Failed to cancel query: {:?}", err);
+                }
+            });
+        }
+        true
     }
 
     pub fn get_current_row_value(&self) -> Option<String> {
@@ -420,6 +1708,15 @@ impl LayoutState {
         }
         self.set_dirty(true);
         let value = row_value.unwrap();
+        if self.leaves_transaction(&value) {
+            self.inner.write().unwrap().pending =
+                Some(PendingAction::Select { value, open: false });
+            return;
+        }
+        self.select_value(value);
+    }
+
+    fn select_value(&mut self, value: String) {
         let window = self.get_active_window();
         if window.selection_type() == types::ItemSelectionType::MULTI {
             self.inner
@@ -436,24 +1733,13 @@ impl LayoutState {
         Find next window for selection based on what is supported by the database
         and what is expected app flow
         */
-        let config = self.get_active_connection_config();
-        if config.is_err() {
-            return None;
-        }
-        let config = config.unwrap();
+        let config = self.get_active_connection_config().ok()?;
         let mut window_id = Some(self.get_active_window().id());
         while window_id.is_some() {
             window_id = types::WINDOW_ORDER.get(&window_id.unwrap()).cloned();
-            if window_id.is_none() {
-                return None;
-            }
-            match feature_supported(config.clone(), window_id.unwrap()) {
-                Ok(supported) => {
-                    if supported {
-                        return window_id;
-                    }
-                }
-                Err(_) => {}
+            window_id?;
+            if let Ok(true) = feature_supported(config.clone(), window_id.unwrap()) {
+                return window_id;
             }
         }
         None
@@ -466,79 +1752,1582 @@ impl LayoutState {
         }
         self.set_dirty(true);
         let value = row_value.unwrap();
+        if self.leaves_transaction(&value) {
+            self.inner.write().unwrap().pending = Some(PendingAction::Select { value, open: true });
+            return;
+        }
+        self.open_value(value);
+    }
+
+    fn open_value(&mut self, value: String) {
         let window = self.get_active_window();
 
         if window.id() == types::WindowTypeID::HISTORY {
             let cc = self.get_active_connection_config().unwrap();
             let mut data = self.inner.write().unwrap();
             data.custom_queries.insert(cc.id.clone(), value.clone());
+            data.sorts.remove(&types::WindowTypeID::QUERY);
+            data.conditions.clear();
         } else {
-            self.inner.write().unwrap().set_active(window.id(), value);
+            self.inner
+                .write()
+                .unwrap()
+                .set_active(window.id(), value.clone());
         }
         for clear in window.clears() {
             self.inner.write().unwrap().del_active(clear);
         }
         let next_window = self.get_next_window();
+        if next_window == Some(types::WindowTypeID::DEFINITION) {
+            self.inner.write().unwrap().definition_of = Some((window.id(), value));
+        }
         if let Some(next) = next_window {
             self.change_window(next);
         }
     }
 
-    pub fn get_active_window(&self) -> types::WindowType {
-        types::get_window(self.inner.read().unwrap().active_window.clone())
+    /// Show the DDL of the table or index under the cursor, or of the table the columns belong to.
+    pub fn show_ddl(&mut self) {
+        let window = self.get_active_window().id();
+        let target = match window {
+            types::WindowTypeID::TABLES | types::WindowTypeID::INDEXES => {
+                self.get_current_row_value().map(|value| (window, value))
+            }
+            types::WindowTypeID::COLUMNS => self
+                .inner
+                .read()
+                .unwrap()
+                .get_active(types::WindowTypeID::TABLES)
+                .map(|table| (types::WindowTypeID::TABLES, table)),
+            _ => None,
+        };
+        let supported = self
+            .get_active_connection_config()
+            .and_then(|config| feature_supported(config, types::WindowTypeID::DEFINITION));
+        if let (Some(target), Ok(true)) = (target, supported) {
+            self.inner.write().unwrap().definition_of = Some(target);
+            self.change_window(types::WindowTypeID::DEFINITION);
+        }
     }
 
-    pub fn get_window_data(&self) -> WindowData {
-        let state = self.inner.read().unwrap();
-        match state.data.get(&state.active_window) {
-            Some(items) => items.clone(),
-            None => WindowData {
-                columns: vec![],
-                rows: vec![],
-            },
+    /// Copy the definition on screen to the clipboard.
+    pub fn yank_definition(&mut self) {
+        let text = self
+            .get_window_data()
+            .rows
+            .iter()
+            .filter_map(|row| row.data.first().map(|value| value.to_string()))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let settings = self.inner.read().unwrap().app.get_settings();
+        let result = clipboard::copy(&text, settings.clipboard_command.as_deref());
+        let mut state = self.inner.write().unwrap();
+        state.dirty = true;
+        match result {
+            Ok(()) => state.status = Some("Definition copied to the clipboard".to_string()),
+            Err(err) => state.error = Some(format!("Copying failed: {:#}", err)),
         }
     }
 
-    pub fn refresh(&mut self) {
-        let moved_state: Arc<RwLock<LayoutStateInner>> = Arc::clone(&self.inner);
-        std::thread::spawn(move || {
-            safely_pull_data(moved_state);
+    /// Copy the cell under the cursor to the clipboard, or with `rows` its whole row.
+    /// Selected rows are copied whole either way.
+    pub fn yank(&mut self, rows: bool) {
+        let settings = self.inner.read().unwrap().app.get_settings();
+        let result = self
+            .yank_text(rows, settings.copy_format)
+            .and_then(|(text, copied)| {
+                clipboard::copy(&text, settings.clipboard_command.as_deref())?;
+                Ok(copied)
+            });
+        let mut state = self.inner.write().unwrap();
+        state.dirty = true;
+        match result {
+            Ok(copied) => state.status = Some(format!("Copied {} to the clipboard", copied)),
+            Err(err) => state.error = Some(format!("Copying failed: {:#}", err)),
+        }
+    }
+
+    /// The text to copy and what it is.
+    fn yank_text(&self, rows: bool, format: CopyFormat) -> Result<(String, String)> {
+        let (data, row, column) = self.get_current_cell()?;
+        let window = self.get_active_window();
+        let selected = match window.selection_type() {
+            types::ItemSelectionType::MULTI => self
+                .inner
+                .read()
+                .unwrap()
+                .get_selection(window.id())
+                .unwrap_or_default(),
+            _ => vec![],
+        };
+        let mut picked = selected_rows(&data.rows, &selected);
+        if picked.is_empty() {
+            if !rows {
+                return Ok((clipboard_text(&row.data[column]), "the cell".to_string()));
+            }
+            picked.push(row);
+        }
+        let copied = match picked.len() {
+            1 => "the row".to_string(),
+            count => format!("{} rows", count),
+        };
+        Ok((clipboard_rows(&data.columns, &picked, format)?, copied))
+    }
+
+    /// Look up the row the foreign key under the cell cursor points at, or with `referencing`
+    /// the rows of other tables pointing at the current row. The result on screen is kept
+    /// to go back to.
+    pub fn follow_foreign_key(&mut self, referencing: bool) {
+        if self.is_running() {
+            return;
+        }
+        if let Err(err) = self.lookup_foreign_key(referencing) {
+            let mut state = self.inner.write().unwrap();
+            state.error = Some(format!("{:#}", err));
+            state.dirty = true;
+        }
+    }
+
+    fn lookup_foreign_key(&mut self, referencing: bool) -> Result<()> {
+        let (data, row, column) = self.get_current_cell()?;
+        let column = &data.columns[column];
+        let (db, source) = {
+            let state = self.inner.read().unwrap();
+            (
+                state.get_active_connection_type()?,
+                state.get_result_table(),
+            )
+        };
+        let source = source.ok_or(anyhow::anyhow!("Browse a table to follow its foreign keys"))?;
+        let value_of = |name: &String| -> Result<Value> {
+            data.columns
+                .iter()
+                .position(|c| c == name)
+                .map(|idx| row.data[idx].clone())
+                .ok_or(anyhow::anyhow!("The result has no {} column", name))
+        };
+
+        let keys = db.list_foreign_keys(&source)?;
+        let (label, lookups) = if referencing {
+            let lookups = keys
+                .iter()
+                .filter(|key| key.referenced_table == source)
+                .map(|key| {
+                    let values = key
+                        .referenced_columns
+                        .iter()
+                        .map(value_of)
+                        .collect::<Result<Vec<_>>>()?;
+                    Ok(lookup(db.as_ref(), &key.table, &key.columns, values))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            if lookups.is_empty() {
+                return Err(anyhow::anyhow!("No foreign key references {}", source));
+            }
+            (format!("rows referencing {}", source), lookups)
+        } else {
+            let key = keys
+                .iter()
+                .find(|key| key.table == source && key.columns.contains(column))
+                .ok_or(anyhow::anyhow!(
+                    "{}.{} is not a foreign key",
+                    source,
+                    column
+                ))?;
+            let values = key
+                .columns
+                .iter()
+                .map(value_of)
+                .collect::<Result<Vec<_>>>()?;
+            if values.contains(&Value::Null) {
+                return Err(anyhow::anyhow!("{}.{} is NULL", source, column));
+            }
+            (
+                format!("{}.{} → {}", source, column, key.referenced_table),
+                vec![lookup(
+                    db.as_ref(),
+                    &key.referenced_table,
+                    &key.referenced_columns,
+                    values,
+                )],
+            )
+        };
+
+        let (position, column) = (self.get_position(), self.get_column_position());
+        {
+            let mut state = self.inner.write().unwrap();
+            let saved = SavedResult {
+                label,
+                tabs: std::mem::take(&mut state.query_tabs),
+                active_tab: state.active_tab,
+                position,
+                column,
+                sort: state.sorts.remove(&types::WindowTypeID::QUERY),
+            };
+            state.followed.push(saved);
+            state.lookups = Some(lookups);
+            state.error = None;
+        }
+        self.positions.insert(types::WindowTypeID::QUERY, 0);
+        self.columns.insert(types::WindowTypeID::QUERY, 0);
+        spawn_pull(&self.inner, None);
+        Ok(())
+    }
+
+    /// The columns of the result on screen with the row under the cursor
+    /// and the index of the column of the cell cursor.
+    fn get_current_cell(&self) -> Result<(WindowData, WindowDataRow, usize)> {
+        let data = self.get_window_data();
+        let row = usize::try_from(self.get_position())
+            .ok()
+            .and_then(|position| data.rows.get(position))
+            .cloned()
+            .ok_or(anyhow::anyhow!("Move to a row first"))?;
+        let column = self.get_column_position();
+        if column >= data.columns.len() {
+            return Err(anyhow::anyhow!("Move to a cell first"));
+        }
+        Ok((data, row, column))
+    }
+
+    /// Start typing a new value for the cell under the cursor, its row is found by the
+    /// primary key of the table.
+    pub fn edit_cell(&mut self) {
+        if self.is_running() {
+            return;
+        }
+        if let Err(err) = self.start_cell_edit() {
+            let mut state = self.inner.write().unwrap();
+            state.error = Some(format!("{:#}", err));
+            state.dirty = true;
+        }
+    }
+
+    fn start_cell_edit(&mut self) -> Result<()> {
+        let (data, row, column) = self.get_current_cell()?;
+        let (db, table) = {
+            let state = self.inner.read().unwrap();
+            (
+                state.get_active_connection_type()?,
+                state.get_result_table(),
+            )
+        };
+        let table = table.ok_or(anyhow::anyhow!(
+            "Browse a table to edit its rows, the results of a custom query can not be edited"
+        ))?;
+        let key = row_key(&primary_key(db.as_ref(), &table)?, &data.columns, &row.data)?;
+        let old = row.data[column].clone();
+        let mut edit = StagedEdit {
+            table,
+            key,
+            column: data.columns[column].clone(),
+            new: old.clone(),
+            old,
+        };
+
+        let mut state = self.inner.write().unwrap();
+        // carry on from the value staged before
+        if let Some(staged) = state.staged.iter().find(|staged| staged.same_cell(&edit)) {
+            edit.new = staged.new.clone();
+        }
+        state.cell_edit = Some(CellEdit {
+            input: format_param(&edit.new),
+            edit,
         });
+        state.error = None;
+        state.dirty = true;
+        Ok(())
     }
 
-    pub fn get_connections(&self) -> Vec<Connection> {
-        self.inner.read().unwrap().app.get_connections().clone()
+    pub fn get_cell_edit(&self) -> Option<CellEdit> {
+        self.inner.read().unwrap().cell_edit.clone()
     }
 
-    pub fn get_active_connection_config(&self) -> Result<Connection> {
-        return self.inner.read().unwrap().get_active_connection_config();
+    /// Type into the value of the cell, `None` deletes the last character.
+    pub fn type_cell(&mut self, c: Option<char>) {
+        let mut state = self.inner.write().unwrap();
+        if let Some(cell_edit) = state.cell_edit.as_mut() {
+            match c {
+                Some(c) => cell_edit.input.push(c),
+                None => {
+                    cell_edit.input.pop();
+                }
+            }
+            state.dirty = true;
+        }
     }
-    pub fn get_active_connection_type(&self) -> Result<Box<dyn ConnectionType>> {
-        return self.inner.read().unwrap().get_active_connection_type();
+
+    /// Stage the typed value, typing the original value back drops the change.
+    pub fn confirm_cell_edit(&mut self) {
+        let mut state = self.inner.write().unwrap();
+        state.dirty = true;
+        let mut cell_edit = match state.cell_edit.take() {
+            Some(cell_edit) => cell_edit,
+            None => return,
+        };
+        cell_edit.edit.new = match parse_edit(&cell_edit.input, &cell_edit.edit.old) {
+            Ok(value) => value,
+            Err(err) => {
+                state.error = Some(format!("{:#}", err));
+                state.cell_edit = Some(cell_edit);
+                return;
+            }
+        };
+        state.error = None;
+        let edit = cell_edit.edit;
+        state.staged.retain(|staged| !staged.same_cell(&edit));
+        if edit.new != edit.old {
+            state.staged.push(edit);
+        }
     }
 
-    pub fn get_current_query(&self) -> String {
+    pub fn cancel_cell_edit(&mut self) {
+        let mut state = self.inner.write().unwrap();
+        state.cell_edit = None;
+        state.dirty = true;
+    }
+
+    /// The staged values of the cells of the result on screen, by row and column index.
+    pub fn get_staged_cells(&self) -> HashMap<(usize, usize), Value> {
         let state = self.inner.read().unwrap();
-        let cc = self.get_active_connection_config().unwrap();
-        let ct = self.get_active_connection_type().unwrap();
-        match state.custom_queries.get(&cc.id) {
-            Some(query) => query.clone(),
-            None => ct.default_query_string(),
+        let mut cells = HashMap::new();
+        let (table, tab) = match (state.get_result_table(), state.get_active_tab()) {
+            (Some(table), Some(tab)) => (table, tab),
+            _ => return cells,
+        };
+        let index_of = |name: &String| tab.data.columns.iter().position(|c| c == name);
+        for edit in state.staged.iter().filter(|edit| edit.table == table) {
+            let column = match index_of(&edit.column) {
+                Some(column) => column,
+                None => continue,
+            };
+            let filter = state.filters.get(&types::WindowTypeID::QUERY);
+            let rows = tab
+                .data
+                .rows
+                .iter()
+                .filter(|row| filter.is_none_or(|filter| filter.matches(row, &[])));
+            for (idx, row) in rows.enumerate() {
+                let matches = edit.key.iter().all(|(name, value)| {
+                    index_of(name).and_then(|i| row.data.get(i)) == Some(value)
+                });
+                if matches {
+                    cells.insert((idx, column), edit.new.clone());
+                }
+            }
         }
+        cells
     }
 
-    pub fn update_custom_query(&mut self, query: Option<String>) {
-        let cc = self.get_active_connection_config();
-        if cc.is_err() {
+    /// Show the statements the staged changes are written with.
+    pub fn review_staged(&mut self) {
+        let mut state = self.inner.write().unwrap();
+        state.dirty = true;
+        if state.staged.is_empty() {
+            state.status = Some("There are no changes to review".to_string());
             return;
         }
-        let cc = cc.unwrap();
-        self.set_dirty(true);
-        let mut data = self.inner.write().unwrap();
-        if let Some(query) = query {
-            data.custom_queries.insert(cc.id.clone(), query);
-        } else if data.custom_queries.contains_key(&cc.id) {
-            data.custom_queries.remove(&cc.id);
+        state.reviewing = true;
+    }
+
+    pub fn is_reviewing(&self) -> bool {
+        self.inner.read().unwrap().reviewing
+    }
+
+    pub fn close_review(&mut self) {
+        let mut state = self.inner.write().unwrap();
+        state.reviewing = false;
+        state.dirty = true;
+    }
+
+    /// The staged changes as a diff: each UPDATE followed by the old and the new values,
+    /// marked with a `-` and a `+`.
+    pub fn get_review(&self) -> Result<Vec<(char, String)>> {
+        let db = self.get_active_connection_type()?;
+        let staged = self.inner.read().unwrap().staged.clone();
+        let mut lines = vec![];
+        for (change, edits) in staged_updates(db.as_ref(), &staged) {
+            lines.push((' ', format!("{};", change.display)));
+            for edit in edits {
+                lines.push((
+                    '-',
+                    format!("{} = {}", edit.column, db.sql_literal(&edit.old)),
+                ));
+                lines.push((
+                    '+',
+                    format!("{} = {}", edit.column, db.sql_literal(&edit.new)),
+                ));
+            }
+        }
+        Ok(lines)
+    }
+
+    /// Write the staged changes in one transaction, or as part of the open one.
+    pub fn commit_staged(&mut self) {
+        if self.refuse_while_running() {
+            return;
+        }
+        let updates = self.get_active_connection_type().map(|db| {
+            let staged = self.inner.read().unwrap().staged.clone();
+            staged_updates(db.as_ref(), &staged)
+                .into_iter()
+                .map(|(change, _)| change)
+                .collect::<Vec<_>>()
+        });
+        let updates = match updates {
+            Ok(updates) => updates,
+            Err(err) => {
+                let mut state = self.inner.write().unwrap();
+                state.error = Some(format!("{:#}", err));
+                state.dirty = true;
+                return;
+            }
+        };
+        self.spawn_changes(
+            updates,
+            "Writing the changes",
+            |state, result| match result {
+                Ok(updated) => {
+                    state.staged.clear();
+                    state.reviewing = false;
+                    state.status = Some(if state.in_transaction() {
+                        format!("{} rows updated in the open transaction", updated)
+                    } else {
+                        format!("{} rows updated", updated)
+                    });
+                }
+                Err(err) => {
+                    error!("Writing the changes failed: {:?}", err);
+                    state.error = Some(format!("{:#}", err));
+                }
+            },
+        );
+    }
+
+    /// Write row changes in the background, Control+c stops them and undoes what was
+    /// written. `done` reports the result, the query is run again after a change.
+    fn spawn_changes(
+        &self,
+        changes: Vec<RowChange>,
+        status: &str,
+        done: impl FnOnce(&mut LayoutStateInner, Result<u64>) + Send + 'static,
+    ) {
+        let (generation, in_transaction) = {
+            let mut state = self.inner.write().unwrap();
+            state.generation += 1;
+            state.running = true;
+            state.error = None;
+            state.status = Some(format!("{}... (Ctrl-C to cancel)", status));
+            state.dirty = true;
+            (state.generation, state.in_transaction())
+        };
+        let moved_state = Arc::clone(&self.inner);
+        std::thread::spawn(move || {
+            let cancelled = || moved_state.read().unwrap().generation != generation;
+            let db = moved_state.read().unwrap().get_active_connection_type();
+            let result =
+                db.and_then(|db| apply_changes(db.as_ref(), &changes, in_transaction, cancelled));
+            let reload_query = {
+                let mut state = moved_state.write().unwrap();
+                if state.generation != generation {
+                    return;
+                }
+                state.running = false;
+                state.status = None;
+                state.dirty = true;
+                let changed = result.is_ok();
+                done(&mut state, result);
+                changed && state.active_window == types::WindowTypeID::QUERY
+            };
+            if reload_query {
+                reload(&moved_state);
+            }
+        });
+    }
+
+    pub fn discard_staged(&mut self) {
+        let mut state = self.inner.write().unwrap();
+        state.staged.clear();
+        state.reviewing = false;
+        state.status = Some("Changes discarded".to_string());
+        state.dirty = true;
+    }
+
+    /// Open a form to insert a row into the table of the result, `duplicate` fills it in
+    /// with the row under the cursor.
+    pub fn insert_row(&mut self, duplicate: bool) {
+        if self.is_running() {
+            return;
+        }
+        if let Err(err) = self.open_row_form(duplicate) {
+            let mut state = self.inner.write().unwrap();
+            state.error = Some(format!("{:#}", err));
+            state.dirty = true;
+        }
+    }
+
+    fn open_row_form(&mut self, duplicate: bool) -> Result<()> {
+        let copy = if duplicate {
+            let (data, row, _) = self.get_current_cell()?;
+            Some((data.columns, row))
+        } else {
+            None
+        };
+        let (db, table) = {
+            let state = self.inner.read().unwrap();
+            let table = state
+                .get_result_table()
+                .ok_or(anyhow::anyhow!("Browse a table to insert rows into"))?;
+            (state.get_table_connection_type(&table)?, table)
+        };
+        let columns = db.list_columns()?;
+        if columns.is_empty() {
+            return Err(anyhow::anyhow!("The columns of {} are unknown", table));
+        }
+        let copied: Vec<Option<Value>> = columns
+            .iter()
+            .map(|column| match &copy {
+                // a copy of a generated key would clash with the original
+                Some(_) if column.primary_key && column.default.is_some() => None,
+                Some((names, row)) => names
+                    .iter()
+                    .position(|name| *name == column.name)
+                    .map(|idx| row.data[idx].clone()),
+                None => None,
+            })
+            .collect();
+        let inputs = copied
+            .iter()
+            .map(|value| value.as_ref().map(format_param).unwrap_or_default())
+            .collect();
+        let mut state = self.inner.write().unwrap();
+        state.row_form = Some(RowForm {
+            table,
+            columns,
+            inputs,
+            copied,
+            current: 0,
+        });
+        state.error = None;
+        state.dirty = true;
+        Ok(())
+    }
+
+    pub fn get_row_form(&self) -> Option<RowForm> {
+        self.inner.read().unwrap().row_form.clone()
+    }
+
+    /// Type into the current field of the form, `None` deletes the last character.
+    pub fn type_row_form(&mut self, c: Option<char>) {
+        let mut state = self.inner.write().unwrap();
+        if let Some(form) = state.row_form.as_mut() {
+            let input = &mut form.inputs[form.current];
+            match c {
+                Some(c) => input.push(c),
+                None => {
+                    input.pop();
+                }
+            }
+            state.dirty = true;
+        }
+    }
+
+    /// Move to the next field, past the last one the row is inserted.
+    pub fn next_row_form_field(&mut self) {
+        {
+            let mut state = self.inner.write().unwrap();
+            state.dirty = true;
+            match state.row_form.as_mut() {
+                Some(form) if form.current + 1 < form.inputs.len() => {
+                    form.current += 1;
+                    return;
+                }
+                Some(_) => {}
+                None => return,
+            }
+        }
+        self.submit_row_form();
+    }
+
+    pub fn previous_row_form_field(&mut self) {
+        let mut state = self.inner.write().unwrap();
+        if let Some(form) = state.row_form.as_mut() {
+            form.current = form.current.saturating_sub(1);
+            state.dirty = true;
         }
     }
+
+    pub fn cancel_row_form(&mut self) {
+        let mut state = self.inner.write().unwrap();
+        state.row_form = None;
+        state.dirty = true;
+    }
+
+    fn submit_row_form(&mut self) {
+        if self.refuse_while_running() {
+            return;
+        }
+        let mut form = match self.get_row_form() {
+            Some(form) => form,
+            None => return,
+        };
+        let mut values = vec![];
+        let mut failed = None;
+        for (idx, (column, input)) in form.columns.iter().zip(form.inputs.iter()).enumerate() {
+            if input.is_empty() {
+                continue;
+            }
+            let value = match &form.copied[idx] {
+                Some(copied) => parse_edit(input, copied),
+                None => parse_param(input),
+            };
+            match value {
+                Ok(value) => values.push((column.name.clone(), value)),
+                Err(err) => {
+                    failed = Some((idx, anyhow::anyhow!("{}: {:#}", column.name, err)));
+                    break;
+                }
+            }
+        }
+        if values.is_empty() && failed.is_none() {
+            failed = Some((
+                0,
+                anyhow::anyhow!("Fill in a value for at least one column"),
+            ));
+        }
+        let insert = match failed {
+            Some((idx, err)) => {
+                form.current = idx;
+                Err(err)
+            }
+            None => self
+                .get_active_connection_type()
+                .map(|db| row_insert(db.as_ref(), &form.table, &values)),
+        };
+        let insert = match insert {
+            Ok(insert) => insert,
+            Err(err) => {
+                let mut state = self.inner.write().unwrap();
+                state.error = Some(format!("{:#}", err));
+                state.row_form = Some(form);
+                state.dirty = true;
+                return;
+            }
+        };
+        // the form is closed while the row is written
+        self.inner.write().unwrap().row_form = None;
+        self.spawn_changes(vec![insert], "Inserting the row", move |state, result| {
+            match result {
+                Ok(_) => state.status = Some(format!("1 row inserted into {}", form.table)),
+                Err(err) => {
+                    error!("Inserting the row failed: {:?}", err);
+                    state.error = Some(format!("{:#}", err));
+                    // keep what was typed to correct it
+                    state.row_form = Some(form);
+                }
+            }
+        });
+    }
+
+    /// Ask to delete the selected rows of the result, or the one under the cursor.
+    pub fn delete_rows(&mut self) {
+        if self.is_running() {
+            return;
+        }
+        let result = self.prepare_delete();
+        let mut state = self.inner.write().unwrap();
+        state.dirty = true;
+        match result {
+            Ok(deletes) => state.pending_delete = Some(deletes),
+            Err(err) => state.error = Some(format!("{:#}", err)),
+        }
+    }
+
+    fn prepare_delete(&self) -> Result<Vec<RowChange>> {
+        let data = self.get_window_data();
+        let selected = self
+            .inner
+            .read()
+            .unwrap()
+            .get_selection(types::WindowTypeID::QUERY)
+            .unwrap_or_default();
+        let rows = if selected.is_empty() {
+            let (_, row, _) = self.get_current_cell()?;
+            vec![row]
+        } else {
+            selected_rows(&data.rows, &selected)
+        };
+        let (db, table) = {
+            let state = self.inner.read().unwrap();
+            (
+                state.get_active_connection_type()?,
+                state.get_result_table(),
+            )
+        };
+        let table = table.ok_or(anyhow::anyhow!(
+            "Browse a table to delete its rows, the results of a custom query can not be deleted"
+        ))?;
+        let primary_key = primary_key(db.as_ref(), &table)?;
+        let keys = rows
+            .iter()
+            .map(|row| row_key(&primary_key, &data.columns, &row.data))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(row_deletes(db.as_ref(), &table, &keys))
+    }
+
+    /// The statements deleting the rows waiting to be confirmed.
+    pub fn get_pending_delete(&self) -> Option<Vec<String>> {
+        self.inner
+            .read()
+            .unwrap()
+            .pending_delete
+            .as_ref()
+            .map(|deletes| {
+                deletes
+                    .iter()
+                    .map(|delete| format!("{};", delete.display))
+                    .collect()
+            })
+    }
+
+    /// Delete the rows waiting to be confirmed, or forget about them.
+    pub fn confirm_delete(&mut self, confirmed: bool) {
+        let deletes = {
+            let mut state = self.inner.write().unwrap();
+            state.dirty = true;
+            match state.pending_delete.take() {
+                Some(deletes) if confirmed => deletes,
+                _ => return,
+            }
+        };
+        if self.refuse_while_running() {
+            return;
+        }
+        self.spawn_changes(deletes, "Deleting the rows", |state, result| match result {
+            Ok(deleted) => state.status = Some(format!("{} rows deleted", deleted)),
+            Err(err) => {
+                error!("Deleting the rows failed: {:?}", err);
+                state.error = Some(format!("{:#}", err));
+            }
+        });
+    }
+
+    /// Write the data on screen to a file, `args` are those of the `:export` command.
+    pub fn export(&mut self, args: &str) {
+        if self.refuse_while_running() {
+            return;
+        }
+        let result = self.start_export(args);
+        let mut state = self.inner.write().unwrap();
+        state.dirty = true;
+        if let Err(err) = result {
+            state.error = Some(format!("{:#}", err));
+        }
+    }
+
+    fn start_export(&mut self, args: &str) -> Result<()> {
+        let mut export = parse_export(args)?;
+        let (db, rerun) = {
+            let state = self.inner.read().unwrap();
+            if export.format == ExportFormat::Sql && export.table.is_none() {
+                export.table = state.get_result_table();
+            }
+            // only results with more pages to fetch are run again for the rest of their rows
+            let rerun = match state.get_active_tab() {
+                Some(tab)
+                    if state.active_window == types::WindowTypeID::QUERY
+                        && tab.data.next_page.is_some()
+                        && !export.loaded_only =>
+                {
+                    Some((tab.statement.clone(), tab.params.clone()))
+                }
+                _ => None,
+            };
+            (state.get_active_connection_type()?, rerun)
+        };
+
+        if let Some((statement, params)) = rerun {
+            let generation = {
+                let mut state = self.inner.write().unwrap();
+                state.generation += 1;
+                state.running = true;
+                state.error = None;
+                state.status = Some(format!("Exporting to {}...", export.path.display()));
+                state.generation
+            };
+            let moved_state = Arc::clone(&self.inner);
+            std::thread::spawn(move || {
+                safely_export(moved_state, generation, export, statement, params);
+            });
+            return Ok(());
+        }
+
+        let data = self.get_window_data();
+        let mut exporter = Exporter::create(&export, data.columns.clone(), db.as_ref())?;
+        for row in data.rows.iter() {
+            exporter.write_row(&row.data)?;
+        }
+        let rows = exporter.finish()?;
+        let mut state = self.inner.write().unwrap();
+        state.error = None;
+        state.status = Some(format!(
+            "Exported {} rows to {}",
+            rows,
+            export.path.display()
+        ));
+        Ok(())
+    }
+
+    /// Read a file to import into the table under the cursor, `args` are those of
+    /// the `:import` command. The rows are written once the columns are mapped.
+    pub fn import(&mut self, args: &str) {
+        if self.is_running() {
+            return;
+        }
+        let result = self.open_import(args);
+        let mut state = self.inner.write().unwrap();
+        state.dirty = true;
+        match result {
+            Ok(plan) => {
+                state.import = Some(plan);
+                state.error = None;
+            }
+            Err(err) => state.error = Some(format!("{:#}", err)),
+        }
+    }
+
+    fn open_import(&self, args: &str) -> Result<ImportPlan> {
+        if self.get_active_window().id() != types::WindowTypeID::TABLES {
+            return Err(anyhow::anyhow!("Import from the tables window"));
+        }
+        let options = parse_import(args)?;
+        let table = self
+            .get_current_row_value()
+            .or(self
+                .inner
+                .read()
+                .unwrap()
+                .get_active(types::WindowTypeID::TABLES))
+            .ok_or(anyhow::anyhow!("Move to the table to import into"))?;
+        let file = read_file(&options)?;
+        let columns = self
+            .inner
+            .read()
+            .unwrap()
+            .get_table_connection_type(&table)?
+            .list_columns()?;
+        if columns.is_empty() {
+            return Err(anyhow::anyhow!("The columns of {} are unknown", table));
+        }
+        Ok(ImportPlan::new(table, options.path, file, columns))
+    }
+
+    pub fn get_import(&self) -> Option<ImportPlan> {
+        self.inner.read().unwrap().import.clone()
+    }
+
+    /// Change the import plan waiting to be started.
+    pub fn update_import(&mut self, update: impl FnOnce(&mut ImportPlan)) {
+        let mut state = self.inner.write().unwrap();
+        if let Some(plan) = state.import.as_mut() {
+            update(plan);
+            state.dirty = true;
+        }
+    }
+
+    pub fn cancel_import(&mut self) {
+        let mut state = self.inner.write().unwrap();
+        state.import = None;
+        state.dirty = true;
+    }
+
+    /// Start writing the rows of the file, Control+c stops it and undoes what was written.
+    pub fn run_import(&mut self) {
+        let mut state = self.inner.write().unwrap();
+        state.dirty = true;
+        let plan = match state.import.take() {
+            Some(plan) => plan,
+            None => return,
+        };
+        if plan.target_columns().is_empty() {
+            state.error = Some("Map a file column to at least one table column".to_string());
+            state.import = Some(plan);
+            return;
+        }
+        state.generation += 1;
+        state.running = true;
+        state.error = None;
+        state.status = Some(format!("Importing into {}...", plan.table));
+        let generation = state.generation;
+        let moved_state = Arc::clone(&self.inner);
+        std::thread::spawn(move || {
+            safely_import(moved_state, generation, plan);
+        });
+    }
+
+    /// Return to the result on screen before the last foreign key was followed.
+    pub fn go_back(&mut self) {
+        if self.inner.read().unwrap().followed.is_empty() {
+            return;
+        }
+        self.cancel();
+        let saved = {
+            let mut state = self.inner.write().unwrap();
+            let saved = match state.followed.pop() {
+                Some(saved) => saved,
+                None => return,
+            };
+            state.query_tabs = saved.tabs;
+            state.active_tab = saved.active_tab;
+            match saved.sort {
+                Some(sort) => state.sorts.insert(types::WindowTypeID::QUERY, sort),
+                None => state.sorts.remove(&types::WindowTypeID::QUERY),
+            };
+            state.del_active(types::WindowTypeID::QUERY);
+            state.error = None;
+            state.status = None;
+            state.dirty = true;
+            (saved.position, saved.column)
+        };
+        self.positions.insert(types::WindowTypeID::QUERY, saved.0);
+        self.columns.insert(types::WindowTypeID::QUERY, saved.1);
+    }
+
+    fn leaves_transaction(&self, value: &str) -> bool {
+        let state = self.inner.read().unwrap();
+        let window = state.active_window;
+        [
+            types::WindowTypeID::CONNECTIONS,
+            types::WindowTypeID::DATABASES,
+        ]
+        .contains(&window)
+            && state.get_active(window).as_deref() != Some(value)
+            && state.in_transaction()
+    }
+
+    pub fn in_transaction(&self) -> bool {
+        self.inner.read().unwrap().in_transaction()
+    }
+
+    /// Open a transaction on the active connection, or ask how to end the open one.
+    pub fn toggle_transaction(&mut self) {
+        if self.in_transaction() {
+            self.inner.write().unwrap().pending = Some(PendingAction::EndTransaction);
+            return;
+        }
+        if self.refuse_while_running() {
+            return;
+        }
+        let result = self
+            .get_active_connection_type()
+            .and_then(|db| db.begin_transaction());
+        self.report_transaction(result, "Transaction started");
+    }
+
+    /// Commit or roll back the open transaction, returns whether it ended.
+    pub fn end_transaction(&mut self, commit: bool) -> bool {
+        if !self.in_transaction() || self.refuse_while_running() {
+            return false;
+        }
+        let result = self.get_active_connection_type().and_then(|db| {
+            if commit {
+                db.commit_transaction()
+            } else {
+                db.rollback_transaction()
+            }
+        });
+        let message = if commit {
+            "Transaction committed"
+        } else {
+            "Transaction rolled back"
+        };
+        self.report_transaction(result, message)
+    }
+
+    // the running query holds the session, ending the transaction would wait for it
+    fn refuse_while_running(&self) -> bool {
+        let mut state = self.inner.write().unwrap();
+        let busy = [types::WindowTypeID::QUERY, types::WindowTypeID::PLAN];
+        if state.running && busy.contains(&state.active_window) {
+            state.error = Some("A query is running, cancel it with Control+c first".to_string());
+            state.dirty = true;
+            return true;
+        }
+        false
+    }
+
+    fn report_transaction(&mut self, result: Result<()>, message: &str) -> bool {
+        let mut state = self.inner.write().unwrap();
+        state.dirty = true;
+        match result {
+            Ok(()) => {
+                state.error = None;
+                state.status = Some(message.to_string());
+                true
+            }
+            Err(err) => {
+                error!("Transaction failed: {:?}", err);
+                state.error = Some(format!("{:#}", err));
+                false
+            }
+        }
+    }
+
+    /// Settle the pending action: commit or roll back first, `None` keeps the transaction open.
+    pub fn resolve_pending(&mut self, commit: Option<bool>) {
+        let pending = self.inner.write().unwrap().pending.take();
+        let (pending, commit) = match (pending, commit) {
+            (Some(pending), Some(commit)) => (pending, commit),
+            _ => {
+                self.set_dirty(true);
+                return;
+            }
+        };
+        if !self.end_transaction(commit) {
+            return;
+        }
+        match pending {
+            PendingAction::EndTransaction => {}
+            PendingAction::Quit => self.inner.write().unwrap().quit = true,
+            PendingAction::Select { value, open } => {
+                if open {
+                    self.open_value(value);
+                } else {
+                    self.select_value(value);
+                }
+            }
+        }
+    }
+
+    /// Whether the app can quit right away, otherwise the user is asked about the open transaction.
+    pub fn request_quit(&mut self) -> bool {
+        if !self.in_transaction() {
+            return true;
+        }
+        let mut state = self.inner.write().unwrap();
+        state.pending = Some(PendingAction::Quit);
+        state.dirty = true;
+        false
+    }
+
+    pub fn should_quit(&self) -> bool {
+        self.inner.read().unwrap().quit
+    }
+
+    pub fn get_pending(&self) -> Option<PendingAction> {
+        self.inner.read().unwrap().pending.clone()
+    }
+
+    pub fn get_active_window(&self) -> types::WindowType {
+        types::get_window(self.inner.read().unwrap().active_window)
+    }
+
+    /// The rows of the active window that pass its filter.
+    pub fn get_window_data(&self) -> WindowData {
+        let mut data = self.get_unfiltered_window_data();
+        let state = self.inner.read().unwrap();
+        if let Some(filter) = state.filters.get(&state.active_window) {
+            let sizes = size_columns(state.active_window, &data.columns);
+            data.rows.retain(|row| filter.matches(row, &sizes));
+        }
+        data
+    }
+
+    /// The number of rows of the active window before they are filtered.
+    pub fn get_total_rows(&self) -> usize {
+        self.get_unfiltered_window_data().rows.len()
+    }
+
+    fn get_unfiltered_window_data(&self) -> WindowData {
+        let state = self.inner.read().unwrap();
+        if state.active_window == types::WindowTypeID::QUERY {
+            return match state.get_active_tab() {
+                Some(tab) => tab.data.clone(),
+                None => WindowData::empty(),
+            };
+        }
+        if state.active_window == types::WindowTypeID::PLAN {
+            let mut rows = vec![];
+            if let Some(plan) = state.plan.as_ref() {
+                plan_rows(plan, "0".to_string(), 0, &state.plan_collapsed, &mut rows);
+            }
+            return WindowData {
+                columns: vec![
+                    "Node".to_string(),
+                    "Cost".to_string(),
+                    "Rows".to_string(),
+                    "Actual Rows".to_string(),
+                    "Actual Time (ms)".to_string(),
+                    "Detail".to_string(),
+                ],
+                rows,
+                next_page: None,
+            };
+        }
+        match state.data.get(&state.active_window) {
+            Some(items) => items.clone(),
+            None => WindowData::empty(),
+        }
+    }
+
+    /// The plan nodes that take the most by themselves, by actual time when analyzed.
+    pub fn get_plan_hotspots(&self) -> Vec<String> {
+        let state = self.inner.read().unwrap();
+        let mut costs = vec![];
+        if let Some(plan) = state.plan.as_ref() {
+            plan_costs(plan, "0".to_string(), &mut costs);
+        }
+        costs.retain(|(_, cost)| *cost > 0.0);
+        costs.sort_by(|a, b| b.1.total_cmp(&a.1));
+        costs
+            .into_iter()
+            .take(PLAN_HOTSPOTS)
+            .map(|(path, _)| path)
+            .collect()
+    }
+
+    /// Show or hide the children of the plan node under the cursor.
+    pub fn toggle_plan_node(&mut self) {
+        let path = match self.get_current_row_value() {
+            Some(path) => path,
+            None => return,
+        };
+        let mut state = self.inner.write().unwrap();
+        if !state.plan_collapsed.remove(&path) {
+            state.plan_collapsed.insert(path);
+        }
+        state.dirty = true;
+    }
+
+    /// Show the query result row under the cursor one column per line.
+    pub fn open_record(&mut self) {
+        if self.get_current_row_value().is_none() {
+            return;
+        }
+        let mut state = self.inner.write().unwrap();
+        state.record = Some(RecordView::default());
+        state.dirty = true;
+    }
+
+    pub fn close_record(&mut self) {
+        let mut state = self.inner.write().unwrap();
+        state.record = None;
+        state.dirty = true;
+    }
+
+    pub fn get_record(&self) -> Option<RecordView> {
+        let state = self.inner.read().unwrap();
+        state
+            .record
+            .clone()
+            .filter(|_| state.active_window == types::WindowTypeID::QUERY)
+    }
+
+    pub fn set_record_line(&mut self, line: usize) {
+        let mut state = self.inner.write().unwrap();
+        if let Some(record) = state.record.as_mut() {
+            record.line = line;
+        }
+        state.dirty = true;
+    }
+
+    /// Hide or show the JSON object or array with the folding key.
+    pub fn toggle_record_fold(&mut self, fold: String) {
+        let mut state = self.inner.write().unwrap();
+        if let Some(record) = state.record.as_mut() {
+            if !record.folded.remove(&fold) {
+                record.folded.insert(fold);
+            }
+        }
+        state.dirty = true;
+    }
+
+    pub fn get_filter(&self) -> Option<Filter> {
+        let state = self.inner.read().unwrap();
+        state.filters.get(&state.active_window).cloned()
+    }
+
+    /// Filter the rows of the active window, or show them all again without a filter.
+    /// The cursor goes back to the first row that matches.
+    pub fn set_filter(&mut self, filter: Option<Filter>) {
+        {
+            let mut state = self.inner.write().unwrap();
+            let window = state.active_window;
+            match filter {
+                Some(filter) if !filter.is_empty() => state.filters.insert(window, filter),
+                _ => state.filters.remove(&window),
+            };
+            state.record = None;
+        }
+        self.set_position(0);
+    }
+
+    /// Ask for a condition to filter the browsed table by, the columns of the table are
+    /// completed.
+    pub fn open_where_prompt(&mut self) {
+        if self.is_running() {
+            return;
+        }
+        if let Err(err) = self.start_where_prompt() {
+            let mut state = self.inner.write().unwrap();
+            state.error = Some(format!("{:#}", err));
+            state.dirty = true;
+        }
+    }
+
+    fn start_where_prompt(&mut self) -> Result<()> {
+        let (db, table) = {
+            let state = self.inner.read().unwrap();
+            if state.get_custom_query().is_some() {
+                return Err(anyhow::anyhow!(
+                    "Only the browsed table is filtered, add the condition to the query instead"
+                ));
+            }
+            if !state.followed.is_empty() {
+                return Err(anyhow::anyhow!("Go back to the browsed table to filter it"));
+            }
+            let table = state
+                .get_active(types::WindowTypeID::TABLES)
+                .ok_or(anyhow::anyhow!("Select a table to filter"))?;
+            let db = state.get_table_connection_type(&table)?;
+            if !db.supports_where() {
+                return Err(anyhow::anyhow!(
+                    "Filtering tables is not supported for this connection"
+                ));
+            }
+            (db, table)
+        };
+        let columns = db.list_columns()?;
+        let mut state = self.inner.write().unwrap();
+        state.where_prompt = Some(WherePrompt {
+            table,
+            columns,
+            input: String::new(),
+            current: None,
+            error: None,
+        });
+        state.error = None;
+        state.dirty = true;
+        Ok(())
+    }
+
+    pub fn get_where_prompt(&self) -> Option<WherePrompt> {
+        self.inner.read().unwrap().where_prompt.clone()
+    }
+
+    pub fn get_conditions(&self) -> Vec<String> {
+        self.inner.read().unwrap().conditions.clone()
+    }
+
+    /// Type into the condition, `None` deletes the last character.
+    pub fn type_where(&mut self, c: Option<char>) {
+        let mut state = self.inner.write().unwrap();
+        if let Some(prompt) = state.where_prompt.as_mut() {
+            match c {
+                Some(c) => prompt.input.push(c),
+                None => {
+                    prompt.input.pop();
+                }
+            }
+            prompt.current = None;
+            prompt.error = None;
+            state.dirty = true;
+        }
+    }
+
+    /// Complete the last word typed to a column name, as far as the names it could be agree.
+    pub fn complete_where_column(&mut self) {
+        let mut state = self.inner.write().unwrap();
+        let prompt = match state.where_prompt.as_mut() {
+            Some(prompt) => prompt,
+            None => return,
+        };
+        let completions = prompt.completions();
+        let completed = match completions.as_slice() {
+            [] => return,
+            [name] => format!("{} ", name),
+            names => names[1..].iter().fold(names[0].clone(), |common, name| {
+                common
+                    .chars()
+                    .zip(name.chars())
+                    .take_while(|(a, b)| a == b)
+                    .map(|(a, _)| a)
+                    .collect()
+            }),
+        };
+        let word = prompt.input.split_whitespace().last().unwrap_or_default();
+        if completed.trim_end().chars().count() >= word.chars().count() {
+            let start = prompt.input.len() - word.len();
+            prompt.input.replace_range(start.., &completed);
+        }
+        state.dirty = true;
+    }
+
+    /// Pick the condition above or below to remove, past the last one goes back to typing.
+    pub fn move_where_condition(&mut self, up: bool) {
+        let mut state = self.inner.write().unwrap();
+        let count = state.conditions.len();
+        if let Some(prompt) = state.where_prompt.as_mut() {
+            prompt.current = match (prompt.current, up) {
+                _ if count == 0 => None,
+                (None, true) => Some(count - 1),
+                (Some(0), true) => Some(0),
+                (Some(current), true) => Some(current - 1),
+                (Some(current), false) if current + 1 < count => Some(current + 1),
+                (_, false) => None,
+            };
+            state.dirty = true;
+        }
+    }
+
+    /// Remove the condition picked in the prompt, the table is read again without it.
+    pub fn remove_where_condition(&mut self) {
+        {
+            let mut state = self.inner.write().unwrap();
+            let current = match state.where_prompt.as_ref().and_then(|p| p.current) {
+                Some(current) if current < state.conditions.len() => current,
+                _ => return,
+            };
+            state.conditions.remove(current);
+            let count = state.conditions.len();
+            if let Some(prompt) = state.where_prompt.as_mut() {
+                prompt.current = Some(current.min(count.saturating_sub(1))).filter(|_| count > 0);
+            }
+        }
+        self.refilter();
+    }
+
+    /// Add the typed condition to the WHERE clause and read the table again.
+    pub fn confirm_where(&mut self) {
+        let condition = {
+            let mut state = self.inner.write().unwrap();
+            state.dirty = true;
+            let prompt = match state.where_prompt.as_ref() {
+                Some(prompt) => prompt,
+                None => return,
+            };
+            if prompt.input.trim().is_empty() {
+                state.where_prompt = None;
+                return;
+            }
+            let condition = state
+                .get_table_connection_type(&prompt.table)
+                .and_then(|db| where_condition(&prompt.input, &prompt.columns, db.as_ref()));
+            match condition {
+                Ok(condition) => condition,
+                Err(err) => {
+                    if let Some(prompt) = state.where_prompt.as_mut() {
+                        prompt.error = Some(format!("{:#}", err));
+                    }
+                    return;
+                }
+            }
+        };
+        {
+            let mut state = self.inner.write().unwrap();
+            state.conditions.push(condition);
+            state.where_prompt = None;
+        }
+        self.refilter();
+    }
+
+    pub fn cancel_where(&mut self) {
+        let mut state = self.inner.write().unwrap();
+        state.where_prompt = None;
+        state.dirty = true;
+    }
+
+    fn refilter(&mut self) {
+        self.positions.insert(types::WindowTypeID::QUERY, 0);
+        spawn_pull(&self.inner, None);
+    }
+
+    pub fn get_sort(&self) -> Option<Sort> {
+        let state = self.inner.read().unwrap();
+        state.sorts.get(&state.active_window).cloned()
+    }
+
+    /// Sort by the column under the cursor, ascending, then descending, then not at all.
+    /// A browsed table runs again sorted by the server, other results sort the rows loaded.
+    pub fn sort_column(&mut self) {
+        let window = self.get_active_window();
+        if window.selection_type() == types::ItemSelectionType::NONE || self.is_running() {
+            return;
+        }
+        let columns = self.get_window_data().columns;
+        let column = match columns.get(self.get_column_position()) {
+            Some(column) => column.clone(),
+            None => return,
+        };
+        let query = window.id() == types::WindowTypeID::QUERY;
+        let server = query && {
+            let state = self.inner.read().unwrap();
+            state.app.get_settings().sort_on_server
+                && state.get_custom_query().is_none()
+                && state.followed.is_empty()
+                && state
+                    .get_active_connection_type()
+                    .map(|db| db.supports_order_by())
+                    .unwrap_or(false)
+        };
+        let sort = match self.get_sort() {
+            Some(sort) if sort.column == column && sort.descending => None,
+            Some(sort) if sort.column == column => Some(Sort {
+                descending: true,
+                ..sort
+            }),
+            _ => Some(Sort {
+                column,
+                descending: false,
+                server,
+            }),
+        };
+        let mut state = self.inner.write().unwrap();
+        state.dirty = true;
+        let unsorted = match sort {
+            Some(sort) if sort.server => {
+                state.sorts.insert(window.id(), sort);
+                true
+            }
+            Some(sort) if query => {
+                state.sorts.insert(window.id(), sort);
+                state.sort_query_tabs();
+                false
+            }
+            Some(sort) => {
+                if let Some(data) = state.data.get_mut(&window.id()) {
+                    sort_rows(&data.columns, &mut data.rows, &sort);
+                }
+                state.sorts.insert(window.id(), sort);
+                false
+            }
+            None => match state.sorts.remove(&window.id()) {
+                Some(sort) if query && !sort.server => {
+                    // query rows are numbered in the order they came in
+                    for tab in state.query_tabs.iter_mut() {
+                        tab.data
+                            .rows
+                            .sort_by_key(|row| row.id.parse::<usize>().unwrap_or(usize::MAX));
+                    }
+                    false
+                }
+                _ => true,
+            },
+        };
+        drop(state);
+        // the order the rows came in is only known by getting them again
+        if unsorted {
+            spawn_pull(&self.inner, None);
+        }
+    }
+
+    /// Switch between the estimated plan and running the statement to analyze it.
+    pub fn toggle_plan_analyze(&mut self) {
+        {
+            let mut state = self.inner.write().unwrap();
+            state.plan_analyze = !state.plan_analyze;
+        }
+        spawn_pull(&self.inner, None);
+    }
+
+    /// Switch between the results of the statements of a script, wrapping around.
+    pub fn switch_tab(&mut self, forward: bool) {
+        {
+            let mut state = self.inner.write().unwrap();
+            let count = state.query_tabs.len();
+            if state.active_window != types::WindowTypeID::QUERY || count < 2 {
+                return;
+            }
+            state.active_tab = if forward {
+                (state.active_tab + 1) % count
+            } else {
+                (state.active_tab + count - 1) % count
+            };
+            // selected rows belong to the result they were picked in
+            state.del_active(types::WindowTypeID::QUERY);
+        }
+        self.set_position(-1);
+    }
+
+    pub fn refresh(&mut self) {
+        spawn_pull(&self.inner, None);
+    }
+
+    /// Pull the data on screen again, results reached by following keys are looked up again.
+    pub fn reload(&mut self) {
+        reload(&self.inner);
+    }
+
+    pub fn get_connections(&self) -> Vec<Connection> {
+        self.inner.read().unwrap().app.get_connections().clone()
+    }
+
+    pub fn get_active_connection_config(&self) -> Result<Connection> {
+        self.inner.read().unwrap().get_active_connection_config()
+    }
+    pub fn get_active_connection_type(&self) -> Result<Box<dyn ConnectionType>> {
+        self.inner.read().unwrap().get_active_connection_type()
+    }
+
+    pub fn get_current_query(&self) -> String {
+        let state = self.inner.read().unwrap();
+        let ct = self.get_active_connection_type().unwrap();
+        state.get_script(ct.as_ref())
+    }
+
+    pub fn update_custom_query(&mut self, query: Option<String>) {
+        let cc = self.get_active_connection_config();
+        if cc.is_err() {
+            return;
+        }
+        let cc = cc.unwrap();
+        self.set_dirty(true);
+        let mut data = self.inner.write().unwrap();
+        // an edited query starts from the one with the WHERE clause and ORDER BY written out
+        data.sorts.remove(&types::WindowTypeID::QUERY);
+        data.conditions.clear();
+        if let Some(query) = query {
+            data.custom_queries.insert(cc.id.clone(), query);
+        } else if data.custom_queries.contains_key(&cc.id) {
+            data.custom_queries.remove(&cc.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connectiontypes::base::QueryResultRow;
+
+    #[test]
+    fn rows_are_selected_by_position() {
+        let rows = [(1, "a"), (1, "b"), (0, "c"), (0, "d")]
+            .iter()
+            .map(|(id, name)| QueryResultRow {
+                data: vec![
+                    if *id == 0 {
+                        Value::Null
+                    } else {
+                        Value::Int(*id)
+                    },
+                    Value::Text(name.to_string()),
+                ],
+            })
+            .collect();
+        let results = QueryResult {
+            rows,
+            ..QueryResult::empty()
+        };
+        let tab = QueryTab::new("SELECT * FROM t", vec![], results);
+        let names = |selected: &[&str]| {
+            let selected = selected.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+            selected_rows(&tab.data.rows, &selected)
+                .into_iter()
+                .map(|row| row.data[1].to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(&["1"]), vec!["b"]);
+        assert_eq!(names(&["3", "0"]), vec!["a", "d"]);
+        assert!(names(&["NULL"]).is_empty());
+    }
 }
@@ -1,55 +1,91 @@
-use crate::app::Application;
 use crossterm::event::Event;
 use ratatui::style::Color;
 use ratatui::text::{Line, Span};
-use ratatui::widgets::{Paragraph, Block, Borders};
-use ratatui::{
-    layout::Rect,
-    style::Style,
-    Frame,
-};
+use ratatui::widgets::{Block, Borders, Paragraph};
+use ratatui::{layout::Rect, style::Style, Frame};
 use std::{sync::Arc, sync::RwLock};
 
 use crate::ui::state::LayoutState;
+use crate::ui::types;
+
+const MAX_NOTICES: usize = 5;
+
+struct StatusMessage {
+    title: &'static str,
+    lines: Vec<(String, Color)>,
+}
 
 pub struct StatusArea {
-    app: Arc<Application>,
     state: Arc<RwLock<LayoutState>>,
 }
 
 impl StatusArea {
-    pub fn new(app: Arc<Application>, state: Arc<RwLock<LayoutState>>) -> StatusArea {
-        StatusArea { app, state }
+    pub fn new(state: Arc<RwLock<LayoutState>>) -> StatusArea {
+        StatusArea { state }
     }
 
-    fn get_error(&self) -> Option<String> {
+    fn get_message(&self) -> Option<StatusMessage> {
         let state = self.state.read().unwrap();
         let inner = state.inner.read().unwrap();
-        inner.error.clone()
+        if let Some(error) = inner.error.clone() {
+            return Some(StatusMessage {
+                title: "Error",
+                lines: vec![(error, Color::Green)],
+            });
+        }
+        if let Some(status) = inner.status.clone() {
+            return Some(StatusMessage {
+                title: "Status",
+                lines: vec![(status, Color::Yellow)],
+            });
+        }
+        if inner.active_window != types::WindowTypeID::QUERY {
+            return None;
+        }
+        let tab = inner.get_active_tab()?;
+        if let Some(error) = tab.error.clone() {
+            return Some(StatusMessage {
+                title: "Error",
+                lines: vec![(error, Color::Green)],
+            });
+        }
+        let outcome = tab.outcome.as_ref()?;
+        let mut lines = vec![(outcome.to_string(), Color::Green)];
+        lines.extend(
+            outcome
+                .notices
+                .iter()
+                .take(MAX_NOTICES)
+                .map(|notice| (notice.clone(), Color::Yellow)),
+        );
+        Some(StatusMessage {
+            title: "Result",
+            lines,
+        })
     }
 
     pub fn layout_size(&self) -> u16 {
-        if self.get_error().is_some() {
-            3
-        } else {
-            0
+        match self.get_message() {
+            Some(message) => message.lines.len() as u16 + 2,
+            None => 0,
         }
     }
 
-    pub fn render(&mut self, frame: &mut Frame, rect: Rect, event: Option<Event>) {
-        let error = self.get_error();
-        if error.is_none() {
-            return;
-        }
-        let lines = vec![Line::from(Span::styled(
-            error.unwrap(),
-            Style::default().fg(Color::Green),
-        ))];
+    pub fn render(&mut self, frame: &mut Frame, rect: Rect, _event: Option<Event>) {
+        let message = match self.get_message() {
+            Some(message) => message,
+            None => return,
+        };
+        let lines = message
+            .lines
+            .into_iter()
+            .map(|(text, color)| Line::from(Span::styled(text, Style::default().fg(color))))
+            .collect::<Vec<_>>();
         let para = Paragraph::new(lines).block(
             Block::default()
                 .borders(Borders::ALL)
                 .style(Style::default())
-                .title("Error"),
+                .title(message.title),
         );
         frame.render_widget(para, rect);
     }
@@ -1,12 +1,9 @@
-use crate::app::Application;
-use chrono::format;
 use crossterm::event::Event;
 use ratatui::prelude::Constraint;
 use ratatui::text::{Line, Span};
 use ratatui::{
     layout::Rect,
-    style::{Color, Style},
-    text::Text,
+    style::{Color, Modifier, Style},
     widgets::{Row, Table},
     Frame,
 };
@@ -18,11 +15,12 @@ use ratatui::layout::{Alignment, Direction, Layout};
 use super::types;
 
 pub struct TopArea {
-    app: Arc<Application>,
     state: Arc<RwLock<LayoutState>>,
 }
 
-static SHORTCUTS: [(&str, &str); 14] = [
+const SHORTCUT_COLUMNS: usize = 5;
+
+static SHORTCUTS: [(&str, &str); 28] = [
     ("j", "Down"),
     ("k", "Up"),
     ("Control-j", "Bottom"),
@@ -31,14 +29,48 @@ static SHORTCUTS: [(&str, &str); 14] = [
     ("space", "Select"),
     ("enter", "Open"),
     (":", "Command"),
+    ("/", "Filter"),
     ("n", "New"),
     ("d", "Delete"),
     ("e", "Edit"),
+    ("y", "Copy cell"),
+    ("Y", "Copy row"),
+    ("h/l", "Move column"),
+    ("</>", "Resize column"),
+    ("-/=", "Hide/reset columns"),
+    ("z", "Freeze columns"),
+    ("s", "Sort"),
     ("Esc", "Cancel"),
     ("Control+r", "Refresh"),
     ("Control+s", "Save"),
+    ("Control+c", "Cancel query"),
+    ("Tab", "Next result"),
+    ("Shift+Tab", "Previous result"),
+    ("Control+t", "Transaction"),
+    ("Control+y", "Commit"),
+    ("Control+z", "Rollback"),
+];
+// shortcuts that only do something in one window, shown while it is active
+static WINDOW_SHORTCUTS: [(types::WindowTypeID, &str, &str); 16] = [
+    (types::WindowTypeID::QUERY, "f", "Follow key"),
+    (types::WindowTypeID::QUERY, "F", "Referencing rows"),
+    (types::WindowTypeID::QUERY, "Backspace", "Back"),
+    (types::WindowTypeID::QUERY, "c", "Edit cell"),
+    (types::WindowTypeID::QUERY, "w", "Review changes"),
+    (types::WindowTypeID::QUERY, "i", "Insert row"),
+    (types::WindowTypeID::QUERY, "I", "Duplicate row"),
+    (types::WindowTypeID::QUERY, "d", "Delete rows"),
+    (types::WindowTypeID::QUERY, "v", "Record view"),
+    (types::WindowTypeID::QUERY, "W", "Where"),
+    (types::WindowTypeID::TABLES, "d", "DDL"),
+    (types::WindowTypeID::INDEXES, "d", "DDL"),
+    (types::WindowTypeID::COLUMNS, "d", "DDL"),
+    (types::WindowTypeID::DEFINITION, "y", "Copy"),
+    (types::WindowTypeID::PLAN, "enter", "Fold node"),
+    (types::WindowTypeID::PLAN, "a", "Analyze"),
 ];
-static COMMNDS: [&str; 7] = [
+
+static COMMNDS: [&str; 15] = [
     "connections",
     "databases",
     "tables",
@@ -46,23 +78,47 @@ static COMMNDS: [&str; 7] = [
     "columns",
     "query",
     "history",
+    "plan",
+    "views",
+    "functions",
+    "triggers",
+    "sequences",
+    "enums",
+    "export",
+    "import",
 ];
 
 impl TopArea {
-    pub fn new(app: Arc<Application>, state: Arc<RwLock<LayoutState>>) -> TopArea {
-        TopArea { app, state }
+    pub fn new(state: Arc<RwLock<LayoutState>>) -> TopArea {
+        TopArea { state }
     }
 
-    fn get_shortcuts(&self, split: usize, part: usize) -> Table {
-        // let current_window = self.state.read().unwrap().get_active_window();
-        let mut take = (SHORTCUTS.len() / split) + 1;
+    fn shortcuts(&self) -> Vec<(&'static str, &'static str)> {
+        let current_window = self.state.read().unwrap().get_active_window();
+        SHORTCUTS
+            .iter()
+            .cloned()
+            .chain(
+                WINDOW_SHORTCUTS
+                    .iter()
+                    .filter(|(window, _, _)| *window == current_window.id())
+                    .map(|(_, k, v)| (*k, *v)),
+            )
+            .collect()
+    }
+
+    /// Tall enough for the shortcuts of the window in five columns.
+    pub fn layout_size(&self) -> u16 {
+        self.shortcuts().len().div_ceil(SHORTCUT_COLUMNS).max(5) as u16
+    }
+
+    fn get_shortcuts(&self, split: usize, part: usize) -> Table<'_> {
+        let shortcuts = self.shortcuts();
+        let take = shortcuts.len().div_ceil(split);
         let skip = take * part;
-        if part == split - 1 {
-            take += SHORTCUTS.len() % split;
-        }
         Table::new(
-            SHORTCUTS
-                .iter()
+            shortcuts
+                .into_iter()
                 .skip(skip)
                 .take(take)
                 .map(|(k, v)| {
@@ -73,7 +129,7 @@ impl TopArea {
                         ))
                         .alignment(Alignment::Right),
                         Line::from(Span::styled(
-                            format!("{}", v),
+                            v.to_string(),
                             Style::default().fg(Color::Gray),
                         )),
                     ])
@@ -83,7 +139,7 @@ impl TopArea {
         .widths(&[Constraint::Percentage(30), Constraint::Percentage(70)])
     }
 
-    fn get_commands(&self, split: usize, part: usize) -> Table {
+    fn get_commands(&self, split: usize, part: usize) -> Table<'_> {
         let mut take = COMMNDS.len() / split;
         let skip = take * part;
         if part == split - 1 {
@@ -96,9 +152,9 @@ impl TopArea {
                 .take(take)
                 .map(|c| {
                     Row::new(vec![Line::from(vec![
-                        Span::styled(format!("["), Style::default().fg(Color::Yellow)),
-                        Span::styled(format!("{}", c), Style::default().fg(Color::Green)),
-                        Span::styled(format!("]"), Style::default().fg(Color::Yellow)),
+                        Span::styled("[", Style::default().fg(Color::Yellow)),
+                        Span::styled(c.to_string(), Style::default().fg(Color::Green)),
+                        Span::styled("]", Style::default().fg(Color::Yellow)),
                     ])])
                 })
                 .collect::<Vec<_>>(),
@@ -106,12 +162,17 @@ impl TopArea {
         .widths(&[Constraint::Percentage(100)])
     }
 
-    fn get_selection_table(&self) -> Table {
+    fn get_selection_table(&self) -> Table<'_> {
         let state = self.state.read().unwrap();
         let inner = state.inner.read().unwrap();
         let mut headers = Vec::new();
         let mut values = Vec::new();
-        for window_type in types::WINDOW_TYPES {
+        let transaction = inner.in_transaction();
+        if transaction {
+            headers.push("Transaction".to_string());
+            values.push("IN TRANSACTION".to_string());
+        }
+        for window_type in types::WINDOW_TYPES.iter() {
             let value = inner.get_selection(window_type.id());
             if value.is_none() {
                 continue;
@@ -122,30 +183,52 @@ impl TopArea {
             }
             if window_type.id() == types::WindowTypeID::CONNECTIONS {
                 let conns = state.get_connections();
-                let conn = conns.iter().find(|c| c.id == value[0]);
-                if conn.is_some() {
-                    value = vec![conn.unwrap().name.clone()];
+                if let Some(conn) = conns.iter().find(|c| c.id == value[0]) {
+                    value = vec![conn.name.clone()];
                 }
             }
+            // result rows are only known by their position
+            if window_type.id() == types::WindowTypeID::QUERY {
+                value = vec![format!("{} rows", value.len())];
+            }
             headers.push(window_type.title());
             values.push(value.join(","));
         }
+        if let Some(status) = inner.get_session_status() {
+            headers.push("Session".to_string());
+            values.push(status.to_string());
+        }
+        if !inner.conditions.is_empty() {
+            headers.push("Where".to_string());
+            values.push(inner.conditions.join(" AND "));
+        }
+        if !inner.staged.is_empty() {
+            headers.push("Changes".to_string());
+            values.push(format!("{} staged", inner.staged.len()));
+        }
 
         Table::new(
             headers
                 .iter()
                 .zip(values.iter())
-                .map(|(h, v)| {
+                .enumerate()
+                .map(|(idx, (h, v))| {
+                    // the open transaction is always the first row and has to stand out
+                    let value_style = if transaction && idx == 0 {
+                        Style::default()
+                            .fg(Color::White)
+                            .bg(Color::Red)
+                            .add_modifier(Modifier::BOLD)
+                    } else {
+                        Style::default().fg(Color::White)
+                    };
                     Row::new(vec![
                         Line::from(Span::styled(
                             format!("{}: ", h),
                             Style::default().fg(Color::Yellow),
                         ))
                         .alignment(Alignment::Right),
-                        Line::from(Span::styled(
-                            v.to_string(),
-                            Style::default().fg(Color::White),
-                        )),
+                        Line::from(Span::styled(v.to_string(), value_style)),
                     ])
                 })
                 .collect::<Vec<_>>(),
@@ -155,35 +238,44 @@ impl TopArea {
         .style(Style::default().fg(Color::White))
     }
 
-    pub fn render(&mut self, frame: &mut Frame, rect: Rect, event: Option<Event>) {
-        let shortcuts_table1 = self.get_shortcuts(3, 0);
-        let shortcuts_table2 = self.get_shortcuts(3, 1);
-        let shortcuts_table3 = self.get_shortcuts(3, 2);
+    pub fn render(&mut self, frame: &mut Frame, rect: Rect, _event: Option<Event>) {
+        let shortcuts_table1 = self.get_shortcuts(SHORTCUT_COLUMNS, 0);
+        let shortcuts_table2 = self.get_shortcuts(SHORTCUT_COLUMNS, 1);
+        let shortcuts_table3 = self.get_shortcuts(SHORTCUT_COLUMNS, 2);
+        let shortcuts_table4 = self.get_shortcuts(SHORTCUT_COLUMNS, 3);
+        let shortcuts_table5 = self.get_shortcuts(SHORTCUT_COLUMNS, 4);
 
-        let commands_table1 = self.get_commands(2, 0);
-        let commands_table2 = self.get_commands(2, 1);
+        let commands_table1 = self.get_commands(3, 0);
+        let commands_table2 = self.get_commands(3, 1);
+        let commands_table3 = self.get_commands(3, 2);
 
         let selection_table = self.get_selection_table();
 
         let areas = Layout::default()
             .direction(Direction::Horizontal)
             .constraints([
-                Constraint::Percentage(25),
+                Constraint::Percentage(22),
+                Constraint::Percentage(8),
+                Constraint::Percentage(8),
+                Constraint::Percentage(8),
+                Constraint::Percentage(4),
+                Constraint::Percentage(10),
+                Constraint::Percentage(10),
+                Constraint::Percentage(10),
                 Constraint::Percentage(10),
                 Constraint::Percentage(10),
-                Constraint::Percentage(5),
-                Constraint::Percentage(15),
-                Constraint::Percentage(15),
-                Constraint::Percentage(15),
             ])
             .split(rect);
 
         frame.render_widget(selection_table, areas[0]);
         frame.render_widget(commands_table1, areas[1]);
         frame.render_widget(commands_table2, areas[2]);
+        frame.render_widget(commands_table3, areas[3]);
         // leave out spacer
-        frame.render_widget(shortcuts_table1, areas[4]);
-        frame.render_widget(shortcuts_table2, areas[5]);
-        frame.render_widget(shortcuts_table3, areas[6]);
+        frame.render_widget(shortcuts_table1, areas[5]);
+        frame.render_widget(shortcuts_table2, areas[6]);
+        frame.render_widget(shortcuts_table3, areas[7]);
+        frame.render_widget(shortcuts_table4, areas[8]);
+        frame.render_widget(shortcuts_table5, areas[9]);
     }
 }
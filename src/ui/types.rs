@@ -22,6 +22,13 @@ pub enum WindowTypeID {
     QUERY,
     HISTORY,
     INDEXES,
+    PLAN,
+    VIEWS,
+    FUNCTIONS,
+    TRIGGERS,
+    SEQUENCES,
+    ENUMS,
+    DEFINITION,
 }
 
 #[derive(Clone, Debug)]
@@ -64,26 +71,26 @@ impl WindowType {
     ) -> Self {
         WindowType {
             id,
-            title: title,
+            title,
             selection_type,
             clears,
         }
     }
     pub fn id(&self) -> WindowTypeID {
-        self.id.clone()
+        self.id
     }
     pub fn title(&self) -> String {
         self.title.clone()
     }
     pub fn selection_type(&self) -> ItemSelectionType {
-        self.selection_type.clone()
+        self.selection_type
     }
     pub fn clears(&self) -> Vec<WindowTypeID> {
         self.clears.clone()
     }
 }
 
-pub const WINDOW_ORDER: Lazy<HashMap<WindowTypeID, WindowTypeID>> = Lazy::new(|| {
+pub static WINDOW_ORDER: Lazy<HashMap<WindowTypeID, WindowTypeID>> = Lazy::new(|| {
     let mut map = HashMap::new();
     map.insert(WindowTypeID::CONNECTIONS, WindowTypeID::TABLES);
     map.insert(WindowTypeID::DATABASES, WindowTypeID::TABLES);
@@ -92,10 +99,15 @@ pub const WINDOW_ORDER: Lazy<HashMap<WindowTypeID, WindowTypeID>> = Lazy::new(||
     map.insert(WindowTypeID::COLUMNS, WindowTypeID::QUERY);
     map.insert(WindowTypeID::HISTORY, WindowTypeID::QUERY);
     map.insert(WindowTypeID::INDEXES, WindowTypeID::QUERY);
+    map.insert(WindowTypeID::VIEWS, WindowTypeID::DEFINITION);
+    map.insert(WindowTypeID::FUNCTIONS, WindowTypeID::DEFINITION);
+    map.insert(WindowTypeID::TRIGGERS, WindowTypeID::DEFINITION);
+    map.insert(WindowTypeID::SEQUENCES, WindowTypeID::DEFINITION);
+    map.insert(WindowTypeID::ENUMS, WindowTypeID::DEFINITION);
     map
 });
 
-pub const CONNECTION_LIST: Lazy<WindowType> = Lazy::new(|| {
+pub static CONNECTION_LIST: Lazy<WindowType> = Lazy::new(|| {
     WindowType::new(
         WindowTypeID::CONNECTIONS,
         "Connections".to_string(),
@@ -108,7 +120,7 @@ pub const CONNECTION_LIST: Lazy<WindowType> = Lazy::new(|| {
         ],
     )
 });
-pub const TABLE_LIST: Lazy<WindowType> = Lazy::new(|| {
+pub static TABLE_LIST: Lazy<WindowType> = Lazy::new(|| {
     WindowType::new(
         WindowTypeID::TABLES,
         "Tables".to_string(),
@@ -116,15 +128,15 @@ pub const TABLE_LIST: Lazy<WindowType> = Lazy::new(|| {
         vec![WindowTypeID::QUERY, WindowTypeID::COLUMNS],
     )
 });
-pub const QUERY: Lazy<WindowType> = Lazy::new(|| {
+pub static QUERY: Lazy<WindowType> = Lazy::new(|| {
     WindowType::new(
         WindowTypeID::QUERY,
         "Query".to_string(),
-        ItemSelectionType::NONE,
+        ItemSelectionType::MULTI,
         vec![],
     )
 });
-pub const SCHEMA_LIST: Lazy<WindowType> = Lazy::new(|| {
+pub static SCHEMA_LIST: Lazy<WindowType> = Lazy::new(|| {
     WindowType::new(
         WindowTypeID::SCHEMAS,
         "Schemas".to_string(),
@@ -132,7 +144,7 @@ pub const SCHEMA_LIST: Lazy<WindowType> = Lazy::new(|| {
         vec![WindowTypeID::TABLES, WindowTypeID::COLUMNS],
     )
 });
-pub const DATABASE_LIST: Lazy<WindowType> = Lazy::new(|| {
+pub static DATABASE_LIST: Lazy<WindowType> = Lazy::new(|| {
     WindowType::new(
         WindowTypeID::DATABASES,
         "Databases".to_string(),
@@ -144,7 +156,7 @@ pub const DATABASE_LIST: Lazy<WindowType> = Lazy::new(|| {
         ],
     )
 });
-pub const COLUMN_LIST: Lazy<WindowType> = Lazy::new(|| {
+pub static COLUMN_LIST: Lazy<WindowType> = Lazy::new(|| {
     WindowType::new(
         WindowTypeID::COLUMNS,
         "Columns".to_string(),
@@ -152,7 +164,7 @@ pub const COLUMN_LIST: Lazy<WindowType> = Lazy::new(|| {
         vec![],
     )
 });
-pub const HISTORY: Lazy<WindowType> = Lazy::new(|| {
+pub static HISTORY: Lazy<WindowType> = Lazy::new(|| {
     WindowType::new(
         WindowTypeID::HISTORY,
         "Query History".to_string(),
@@ -160,7 +172,7 @@ pub const HISTORY: Lazy<WindowType> = Lazy::new(|| {
         vec![],
     )
 });
-pub const INDEXES: Lazy<WindowType> = Lazy::new(|| {
+pub static INDEXES: Lazy<WindowType> = Lazy::new(|| {
     WindowType::new(
         WindowTypeID::INDEXES,
         "Indexes".to_string(),
@@ -168,22 +180,87 @@ pub const INDEXES: Lazy<WindowType> = Lazy::new(|| {
         vec![],
     )
 });
+pub static PLAN: Lazy<WindowType> = Lazy::new(|| {
+    WindowType::new(
+        WindowTypeID::PLAN,
+        "Query Plan".to_string(),
+        ItemSelectionType::NONE,
+        vec![],
+    )
+});
+pub static VIEW_LIST: Lazy<WindowType> = Lazy::new(|| {
+    WindowType::new(
+        WindowTypeID::VIEWS,
+        "Views".to_string(),
+        ItemSelectionType::SINGLE,
+        vec![],
+    )
+});
+pub static FUNCTION_LIST: Lazy<WindowType> = Lazy::new(|| {
+    WindowType::new(
+        WindowTypeID::FUNCTIONS,
+        "Functions".to_string(),
+        ItemSelectionType::SINGLE,
+        vec![],
+    )
+});
+pub static TRIGGER_LIST: Lazy<WindowType> = Lazy::new(|| {
+    WindowType::new(
+        WindowTypeID::TRIGGERS,
+        "Triggers".to_string(),
+        ItemSelectionType::SINGLE,
+        vec![],
+    )
+});
+pub static SEQUENCE_LIST: Lazy<WindowType> = Lazy::new(|| {
+    WindowType::new(
+        WindowTypeID::SEQUENCES,
+        "Sequences".to_string(),
+        ItemSelectionType::SINGLE,
+        vec![],
+    )
+});
+pub static ENUM_LIST: Lazy<WindowType> = Lazy::new(|| {
+    WindowType::new(
+        WindowTypeID::ENUMS,
+        "Enums".to_string(),
+        ItemSelectionType::SINGLE,
+        vec![],
+    )
+});
+pub static DEFINITION: Lazy<WindowType> = Lazy::new(|| {
+    WindowType::new(
+        WindowTypeID::DEFINITION,
+        "Definition".to_string(),
+        ItemSelectionType::NONE,
+        vec![],
+    )
+});
 
-pub const WINDOW_TYPES: [Lazy<WindowType>; 8] = [
-    CONNECTION_LIST,
-    TABLE_LIST,
-    QUERY,
-    SCHEMA_LIST,
-    DATABASE_LIST,
-    COLUMN_LIST,
-    HISTORY,
-    INDEXES,
-];
+pub static WINDOW_TYPES: Lazy<Vec<WindowType>> = Lazy::new(|| {
+    vec![
+        (&CONNECTION_LIST).into(),
+        (&TABLE_LIST).into(),
+        (&QUERY).into(),
+        (&SCHEMA_LIST).into(),
+        (&DATABASE_LIST).into(),
+        (&COLUMN_LIST).into(),
+        (&HISTORY).into(),
+        (&INDEXES).into(),
+        (&PLAN).into(),
+        (&VIEW_LIST).into(),
+        (&FUNCTION_LIST).into(),
+        (&TRIGGER_LIST).into(),
+        (&SEQUENCE_LIST).into(),
+        (&ENUM_LIST).into(),
+        (&DEFINITION).into(),
+    ]
+});
 
 pub fn get_window(id: WindowTypeID) -> WindowType {
     for window_type in WINDOW_TYPES.iter() {
         if window_type.id() == id {
-            return window_type.into();
+            return window_type.clone();
         }
     }
     panic!("No window type found for id: {:?}", id);